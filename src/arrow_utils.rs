@@ -1,24 +1,25 @@
 /// Shared utilities for arrow and arrowhead rendering.This module provides common logic used by both SVG and Skia renderers
-use crate::math_utils::catmull_rom_cubics;
+use crate::math_utils::{catmull_rom_cubics, distance};
+use crate::models::{Arrowhead, ExcalidrawElement};
 
 /// Get arrowhead size based on arrowhead type (in Excalidraw units)
-pub fn get_arrowhead_size(arrowhead: &str) -> f64 {
+pub fn get_arrowhead_size(arrowhead: Arrowhead) -> f64 {
     match arrowhead {
-        "arrow" => 25.0,
-        "diamond" | "diamond_outline" => 12.0,
-        "crowfoot_many" | "crowfoot_one" | "crowfoot_one_or_many" => 20.0,
-        "dot" | "circle" | "circle_outline" => 15.0,
-        "bar" => 15.0,
-        "triangle" | "triangle_outline" => 15.0,
-        _ => 15.0,
+        Arrowhead::Arrow => 25.0,
+        Arrowhead::Diamond | Arrowhead::DiamondOutline => 12.0,
+        Arrowhead::CrowfootMany | Arrowhead::CrowfootOne | Arrowhead::CrowfootOneOrMany => 20.0,
+        Arrowhead::Dot | Arrowhead::Circle | Arrowhead::CircleOutline => 15.0,
+        Arrowhead::Bar => 15.0,
+        Arrowhead::Triangle | Arrowhead::TriangleOutline => 15.0,
+        Arrowhead::Unknown => 15.0,
     }
 }
 
 /// Get arrowhead angle in degrees based on arrowhead type
-pub fn get_arrowhead_angle(arrowhead: &str) -> f64 {
+pub fn get_arrowhead_angle(arrowhead: Arrowhead) -> f64 {
     match arrowhead {
-        "bar" => 90.0,
-        "arrow" => 20.0,
+        Arrowhead::Bar => 90.0,
+        Arrowhead::Arrow => 20.0,
         _ => 25.0,
     }
 }
@@ -62,7 +63,7 @@ pub fn calc_arrowhead_points<T>(
     y_tail: T,
     x_tip: T,
     y_tip: T,
-    arrowhead: &str,
+    arrowhead: Arrowhead,
     stroke_width: T,
     segment_length: T,
 ) -> Vec<T>
@@ -88,7 +89,7 @@ where
     let size_multiplier = T::one() + (stroke_width - T::one()) * T::from(0.3).unwrap();
     
     // Scale down for short segments
-    let length_mult = if arrowhead == "diamond" || arrowhead == "diamond_outline" {
+    let length_mult = if matches!(arrowhead, Arrowhead::Diamond | Arrowhead::DiamondOutline) {
         T::from(0.25).unwrap()
     } else {
         T::from(0.5).unwrap()
@@ -100,12 +101,12 @@ where
     let ys = y_tip - ny * min_size;
 
     match arrowhead {
-        "dot" | "circle" | "circle_outline" => {
+        Arrowhead::Dot | Arrowhead::Circle | Arrowhead::CircleOutline => {
             // Return [center_x, center_y, diameter]
             let diameter = ((ys - y_tip).powi(2) + (xs - x_tip).powi(2)).sqrt() + stroke_width - T::from(2.0).unwrap();
             vec![x_tip, y_tip, diameter]
         }
-        "bar" => {
+        Arrowhead::Bar => {
             // Perpendicular bar
             let angle = T::from(get_arrowhead_angle(arrowhead)).unwrap().to_radians();
             let cos_a = angle.cos();
@@ -116,7 +117,7 @@ where
             let y4 = ys + (nx * cos_a + ny * sin_a) * min_size;
             vec![x3, y3, x4, y4]
         }
-        "arrow" => {
+        Arrowhead::Arrow => {
             // Open arrow (two lines)
             let angle = T::from(get_arrowhead_angle(arrowhead)).unwrap().to_radians();
             let cos_a = angle.cos();
@@ -128,7 +129,7 @@ where
             let y4 = y_tip + (-ny * cos_a - nx * sin_a) * min_size;
             vec![x_tip, y_tip, x3, y3, x4, y4]
         }
-        "triangle" | "triangle_outline" => {
+        Arrowhead::Triangle | Arrowhead::TriangleOutline => {
             let angle = T::from(get_arrowhead_angle(arrowhead)).unwrap().to_radians();
             let cos_a = angle.cos();
             let sin_a = angle.sin();
@@ -138,7 +139,7 @@ where
             let y4 = ys + (nx * cos_a + ny * sin_a) * min_size;
             vec![x_tip, y_tip, x3, y3, x4, y4]
         }
-        "diamond" | "diamond_outline" => {
+        Arrowhead::Diamond | Arrowhead::DiamondOutline => {
             let angle = T::from(get_arrowhead_angle(arrowhead)).unwrap().to_radians();
             let cos_a = angle.cos();
             let sin_a = angle.sin();
@@ -151,21 +152,21 @@ where
             let oy = y_tip - ny * min_size * T::from(2.0).unwrap();
             vec![x_tip, y_tip, x3, y3, ox, oy, x4, y4]
         }
-        "crowfoot_many" | "crowfoot_one_or_many" => {
+        Arrowhead::CrowfootMany | Arrowhead::CrowfootOneOrMany => {
             // swap (xs,ys) with (x_tip,y_tip) and rotate around (xs,ys)
             let angle = T::from(get_arrowhead_angle(arrowhead)).unwrap().to_radians();
             let (x3, y3) = rotate_point(x_tip, y_tip, xs, ys, -angle);
             let (x4, y4) = rotate_point(x_tip, y_tip, xs, ys, angle);
             vec![xs, ys, x3, y3, x4, y4]
         }
-        "crowfoot_one" => {
+        Arrowhead::CrowfootOne => {
             // Similar to crowfoot_many but different rendering
             let angle = T::from(get_arrowhead_angle(arrowhead)).unwrap().to_radians();
             let (x3, y3) = rotate_point(x_tip, y_tip, xs, ys, -angle);
             let (x4, y4) = rotate_point(x_tip, y_tip, xs, ys, angle);
             vec![xs, ys, x3, y3, x4, y4]
         }
-        _ => vec![],
+        Arrowhead::Unknown => vec![],
     }
 }
 
@@ -336,3 +337,49 @@ where
     Some((tail_x, tail_y, tip_x, tip_y, seg_len))
 }
 
+
+/// Whether a line or arrow is drawn as a smooth curve rather than straight segments
+/// Elbow arrows are always straight; other linear elements curve when they have roundness.
+pub fn is_curved_linear(element: &ExcalidrawElement) -> bool {
+    !element.elbowed.unwrap_or(false) && element.roundness.is_some()
+}
+
+/// Polyline following the path a line or arrow is drawn with, in absolute coordinates
+/// Curved paths are sampled along their Catmull-Rom cubics.
+pub fn get_linear_path_polyline(element: &ExcalidrawElement) -> Vec<(f64, f64)> {
+    const SAMPLES_PER_SEGMENT: usize = 16;
+
+    let Some(ref points) = element.points else {
+        return vec![];
+    };
+    let abs_points: Vec<(f64, f64)> = points.iter().map(|(px, py)| (element.x + px, element.y + py)).collect();
+    if !is_curved_linear(element) || abs_points.len() < 3 {
+        return abs_points;
+    }
+
+    let mut polyline = vec![abs_points[0]];
+    for (p0, cp1, cp2, p1) in catmull_rom_cubics(&abs_points, 0.5) {
+        for i in 1..=SAMPLES_PER_SEGMENT {
+            polyline.push(cubic_point(p0, cp1, cp2, p1, i as f64 / SAMPLES_PER_SEGMENT as f64));
+        }
+    }
+    polyline
+}
+
+/// Point halfway along a polyline's length
+pub fn get_polyline_midpoint(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let first = *points.first()?;
+    let lengths: Vec<f64> = points.windows(2).map(|w| distance(w[0], w[1])).collect();
+    let mut remaining = lengths.iter().sum::<f64>() / 2.0;
+    for (segment, length) in points.windows(2).zip(lengths) {
+        if remaining <= length && length > 0.0 {
+            let t = remaining / length;
+            return Some((
+                segment[0].0 + (segment[1].0 - segment[0].0) * t,
+                segment[0].1 + (segment[1].1 - segment[0].1) * t,
+            ));
+        }
+        remaining -= length;
+    }
+    Some(first)
+}
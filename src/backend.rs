@@ -0,0 +1,110 @@
+//! A common interface over the rendering backends
+//! Every backend renders a scene with the same [`RenderOptions`], so callers can switch
+//! between SVG, tiny-skia, the legacy resvg pipeline and PDF without changing their calls.
+use crate::converter::convert_scene_to_pixmap;
+use crate::encoder::{encode_pixmap, RasterFormat};
+use crate::models::ExcalidrawData;
+use crate::options::RenderOptions;
+use crate::renderer::generate_svg_with_options;
+use crate::renderer_pdf::render_to_pdf_bytes;
+use crate::renderer_skia::{render_to_pixmap, render_to_raster_bytes};
+use crate::error::{ExcaliosaError, Result};
+use std::io::Write;
+use std::path::Path;
+use tiny_skia::Pixmap;
+
+/// A backend that renders scenes
+pub trait Renderer {
+    /// What the backend renders to before encoding: SVG markup, a pixmap or a document
+    type Output;
+
+    /// Render a scene
+    fn render(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<Self::Output>;
+
+    /// Render a scene and encode it as file contents
+    fn render_bytes(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<Vec<u8>>;
+
+    /// Render a scene into any writer
+    fn render_to_writer(&self, data: &ExcalidrawData, writer: &mut dyn Write, options: &RenderOptions) -> Result<()> {
+        let bytes = self.render_bytes(data, options)?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Render a scene to a file
+    fn render_to_file(&self, data: &ExcalidrawData, output_path: &Path, options: &RenderOptions) -> Result<()> {
+        let bytes = self.render_bytes(data, options)?;
+        std::fs::write(output_path, bytes).map_err(|source| ExcaliosaError::Write { path: output_path.to_path_buf(), source })
+    }
+}
+
+/// SVG markup from the SVG renderer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SvgRenderer;
+
+impl Renderer for SvgRenderer {
+    type Output = String;
+
+    fn render(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<String> {
+        generate_svg_with_options(data, options)
+    }
+
+    fn render_bytes(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<Vec<u8>> {
+        Ok(self.render(data, options)?.into_bytes())
+    }
+}
+
+/// Raster images from the tiny-skia renderer, encoded as `format`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SkiaRenderer {
+    pub format: RasterFormat,
+}
+
+impl Renderer for SkiaRenderer {
+    type Output = Pixmap;
+
+    fn render(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<Pixmap> {
+        render_to_pixmap(data, options)
+    }
+
+    fn render_bytes(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<Vec<u8>> {
+        render_to_raster_bytes(data, self.format, options)
+    }
+}
+
+/// Raster images from the legacy pipeline, the SVG output rasterized by resvg, encoded as
+/// `format`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResvgRenderer {
+    pub format: RasterFormat,
+}
+
+impl Renderer for ResvgRenderer {
+    type Output = Pixmap;
+
+    fn render(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<Pixmap> {
+        convert_scene_to_pixmap(data, options)
+    }
+
+    fn render_bytes(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<Vec<u8>> {
+        let pixmap = self.render(data, options)?;
+        let embed_scene = options.resolve_embedded_scene(data)?;
+        encode_pixmap(&pixmap, self.format, options.quality, options.resolve_matte(data), embed_scene.as_ref())
+    }
+}
+
+/// PDF documents, one page per scene or per frame
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PdfRenderer;
+
+impl Renderer for PdfRenderer {
+    type Output = Vec<u8>;
+
+    fn render(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<Vec<u8>> {
+        render_to_pdf_bytes(data, options)
+    }
+
+    fn render_bytes(&self, data: &ExcalidrawData, options: &RenderOptions) -> Result<Vec<u8>> {
+        self.render(data, options)
+    }
+}
@@ -0,0 +1,150 @@
+//! Bound text: positioning text elements inside their containers
+//! Mirrors Excalidraw's `computeBoundTextPosition` and container text box rules.
+use crate::arrow_utils::{get_linear_path_polyline, get_polyline_midpoint};
+use crate::font_utils::get_line_height;
+use crate::models::{ElementType, ExcalidrawElement, TextAlign, VerticalAlign};
+use std::collections::HashMap;
+
+/// Padding between a container's edge and its bound text
+pub const BOUND_TEXT_PADDING: f64 = 5.0;
+
+/// Arrow labels may use this fraction of the arrow's width
+pub const ARROW_LABEL_WIDTH_FRACTION: f64 = 0.7;
+
+/// Minimum arrow label width, as a multiple of the font size
+pub const ARROW_LABEL_FONT_SIZE_TO_MIN_WIDTH_RATIO: f64 = 11.0;
+
+/// Whether the element type can hold bound text
+pub fn is_text_container(element: &ExcalidrawElement) -> bool {
+    matches!(
+        element.element_type,
+        ElementType::Rectangle | ElementType::Ellipse | ElementType::Diamond | ElementType::Arrow
+    )
+}
+
+/// Live container of a bound text element, if any
+pub fn get_container<'a>(
+    text: &ExcalidrawElement,
+    elements_by_id: &HashMap<&str, &'a ExcalidrawElement>,
+) -> Option<&'a ExcalidrawElement> {
+    text.container_id
+        .as_deref()
+        .and_then(|id| elements_by_id.get(id).copied())
+        .filter(|container| !container.is_deleted && is_text_container(container))
+}
+
+/// Top-left corner of the container's text box
+/// Ellipses and diamonds inset the box so it stays inside the shape.
+pub fn get_container_coords(container: &ExcalidrawElement) -> (f64, f64) {
+    let mut offset_x = BOUND_TEXT_PADDING;
+    let mut offset_y = BOUND_TEXT_PADDING;
+    match container.element_type {
+        ElementType::Ellipse => {
+            offset_x += (container.width / 2.0) * (1.0 - std::f64::consts::SQRT_2 / 2.0);
+            offset_y += (container.height / 2.0) * (1.0 - std::f64::consts::SQRT_2 / 2.0);
+        }
+        ElementType::Diamond => {
+            offset_x += container.width / 4.0;
+            offset_y += container.height / 4.0;
+        }
+        _ => {}
+    }
+    (container.x + offset_x, container.y + offset_y)
+}
+
+/// Width available to bound text inside the container
+pub fn get_bound_text_max_width(container: &ExcalidrawElement, font_size: f64) -> f64 {
+    let width = container.width;
+    match container.element_type {
+        ElementType::Arrow => (width * ARROW_LABEL_WIDTH_FRACTION).max(font_size * ARROW_LABEL_FONT_SIZE_TO_MIN_WIDTH_RATIO),
+        ElementType::Ellipse => ((width / 2.0) * std::f64::consts::SQRT_2).round() - BOUND_TEXT_PADDING * 2.0,
+        ElementType::Diamond => (width / 2.0).round() - BOUND_TEXT_PADDING * 2.0,
+        _ => width - BOUND_TEXT_PADDING * 2.0,
+    }
+}
+
+/// Height available to bound text inside the container
+pub fn get_bound_text_max_height(container: &ExcalidrawElement, text_height: f64) -> f64 {
+    let height = container.height;
+    match container.element_type {
+        ElementType::Arrow => {
+            if height - BOUND_TEXT_PADDING * 8.0 * 2.0 <= 0.0 {
+                text_height
+            } else {
+                height
+            }
+        }
+        ElementType::Ellipse => ((height / 2.0) * std::f64::consts::SQRT_2).round() - BOUND_TEXT_PADDING * 2.0,
+        ElementType::Diamond => (height / 2.0).round() - BOUND_TEXT_PADDING * 2.0,
+        _ => height - BOUND_TEXT_PADDING * 2.0,
+    }
+}
+
+/// Height of a text element's box: the stored height, or its line count times the line height
+pub fn get_text_height(text: &ExcalidrawElement) -> f64 {
+    if text.height > 0.0 {
+        return text.height;
+    }
+    let font_size = text.font_size.unwrap_or(20.0);
+    let line_count = text.text.as_deref().unwrap_or("").split('\n').count().max(1);
+    line_count as f64 * get_line_height(font_size, text.line_height)
+}
+
+/// Label anchor of an arrow: the point halfway along its rendered path
+pub fn get_arrow_label_midpoint(arrow: &ExcalidrawElement) -> Option<(f64, f64)> {
+    get_polyline_midpoint(&get_linear_path_polyline(arrow))
+}
+
+/// Live text element bound to a container through its `boundElements`
+pub fn get_bound_text<'a>(
+    container: &ExcalidrawElement,
+    elements_by_id: &HashMap<&str, &'a ExcalidrawElement>,
+) -> Option<&'a ExcalidrawElement> {
+    container
+        .bound_elements
+        .as_ref()?
+        .iter()
+        .filter(|bound| bound.element_type == ElementType::Text)
+        .filter_map(|bound| elements_by_id.get(bound.id.as_str()).copied())
+        .find(|text| {
+            !text.is_deleted
+                && text.container_id.as_deref() == Some(container.id.as_str())
+                && !text.text.as_deref().unwrap_or("").is_empty()
+        })
+}
+
+/// Top-left corner of bound text with the given box size inside its container
+pub fn compute_bound_text_position(
+    container: &ExcalidrawElement,
+    text: &ExcalidrawElement,
+    text_width: f64,
+    text_height: f64,
+) -> (f64, f64) {
+    // Arrow labels sit centered halfway along the arrow's path
+    if container.element_type == ElementType::Arrow {
+        let (mx, my) = get_arrow_label_midpoint(container).unwrap_or((container.x, container.y));
+        return (mx - text_width / 2.0, my - text_height / 2.0);
+    }
+
+    let (container_x, container_y) = get_container_coords(container);
+    let font_size = text.font_size.unwrap_or(20.0);
+    let max_width = get_bound_text_max_width(container, font_size);
+    let max_height = get_bound_text_max_height(container, text_height);
+
+    let y = match text.vertical_align {
+        Some(VerticalAlign::Top) => container_y,
+        Some(VerticalAlign::Bottom) => container_y + (max_height - text_height),
+        _ => container_y + (max_height / 2.0 - text_height / 2.0),
+    };
+    let x = match text.text_align {
+        Some(TextAlign::Left) => container_x,
+        Some(TextAlign::Right) => container_x + (max_width - text_width),
+        _ => container_x + (max_width / 2.0 - text_width / 2.0),
+    };
+    (x, y)
+}
+
+/// Index of all elements by id, for resolving containers
+pub fn index_elements(elements: &[ExcalidrawElement]) -> HashMap<&str, &ExcalidrawElement> {
+    elements.iter().map(|el| (el.id.as_str(), el)).collect()
+}
@@ -1,5 +1,6 @@
 /// Color parsing and element color/stroke detection utilities
 use crate::models::ExcalidrawElement;
+use crate::options::Theme;
 
 /// Parse a hex color string into RGBA components
 /// Accepts:
@@ -120,3 +121,50 @@ pub fn has_fill(element: &ExcalidrawElement) -> bool {
         && !is_transparent(&element.background_color)
 }
 
+/// Apply Excalidraw's dark export filter, `invert(93%) hue-rotate(180deg)`, to an RGBA color
+/// Alpha is kept as is.
+pub fn apply_dark_mode_filter(rgba: (u8, u8, u8, u8)) -> (u8, u8, u8, u8) {
+    const INVERT: f64 = 0.93;
+    // CSS hue-rotate matrix at 180 degrees (cos = -1, sin = 0)
+    const HUE_ROTATE_180: [[f64; 3]; 3] = [
+        [-0.574, 1.430, 0.144],
+        [0.426, 0.430, 0.144],
+        [0.426, 1.430, -0.856],
+    ];
+
+    let (r, g, b, a) = rgba;
+    let inverted = [r, g, b].map(|c| INVERT + (c as f64 / 255.0) * (1.0 - 2.0 * INVERT));
+    let [r, g, b] = HUE_ROTATE_180.map(|row| {
+        let c = row[0] * inverted[0] + row[1] * inverted[1] + row[2] * inverted[2];
+        (c.clamp(0.0, 1.0) * 255.0).round() as u8
+    });
+    (r, g, b, a)
+}
+
+/// RGBA color as drawn in the given theme
+pub fn get_themed_rgba(rgba: (u8, u8, u8, u8), theme: Theme) -> (u8, u8, u8, u8) {
+    match theme {
+        Theme::Light => rgba,
+        Theme::Dark => apply_dark_mode_filter(rgba),
+    }
+}
+
+/// Color string as drawn in the given theme
+/// Keywords such as "transparent" and unparseable colors are returned unchanged.
+pub fn get_themed_color(color_str: &str, theme: Theme) -> String {
+    if theme == Theme::Light {
+        return color_str.to_string();
+    }
+    match parse_color_result(color_str) {
+        Ok((_, _, _, 0)) | Err(_) => color_str.to_string(),
+        Ok(rgba) => {
+            let (r, g, b, a) = apply_dark_mode_filter(rgba);
+            if a == 255 {
+                format!("#{r:02x}{g:02x}{b:02x}")
+            } else {
+                format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+            }
+        }
+    }
+}
+
@@ -1,8 +1,13 @@
-use anyhow::Result;
 use resvg::usvg::{self, Tree};
 use std::path::Path;
 use tiny_skia::Pixmap;
-use crate::utils::save_png_with_quality;
+use crate::color_utils::get_themed_rgba;
+use crate::error::{ExcaliosaError, Result};
+use crate::fonts::{FontProvider, FontSet};
+use crate::models::ExcalidrawData;
+use crate::options::RenderOptions;
+use crate::renderer::generate_svg_with_options;
+use crate::utils::{encode_png_with_quality, save_png_with_quality};
 
 // Include fonts as bytes
 pub const EXCALIFONT_REGULAR: &[u8] = include_bytes!("../fonts/Excalifont-Regular.ttf");
@@ -11,22 +16,78 @@ pub const LIBERATION_SANS_BOLD: &[u8] = include_bytes!("../fonts/LiberationSans-
 pub const CASCADIA_CODE: &[u8] = include_bytes!("../fonts/CascadiaCode.ttf");
 
 pub fn convert_svg_to_png(svg_content: &str, output_path: &Path, background: Option<(u8,u8,u8,u8)>, quality: u8, dpi: Option<u32>) -> Result<()> {
-    // Prepare usvg options and load embedded fonts into its font database
+    let pixmap = convert_svg_to_pixmap(svg_content, background, dpi)?;
+
+    // Save as PNG with quality control
+    save_png_with_quality(&pixmap, output_path, quality, None)?;
+
+    Ok(())
+}
+
+/// Rasterize an SVG with resvg and encode it as PNG bytes
+pub fn convert_svg_to_png_bytes(svg_content: &str, background: Option<(u8,u8,u8,u8)>, quality: u8, dpi: Option<u32>) -> Result<Vec<u8>> {
+    let pixmap = convert_svg_to_pixmap(svg_content, background, dpi)?;
+    encode_png_with_quality(&pixmap, quality, None)
+}
+
+/// Render a scene through the legacy pipeline: the SVG renderer's output rasterized by resvg
+pub fn convert_scene_to_pixmap(data: &ExcalidrawData, options: &RenderOptions) -> Result<Pixmap> {
+    // Avoid double background: rasterizer will fill background; keep SVG transparent
+    let svg_options = RenderOptions {
+        background: Some((0, 0, 0, 0)),
+        embed_scene: false,
+        ..options.clone()
+    };
+    let svg_content = generate_svg_with_options(data, &svg_options)?;
+    let background = get_themed_rgba(
+        options.resolve_background(data).unwrap_or((255, 255, 255, 255)),
+        options.resolve_theme(data),
+    );
+    let scale = options.resolve_scale(data) as f32;
+    rasterize_svg(&svg_content, Some(background), scale, options.fonts.as_ref())
+}
+
+/// Rasterize an SVG with resvg onto the background (white if None), with the embedded fonts
+pub fn convert_svg_to_pixmap(svg_content: &str, background: Option<(u8,u8,u8,u8)>, dpi: Option<u32>) -> Result<Pixmap> {
+    convert_svg_to_pixmap_with_fonts(svg_content, background, dpi, &FontSet::default())
+}
+
+/// Rasterize an SVG with resvg onto the background (white if None), resolving its
+/// `font-family` attributes against the provider's fonts
+pub fn convert_svg_to_pixmap_with_fonts(
+    svg_content: &str,
+    background: Option<(u8,u8,u8,u8)>,
+    dpi: Option<u32>,
+    fonts: &dyn FontProvider,
+) -> Result<Pixmap> {
+    // Calculate scale factor from DPI (assume source is 96 DPI)
+    const SOURCE_DPI: f32 = 96.0;
+    let scale = dpi.map(|d| d as f32 / SOURCE_DPI).unwrap_or(1.0);
+    rasterize_svg(svg_content, background, scale, fonts)
+}
+
+/// Rasterize an SVG with resvg at the given scale onto the background (white if None)
+fn rasterize_svg(
+    svg_content: &str,
+    background: Option<(u8,u8,u8,u8)>,
+    scale: f32,
+    fonts: &dyn FontProvider,
+) -> Result<Pixmap> {
+    // Prepare usvg options and load the provider's fonts into its font database
     let mut options = usvg::Options::default();
     // Build a font database and then assign it to options (options.fontdb is Arc)
     let mut fontdb = fontdb::Database::new();
-    fontdb.load_font_data(EXCALIFONT_REGULAR.to_vec());
-    fontdb.load_font_data(LIBERATION_SANS_REGULAR.to_vec());
-    fontdb.load_font_data(LIBERATION_SANS_BOLD.to_vec());
-    fontdb.load_font_data(CASCADIA_CODE.to_vec());
+    for font_data in fonts.fonts() {
+        fontdb.load_font_source(fontdb::Source::Binary(font_data));
+    }
+    if fonts.uses_system_fonts() {
+        fontdb.load_system_fonts();
+    }
     options.fontdb = std::sync::Arc::new(fontdb);
 
     // Parse SVG
-    let tree = Tree::from_str(svg_content, &options)?;
-
-    // Calculate scale factor from DPI (assume source is 96 DPI)
-    const SOURCE_DPI: f32 = 96.0;
-    let scale = dpi.map(|d| d as f32 / SOURCE_DPI).unwrap_or(1.0);
+    let tree = Tree::from_str(svg_content, &options)
+        .map_err(|e| ExcaliosaError::Parse(format!("Invalid SVG: {e}")))?;
 
     // Get dimensions from SVG viewBox or use default
     let size = tree.size();
@@ -39,20 +100,18 @@ pub fn convert_svg_to_png(svg_content: &str, output_path: &Path, background: Opt
 
     // Create pixmap
     let mut pixmap = Pixmap::new(width, height)
-        .ok_or_else(|| anyhow::anyhow!("Failed to create pixmap"))?;
+        .ok_or(ExcaliosaError::DimensionTooLarge { width, height })?;
 
     // Fill with background (default white if None)
-    if let Some((r,g,b,a)) = background.or(Some((255,255,255,255))) {
-        if a > 0 {
-            let mut paint = tiny_skia::Paint::default();
-            paint.set_color_rgba8(r,g,b,a);
-            pixmap.fill_rect(
-                tiny_skia::Rect::from_xywh(0.0, 0.0, width as f32, height as f32).unwrap(),
-                &paint,
-                tiny_skia::Transform::identity(),
-                None,
-            );
-        }
+    if let Some((r,g,b,a)) = background.or(Some((255,255,255,255))) && a > 0 {
+        let mut paint = tiny_skia::Paint::default();
+        paint.set_color_rgba8(r,g,b,a);
+        pixmap.fill_rect(
+            tiny_skia::Rect::from_xywh(0.0, 0.0, width as f32, height as f32).unwrap(),
+            &paint,
+            tiny_skia::Transform::identity(),
+            None,
+        );
     }
 
     // Render SVG to pixmap with scaling transform
@@ -63,8 +122,5 @@ pub fn convert_svg_to_png(svg_content: &str, output_path: &Path, background: Opt
         &mut pixmap.as_mut(),
     );
 
-    // Save as PNG with quality control
-    save_png_with_quality(&pixmap, output_path, quality)?;
-
-    Ok(())
+    Ok(pixmap)
 }
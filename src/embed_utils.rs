@@ -0,0 +1,209 @@
+//! Scene data embedded in exported images, in Excalidraw's format
+//! PNG exports carry the scene in a `tEXt` chunk and SVG exports in `<metadata>`, so
+//! excalidraw.com can open them again for editing.
+use crate::models::{ElementType, ExcalidrawData};
+use crate::error::{ExcaliosaError, Result};
+use crate::frame_utils::find_frame;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// MIME type of Excalidraw scenes, also the keyword of the PNG `tEXt` chunk
+pub const EXCALIDRAW_MIME: &str = "application/vnd.excalidraw+json";
+
+/// Comment that marks an SVG as exported by Excalidraw
+pub const SVG_EXPORT_TAG: &str = "<!-- svg-source:excalidraw -->";
+
+/// Compression level of embedded scenes
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// Excalidraw's encoded text envelope (`encode()` in its `data/encode.ts`)
+/// `encoded` is a byte string: one char per byte, deflated (zlib) when `compressed`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncodedData {
+    pub version: String,
+    pub encoding: String,
+    pub compressed: bool,
+    pub encoded: String,
+}
+
+/// The part of a scene an export embeds, like Excalidraw's `serializeAsJSON`: the exported
+/// elements (the frame and its children with `frame`, else the whole scene) and the files
+/// they reference. Deleted elements and elements of unknown types are left out.
+pub fn get_exported_scene(data: &ExcalidrawData, frame: Option<&str>) -> Result<ExcalidrawData> {
+    let frame_id = match frame {
+        Some(name_or_id) => Some(
+            find_frame(&data.elements, name_or_id)
+                .ok_or_else(|| ExcaliosaError::FrameNotFound(name_or_id.to_string()))?
+                .id
+                .as_str(),
+        ),
+        None => None,
+    };
+    let elements: Vec<_> = data
+        .elements
+        .iter()
+        .filter(|el| !el.is_deleted && el.element_type != ElementType::Unknown)
+        .filter(|el| {
+            frame_id.is_none_or(|id| el.id == id || el.frame_id.as_deref() == Some(id))
+        })
+        .cloned()
+        .collect();
+    let files = data
+        .files
+        .iter()
+        .filter(|(id, _)| elements.iter().any(|el| el.file_id.as_ref() == Some(*id)))
+        .map(|(id, file)| (id.clone(), file.clone()))
+        .collect();
+    Ok(ExcalidrawData {
+        data_type: data.data_type.clone(),
+        version: data.version,
+        version_nonce: data.version_nonce,
+        source: data.source.clone(),
+        elements,
+        app_state: data.app_state.clone(),
+        files,
+    })
+}
+
+/// Scene JSON as Excalidraw stores it in exports
+pub fn serialize_scene(data: &ExcalidrawData) -> Result<String> {
+    serde_json::to_string(data).map_err(scene_encode_error)
+}
+
+fn scene_encode_error(e: serde_json::Error) -> ExcaliosaError {
+    ExcaliosaError::Encode { format: "scene", message: e.to_string() }
+}
+
+/// Bytes as a byte string, one char (U+0000-U+00FF) per byte
+fn to_byte_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Text compressed into Excalidraw's JSON envelope
+/// The result only holds chars up to U+00FF, so it can be written as Latin-1.
+pub fn encode_text(text: &str) -> Result<String> {
+    let deflated = miniz_oxide::deflate::compress_to_vec_zlib(text.as_bytes(), COMPRESSION_LEVEL);
+    let encoded = EncodedData {
+        version: "1".to_string(),
+        encoding: "bstring".to_string(),
+        compressed: true,
+        encoded: to_byte_string(&deflated),
+    };
+    serde_json::to_string(&encoded).map_err(scene_encode_error)
+}
+
+/// Text of the PNG `tEXt` chunk (keyword [`EXCALIDRAW_MIME`]) that embeds the scene
+pub fn png_scene_text(data: &ExcalidrawData) -> Result<String> {
+    encode_text(&serialize_scene(data)?)
+}
+
+/// SVG export tag and `<metadata>` element that embed the scene: the encoded envelope,
+/// base64'd between Excalidraw's payload comments
+pub fn svg_scene_metadata(data: &ExcalidrawData) -> Result<String> {
+    let envelope = encode_text(&serialize_scene(data)?)?;
+    let latin1: Vec<u8> = envelope.chars().map(|ch| ch as u8).collect();
+    let base64 = base64::engine::general_purpose::STANDARD.encode(latin1);
+    Ok(format!(
+        "{SVG_EXPORT_TAG}<metadata><!-- payload-type:{EXCALIDRAW_MIME} --><!-- payload-version:2 --><!-- payload-start -->{base64}<!-- payload-end --></metadata>"
+    ))
+}
+
+/// Text of Excalidraw's JSON envelope (`decode()` in its `data/encode.ts`)
+pub fn decode_text(data: &EncodedData) -> Result<String> {
+    if data.encoding != "bstring" {
+        return Err(ExcaliosaError::Parse(format!("Unknown scene encoding \"{}\"", data.encoding)));
+    }
+    let bytes = data
+        .encoded
+        .chars()
+        .map(|ch| u8::try_from(ch as u32))
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .map_err(|_| ExcaliosaError::Parse("Embedded scene is not a byte string".to_string()))?;
+    let bytes = if data.compressed {
+        miniz_oxide::inflate::decompress_to_vec_zlib(&bytes)
+            .map_err(|e| ExcaliosaError::Parse(format!("Failed to inflate embedded scene: {e:?}")))?
+    } else {
+        bytes
+    };
+    String::from_utf8(bytes).map_err(|e| ExcaliosaError::Parse(format!("Embedded scene is not UTF-8: {e}")))
+}
+
+/// Scene JSON from an embedded payload: an encoded envelope, or the scene itself in
+/// exports older than the envelope
+fn decode_payload(payload: &str) -> Result<String> {
+    let value: serde_json::Value =
+        serde_json::from_str(payload).map_err(|e| ExcaliosaError::Parse(format!("Invalid embedded scene: {e}")))?;
+    if value.get("encoded").is_some() {
+        let encoded: EncodedData =
+            serde_json::from_value(value).map_err(|e| ExcaliosaError::Parse(format!("Invalid embedded scene: {e}")))?;
+        return decode_text(&encoded);
+    }
+    if value.get("type").and_then(|t| t.as_str()) == Some("excalidraw") {
+        return Ok(payload.to_string());
+    }
+    Err(ExcaliosaError::Parse("Embedded payload is not an Excalidraw scene".to_string()))
+}
+
+/// Scene JSON embedded in a PNG's `tEXt` chunk
+pub fn decode_png_scene(png: &[u8]) -> Result<String> {
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !png.starts_with(PNG_SIGNATURE) {
+        return Err(ExcaliosaError::Parse("Not a PNG file".to_string()));
+    }
+
+    // Walk the chunks (length, type, data, CRC): Excalidraw writes the scene just before IEND
+    let mut offset = PNG_SIGNATURE.len();
+    while offset + 8 <= png.len() {
+        let length = u32::from_be_bytes([png[offset], png[offset + 1], png[offset + 2], png[offset + 3]]) as usize;
+        let chunk_type = &png[offset + 4..offset + 8];
+        let data_start = offset + 8;
+        let Some(data) = png.get(data_start..data_start.saturating_add(length)) else {
+            break;
+        };
+        if chunk_type == b"tEXt" {
+            if let Some(separator) = data.iter().position(|&b| b == 0) && &data[..separator] == EXCALIDRAW_MIME.as_bytes() {
+                // tEXt is Latin-1
+                let text: String = data[separator + 1..].iter().map(|&b| b as char).collect();
+                return decode_payload(&text);
+            }
+        } else if chunk_type == b"IEND" {
+            break;
+        }
+        offset = data_start + length + 4;
+    }
+    Err(ExcaliosaError::Parse("PNG has no embedded Excalidraw scene".to_string()))
+}
+
+/// Scene JSON embedded in an SVG's payload comments
+/// Payload version 1 is base64'd UTF-8, later versions base64 a byte string.
+pub fn decode_svg_scene(svg: &str) -> Result<String> {
+    if !svg.contains(&format!("payload-type:{EXCALIDRAW_MIME}")) {
+        return Err(ExcaliosaError::Parse("SVG has no embedded Excalidraw scene".to_string()));
+    }
+    const START: &str = "<!-- payload-start -->";
+    const END: &str = "<!-- payload-end -->";
+    let start = svg
+        .find(START)
+        .map(|i| i + START.len())
+        .ok_or_else(|| ExcaliosaError::Parse("SVG scene payload has no start marker".to_string()))?;
+    let end = svg[start..]
+        .find(END)
+        .map(|i| start + i)
+        .ok_or_else(|| ExcaliosaError::Parse("SVG scene payload has no end marker".to_string()))?;
+    let base64: String = svg[start..end].chars().filter(|ch| !ch.is_whitespace()).collect();
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64.as_bytes())
+        .map_err(|e| ExcaliosaError::Parse(format!("Invalid base64 in SVG scene payload: {e}")))?;
+
+    let version = svg
+        .split("<!-- payload-version:")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or("1");
+    let payload = if version == "1" {
+        String::from_utf8(bytes).map_err(|e| ExcaliosaError::Parse(format!("SVG scene payload is not UTF-8: {e}")))?
+    } else {
+        bytes.iter().map(|&b| b as char).collect()
+    };
+    decode_payload(&payload)
+}
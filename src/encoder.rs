@@ -0,0 +1,182 @@
+//! Raster encoders for rendered pixmaps
+//! PNG keeps the alpha channel, JPEG is flattened onto an opaque color, and WebP is
+//! either lossy with a quality setting or lossless.
+//! Lossy WebP is encoded by libwebp, a C library, so it needs the `webp-lossy` feature;
+//! the other encoders are pure Rust.
+use crate::models::ExcalidrawData;
+use crate::utils::encode_png_with_quality;
+use crate::error::{ExcaliosaError, Result};
+use std::io::Write;
+use std::path::Path;
+use tiny_skia::Pixmap;
+
+/// Raster image format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RasterFormat {
+    /// Lossless with alpha; quality picks the zlib compression level
+    #[default]
+    Png,
+    /// Lossy without alpha; quality is the JPEG quality
+    Jpeg,
+    /// Lossy with alpha; quality is the WebP quality. Needs the `webp-lossy` feature
+    WebP,
+    /// Lossless with alpha
+    WebPLossless,
+}
+
+impl RasterFormat {
+    /// Whether lossy WebP can be encoded, i.e. the `webp-lossy` feature is enabled
+    pub const LOSSY_WEBP: bool = cfg!(feature = "webp-lossy");
+
+    /// Format for a file extension ("png", "jpg", "jpeg" or "webp"), case-insensitive
+    /// `.webp` files are lossy with the `webp-lossy` feature and lossless without it; use
+    /// [`RasterFormat::WebPLossless`] explicitly for lossless either way.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(RasterFormat::Png),
+            "jpg" | "jpeg" => Some(RasterFormat::Jpeg),
+            "webp" if Self::LOSSY_WEBP => Some(RasterFormat::WebP),
+            "webp" => Some(RasterFormat::WebPLossless),
+            _ => None,
+        }
+    }
+
+    /// Format for an output path's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension)
+    }
+
+    /// Usual file extension of the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            RasterFormat::Png => "png",
+            RasterFormat::Jpeg => "jpg",
+            RasterFormat::WebP | RasterFormat::WebPLossless => "webp",
+        }
+    }
+}
+
+/// Encode a pixmap in the given format
+/// `quality` (0-100) is the compression level for PNG and the encoder quality for JPEG and
+/// lossy WebP. JPEG has no alpha, so the pixmap is flattened onto the opaque `matte` color.
+/// `embed_scene` is only stored in PNGs; the other formats ignore it.
+pub fn encode_pixmap(
+    pixmap: &Pixmap,
+    format: RasterFormat,
+    quality: u8,
+    matte: (u8, u8, u8),
+    embed_scene: Option<&ExcalidrawData>,
+) -> Result<Vec<u8>> {
+    match format {
+        RasterFormat::Png => encode_png_with_quality(pixmap, quality, embed_scene),
+        RasterFormat::Jpeg => encode_jpeg(pixmap, quality, matte),
+        RasterFormat::WebP => encode_webp(pixmap, Some(quality)),
+        RasterFormat::WebPLossless => encode_webp(pixmap, None),
+    }
+}
+
+/// Encode a pixmap and write it to a file
+pub fn save_pixmap(
+    pixmap: &Pixmap,
+    output_path: &Path,
+    format: RasterFormat,
+    quality: u8,
+    matte: (u8, u8, u8),
+    embed_scene: Option<&ExcalidrawData>,
+) -> Result<()> {
+    let bytes = encode_pixmap(pixmap, format, quality, matte, embed_scene)?;
+    std::fs::write(output_path, bytes).map_err(|source| ExcaliosaError::Write { path: output_path.to_path_buf(), source })
+}
+
+/// Encode a pixmap and write it to any writer
+pub fn write_pixmap<W: Write>(
+    pixmap: &Pixmap,
+    writer: &mut W,
+    format: RasterFormat,
+    quality: u8,
+    matte: (u8, u8, u8),
+    embed_scene: Option<&ExcalidrawData>,
+) -> Result<()> {
+    let bytes = encode_pixmap(pixmap, format, quality, matte, embed_scene)?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Pixels as packed RGBA with straight (not premultiplied) alpha, row by row
+pub fn pixmap_to_rgba(pixmap: &Pixmap) -> Vec<u8> {
+    pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect()
+}
+
+/// Composite the premultiplied pixels over an opaque color, as packed RGB
+fn flatten_rgb(pixmap: &Pixmap, (r, g, b): (u8, u8, u8)) -> Vec<u8> {
+    let over = |src: u8, alpha: u8, matte: u8| -> u8 {
+        (src as u32 + (matte as u32 * (255 - alpha as u32) + 127) / 255).min(255) as u8
+    };
+    let mut rgb = Vec::with_capacity(pixmap.width() as usize * pixmap.height() as usize * 3);
+    for pixel in pixmap.pixels() {
+        let alpha = pixel.alpha();
+        rgb.push(over(pixel.red(), alpha, r));
+        rgb.push(over(pixel.green(), alpha, g));
+        rgb.push(over(pixel.blue(), alpha, b));
+    }
+    rgb
+}
+
+fn encode_jpeg(pixmap: &Pixmap, quality: u8, matte: (u8, u8, u8)) -> Result<Vec<u8>> {
+    use image::codecs::jpeg::JpegEncoder;
+    use image::ExtendedColorType;
+
+    let rgb = flatten_rgb(pixmap, matte);
+    let mut bytes = Vec::new();
+    // The JPEG encoder accepts qualities 1-100
+    JpegEncoder::new_with_quality(&mut bytes, quality.clamp(1, 100))
+        .encode(&rgb, pixmap.width(), pixmap.height(), ExtendedColorType::Rgb8)
+        .map_err(|e| ExcaliosaError::Encode { format: "JPEG", message: e.to_string() })?;
+    Ok(bytes)
+}
+
+/// Lossy WebP with the given quality, or lossless WebP when `quality` is `None`
+fn encode_webp(pixmap: &Pixmap, quality: Option<u8>) -> Result<Vec<u8>> {
+    // WebP expects straight alpha, tiny-skia stores premultiplied colors
+    let rgba = pixmap_to_rgba(pixmap);
+    match quality {
+        Some(quality) => encode_webp_lossy(&rgba, pixmap.width(), pixmap.height(), quality),
+        None => encode_webp_lossless(&rgba, pixmap.width(), pixmap.height()),
+    }
+}
+
+fn encode_webp_lossless(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    use image::codecs::webp::WebPEncoder;
+    use image::ExtendedColorType;
+
+    let mut bytes = Vec::new();
+    WebPEncoder::new_lossless(&mut bytes)
+        .encode(rgba, width, height, ExtendedColorType::Rgba8)
+        .map_err(|e| ExcaliosaError::Encode { format: "WebP", message: e.to_string() })?;
+    Ok(bytes)
+}
+
+#[cfg(feature = "webp-lossy")]
+fn encode_webp_lossy(rgba: &[u8], width: u32, height: u32, quality: u8) -> Result<Vec<u8>> {
+    let memory = webp::Encoder::from_rgba(rgba, width, height)
+        .encode_simple(false, quality.min(100) as f32)
+        .map_err(|e| ExcaliosaError::Encode { format: "WebP", message: format!("{e:?}") })?;
+    Ok(memory.to_vec())
+}
+
+#[cfg(not(feature = "webp-lossy"))]
+fn encode_webp_lossy(_rgba: &[u8], _width: u32, _height: u32, _quality: u8) -> Result<Vec<u8>> {
+    Err(ExcaliosaError::Encode {
+        format: "WebP",
+        message: "lossy WebP needs the `webp-lossy` feature; use lossless WebP instead".to_string(),
+    })
+}
@@ -0,0 +1,115 @@
+//! Errors and warnings of the library
+//! Errors fail a load or render. Warnings report what was skipped without failing it; they
+//! are returned by the loaders and sent to [`RenderOptions::warnings`] by the renderers.
+//!
+//! [`RenderOptions::warnings`]: crate::options::RenderOptions::warnings
+use crate::models::{ElementType, ExcalidrawElement, FileData};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Result of the library's fallible functions
+pub type Result<T, E = ExcaliosaError> = std::result::Result<T, E>;
+
+/// Why a scene could not be loaded or rendered
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ExcaliosaError {
+    /// The input is not a scene: malformed JSON, or an export without a readable scene
+    #[error("{0}")]
+    Parse(String),
+    /// The scene JSON does not match the Excalidraw format
+    #[error("Failed to parse Excalidraw JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// An element of a type no backend can draw
+    #[error("Unsupported element type \"{element_type}\" of element {id}")]
+    UnsupportedElement { id: String, element_type: ElementType },
+    /// An image element whose file is not in the scene's `files`
+    #[error("Image element {element_id} references missing file {file_id}")]
+    MissingFile { element_id: String, file_id: String },
+    /// A font could not be loaded or used
+    #[error("Font error: {0}")]
+    Font(String),
+    /// The rendered image could not be encoded
+    #[error("Failed to encode {format}: {message}")]
+    Encode { format: &'static str, message: String },
+    /// The output is too large (or empty) to allocate
+    #[error("Image size {width}x{height} is too large to render")]
+    DimensionTooLarge { width: u32, height: u32 },
+    /// `RenderOptions::frame` names no frame of the scene
+    #[error("Frame not found: {0}")]
+    FrameNotFound(String),
+    /// `RenderOptions::region` has no area
+    #[error("Export region {width}x{height} is empty")]
+    EmptyRegion { width: f64, height: f64 },
+    /// Reading an input file failed
+    #[error("Failed to read {path:?}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    /// Writing an output file failed
+    #[error("Failed to write {path:?}: {source}")]
+    Write { path: PathBuf, source: std::io::Error },
+    /// Writing to a caller's writer failed
+    #[error("Failed to write output: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Something a load or render skipped without failing
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum Warning {
+    /// An element the loader could not restore was dropped; `index` is its position in
+    /// the scene's `elements`
+    #[error("Skipping element {index}: {reason}")]
+    SkippedElement { index: usize, reason: String },
+    /// An element of a type the renderers cannot draw was left out
+    #[error("Unsupported element type \"{element_type}\" of element {id}")]
+    UnsupportedElement { id: String, element_type: ElementType },
+    /// An image element whose file is not in the scene was left out
+    #[error("Image element {element_id} references missing file {file_id}")]
+    MissingFile { element_id: String, file_id: String },
+    /// Characters of an element's text that no font has a glyph for were left out
+    #[error("Element {element_id} has characters no font can draw: {}", format_codepoints(.characters))]
+    MissingGlyphs { element_id: String, characters: Vec<char> },
+}
+
+/// Warnings for the elements of a render that cannot be drawn
+pub(crate) fn get_element_warnings(
+    elements: &[&ExcalidrawElement],
+    files: &HashMap<String, FileData>,
+) -> Vec<Warning> {
+    elements
+        .iter()
+        .filter_map(|el| match el.element_type {
+            ElementType::Unknown | ElementType::Embeddable | ElementType::Iframe => {
+                Some(Warning::UnsupportedElement { id: el.id.clone(), element_type: el.element_type })
+            }
+            ElementType::Image => el
+                .file_id
+                .as_ref()
+                .filter(|file_id| !files.contains_key(file_id.as_str()))
+                .map(|file_id| Warning::MissingFile { element_id: el.id.clone(), file_id: file_id.clone() }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Characters as Unicode code points, e.g. "U+4E2D U+6587"
+fn format_codepoints(characters: &[char]) -> String {
+    characters
+        .iter()
+        .map(|ch| format!("U+{:04X}", *ch as u32))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Warnings as errors, for callers that want to fail on them
+impl From<Warning> for ExcaliosaError {
+    fn from(warning: Warning) -> Self {
+        match warning {
+            Warning::SkippedElement { .. } => ExcaliosaError::Parse(warning.to_string()),
+            Warning::UnsupportedElement { id, element_type } => ExcaliosaError::UnsupportedElement { id, element_type },
+            Warning::MissingFile { element_id, file_id } => ExcaliosaError::MissingFile { element_id, file_id },
+            Warning::MissingGlyphs { .. } => ExcaliosaError::Font(warning.to_string()),
+        }
+    }
+}
@@ -1,25 +1,73 @@
+use crate::converter::{CASCADIA_CODE, EXCALIFONT_REGULAR, LIBERATION_SANS_REGULAR};
+use crate::models::{TextAlign, VerticalAlign};
+
+/// An Excalidraw font family: its id in scenes, its name, and the bundled family drawn in
+/// its place when the font itself is not available
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontFamily {
+    pub id: i32,
+    pub name: &'static str,
+    pub fallback: &'static str,
+}
+
+/// Excalidraw's font families (`FONT_FAMILY` in Excalidraw's constants)
+/// Families that are not bundled fall back to a bundled family of similar style: the
+/// sans-serif Helvetica, Nunito and Lilita One to Liberation Sans (metric-compatible with
+/// Helvetica/Arial only), the hand-drawn Virgil to its successor Excalifont, and the
+/// monospaced Comic Shanns to Cascadia Code. SVG output names the family followed by its
+/// fallback. Font files of the missing families can be added to a
+/// [`FontSet`](crate::fonts::FontSet) and are then matched by name.
+pub const FONT_FAMILIES: &[FontFamily] = &[
+    FontFamily { id: 1, name: "Virgil", fallback: "Excalifont" },
+    FontFamily { id: 2, name: "Helvetica", fallback: "Liberation Sans" },
+    FontFamily { id: 3, name: "Cascadia", fallback: "Cascadia Code" },
+    FontFamily { id: 4, name: "Local Font", fallback: "Excalifont" },
+    FontFamily { id: 5, name: "Excalifont", fallback: "Excalifont" },
+    FontFamily { id: 6, name: "Nunito", fallback: "Liberation Sans" },
+    FontFamily { id: 7, name: "Lilita One", fallback: "Liberation Sans" },
+    FontFamily { id: 8, name: "Comic Shanns", fallback: "Cascadia Code" },
+    FontFamily { id: 9, name: "Liberation Sans", fallback: "Liberation Sans" },
+];
+
+/// Excalidraw's default font family, used for missing and unknown ids
+pub const DEFAULT_FONT_FAMILY: FontFamily = FontFamily { id: 5, name: "Excalifont", fallback: "Excalifont" };
+
+/// Get the Excalidraw font family of a font id, or Excalifont for missing and unknown ids
+pub fn get_font_family_by_id(font_id: Option<i32>) -> FontFamily {
+    font_id
+        .and_then(|id| FONT_FAMILIES.iter().find(|family| family.id == id))
+        .copied()
+        .unwrap_or(DEFAULT_FONT_FAMILY)
+}
+
+/// Get the Excalidraw font family with a name, ignoring case
+pub fn get_font_family_by_name(name: &str) -> Option<FontFamily> {
+    FONT_FAMILIES.iter().find(|family| family.name.eq_ignore_ascii_case(name)).copied()
+}
+
 /// Get font family name based on Excalidraw font ID
-/// Maps font IDs to family names that match the loaded fonts
-/// 
-/// Font ID mapping:
-/// - None/0: "Excalifont" (default)
-/// - 1: "Liberation Sans"
-/// - 2: "Cascadia Code"
+/// See [`FONT_FAMILIES`]; missing and unknown ids are Excalifont.
 pub fn get_font_family(font_id: Option<i32>) -> &'static str {
-    match font_id {
-        Some(1) => "Liberation Sans",
-        Some(2) => "Cascadia Code",
-        _ => "Excalifont", // Default or ID 0
+    get_font_family_by_id(font_id).name
+}
+
+/// Get the embedded font bytes for an Excalidraw font ID: the family's bundled face, or
+/// its fallback
+pub fn get_font_data(font_id: Option<i32>) -> &'static [u8] {
+    match get_font_family_by_id(font_id).fallback {
+        "Liberation Sans" => LIBERATION_SANS_REGULAR,
+        "Cascadia Code" => CASCADIA_CODE,
+        _ => EXCALIFONT_REGULAR,
     }
 }
 
 /// Get SVG text-anchor attribute value based on text alignment
 /// Maps Excalidraw text alignment to SVG text-anchor values
-pub fn get_svg_text_anchor(text_align: Option<&str>) -> &'static str {
+pub fn get_svg_text_anchor(text_align: Option<TextAlign>) -> &'static str {
     match text_align {
-        Some("center") => "middle",
-        Some("right") => "end",
-        Some("left") => "start",
+        Some(TextAlign::Center) => "middle",
+        Some(TextAlign::Right) => "end",
+        Some(TextAlign::Left) => "start",
         _ => "start",
     }
 }
@@ -30,17 +78,17 @@ pub fn get_svg_text_anchor(text_align: Option<&str>) -> &'static str {
 /// # Arguments
 /// * `x` - Left edge of text container
 /// * `width` - Width of text container
-/// * `text_align` - Text alignment ("left", "center", "right", or None)
+/// * `text_align` - Text alignment (left, center, right, or None)
 /// 
 /// # Returns
 /// Absolute X coordinate for text positioning
-pub fn calculate_text_x_position<T>(x: T, width: T, text_align: Option<&str>) -> T
+pub fn calculate_text_x_position<T>(x: T, width: T, text_align: Option<TextAlign>) -> T
 where
     T: num_traits::Float,
 {
     match text_align {
-        Some("center") => x + width / T::from(2.0).unwrap(),
-        Some("right") => x + width,
+        Some(TextAlign::Center) => x + width / T::from(2.0).unwrap(),
+        Some(TextAlign::Right) => x + width,
         _ => x, // "left" or default
     }
 }
@@ -52,7 +100,7 @@ where
 /// * `x` - Left edge of text container
 /// * `container_width` - Width of text container
 /// * `line_width` - Actual rendered width of the text line
-/// * `text_align` - Text alignment ("left", "center", "right", or None)
+/// * `text_align` - Text alignment (left, center, right, or None)
 /// 
 /// # Returns
 /// Starting X coordinate for rendering the text line
@@ -60,14 +108,14 @@ pub fn calculate_text_x_position_for_line<T>(
     x: T,
     container_width: T,
     line_width: T,
-    text_align: Option<&str>,
+    text_align: Option<TextAlign>,
 ) -> T
 where
     T: num_traits::Float,
 {
     match text_align {
-        Some("center") => x + (container_width - line_width) / T::from(2.0).unwrap(),
-        Some("right") => x + container_width - line_width,
+        Some(TextAlign::Center) => x + (container_width - line_width) / T::from(2.0).unwrap(),
+        Some(TextAlign::Right) => x + container_width - line_width,
         _ => x, // "left" or default
     }
 }
@@ -75,18 +123,18 @@ where
 /// Calculate vertical offset for text baseline based on vertical alignment
 /// 
 /// # Arguments
-/// * `vertical_align` - Vertical alignment ("top", "middle", "bottom", or None)
+/// * `vertical_align` - Vertical alignment (top, middle, bottom, or None)
 /// * `font_size` - Font size in pixels
 /// 
 /// # Returns
 /// Vertical offset from top of text container
-pub fn get_vertical_offset<T>(vertical_align: Option<&str>, font_size: T) -> T
+pub fn get_vertical_offset<T>(vertical_align: Option<VerticalAlign>, font_size: T) -> T
 where
     T: num_traits::Float,
 {
     match vertical_align {
-        Some("middle") => font_size * T::from(0.35).unwrap(),
-        Some("bottom") => font_size * T::from(0.9).unwrap(),
+        Some(VerticalAlign::Middle) => font_size * T::from(0.35).unwrap(),
+        Some(VerticalAlign::Bottom) => font_size * T::from(0.9).unwrap(),
         _ => font_size * T::from(0.75).unwrap(), // "top" or default
     }
 }
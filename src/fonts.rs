@@ -0,0 +1,447 @@
+//! Fonts used to draw text
+//! A [`FontProvider`] maps Excalidraw font ids to families and families to font files. Every
+//! backend draws with the provider of [`RenderOptions::fonts`]: the skrifa and PDF text paths
+//! look fonts up by family, while parley and resvg get all of the provider's fonts.
+//! [`FontSet`], the default provider, holds the embedded fonts plus any font files added to it;
+//! Excalidraw families it has no file for are drawn with their bundled fallback.
+//!
+//! Characters the chosen font has no glyph for (CJK, emoji, ...) are drawn with a fallback
+//! font: the provider's preferred fallback families, then the embedded fonts, then the added
+//! font files, then, when enabled, the system fonts found by fontdb through fontconfig.
+//!
+//! [`RenderOptions::fonts`]: crate::options::RenderOptions::fonts
+use crate::converter::{CASCADIA_CODE, EXCALIFONT_REGULAR, LIBERATION_SANS_BOLD, LIBERATION_SANS_REGULAR};
+use crate::error::{ExcaliosaError, Result, Warning};
+use crate::font_utils::{get_font_family, get_font_family_by_name};
+use crate::frame_utils::{get_frame_name, FRAME_NAME_FONT_FAMILY};
+use crate::models::{ElementType, ExcalidrawElement};
+use skrifa::attribute::{Style, Weight};
+use skrifa::raw::{FileRef, FontRef};
+use skrifa::string::StringId;
+use skrifa::MetadataProvider;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Bytes of a font file, shared without copying between the backends' font databases
+pub type FontData = Arc<dyn AsRef<[u8]> + Send + Sync>;
+
+/// A font in a font file, which may be a collection of several fonts
+#[derive(Clone)]
+pub struct FontFace {
+    pub data: FontData,
+    /// Index of the font in a collection; 0 for single fonts
+    pub index: u32,
+}
+
+impl FontFace {
+    /// The first font of a font file
+    pub fn new(data: FontData) -> Self {
+        Self { data, index: 0 }
+    }
+
+    /// Parsed font, or `None` if the data is not a font
+    pub fn font_ref(&self) -> Option<FontRef<'_>> {
+        FontRef::from_index((*self.data).as_ref(), self.index).ok()
+    }
+
+    /// Whether the font has an outline glyph for a character
+    /// Bitmap-only glyphs (color emoji fonts with CBDT or sbix tables) do not count: the
+    /// backends draw outlines only.
+    pub fn has_glyph(&self, ch: char) -> bool {
+        self.font_ref().is_some_and(|font| has_outline_glyph(&font, ch))
+    }
+
+    fn same_file(&self, other: &FontFace) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+
+    fn same_font(&self, other: &FontFace) -> bool {
+        Arc::ptr_eq(&self.data, &other.data) && self.index == other.index
+    }
+}
+
+/// Source of the fonts text is drawn with
+pub trait FontProvider: fmt::Debug + Send + Sync {
+    /// Family that text with an Excalidraw font id is drawn with
+    fn family_for_id(&self, font_id: Option<i32>) -> String;
+
+    /// Font of a family, or `None` if the provider has none
+    fn font(&self, family: &str) -> Option<FontFace>;
+
+    /// Every font file of the provider, for font databases that match families themselves
+    /// Collections are listed once; the databases load all of their fonts.
+    fn fonts(&self) -> Vec<FontData>;
+
+    /// Font text of a family is drawn with, falling back to the embedded Excalifont
+    fn family_font(&self, family: &str) -> FontFace {
+        self.font(family)
+            .unwrap_or_else(|| FontFace::new(Arc::new(EXCALIFONT_REGULAR)))
+    }
+
+    /// Font for an Excalidraw font id, falling back to the embedded Excalifont
+    fn font_for_id(&self, font_id: Option<i32>) -> FontFace {
+        self.family_font(&self.family_for_id(font_id))
+    }
+
+    /// Font for a character the text's own font has no glyph for, or `None` if no font has one
+    fn fallback_font(&self, _ch: char) -> Option<FontFace> {
+        None
+    }
+
+    /// Whether font databases should also load the system fonts
+    fn uses_system_fonts(&self) -> bool {
+        false
+    }
+}
+
+/// A font registered in a [`FontSet`]
+#[derive(Clone)]
+struct Face {
+    family: String,
+    /// Regular weight and upright; preferred when a family has several faces
+    regular: bool,
+    face: FontFace,
+}
+
+/// The default [`FontProvider`]: the embedded fonts, plus font files added by the caller
+/// Families are matched case-insensitively, and fonts added later win over earlier ones of
+/// the same family. Font ids and families can be mapped to other families, e.g. to draw
+/// Excalidraw's "Local font" (id 4) or a corporate font with a real file.
+#[derive(Clone)]
+pub struct FontSet {
+    faces: Vec<Face>,
+    /// Families drawn with another family, keyed by lowercase name
+    aliases: HashMap<String, String>,
+    /// Excalidraw font ids drawn with a family other than the built-in mapping
+    font_ids: HashMap<i32, String>,
+    /// Families tried first for characters a text's own font lacks
+    fallback_families: Vec<String>,
+    /// System fonts, the last fallbacks
+    system: Option<Arc<SystemFonts>>,
+}
+
+impl Default for FontSet {
+    fn default() -> Self {
+        let mut set = Self {
+            faces: Vec::new(),
+            aliases: HashMap::new(),
+            font_ids: HashMap::new(),
+            fallback_families: Vec::new(),
+            system: None,
+        };
+        set.push_face("Excalifont", true, FontFace::new(Arc::new(EXCALIFONT_REGULAR)));
+        set.push_face("Liberation Sans", true, FontFace::new(Arc::new(LIBERATION_SANS_REGULAR)));
+        set.push_face("Liberation Sans", false, FontFace::new(Arc::new(LIBERATION_SANS_BOLD)));
+        set.push_face("Cascadia Code", true, FontFace::new(Arc::new(CASCADIA_CODE)));
+        set
+    }
+}
+
+impl fmt::Debug for FontSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FontSet")
+            .field("families", &self.faces.iter().map(|face| face.family.as_str()).collect::<Vec<_>>())
+            .field("aliases", &self.aliases)
+            .field("font_ids", &self.font_ids)
+            .field("fallback_families", &self.fallback_families)
+            .field("system_fonts", &self.system.as_ref().map(|system| system.db.len()))
+            .finish()
+    }
+}
+
+impl FontSet {
+    /// The embedded fonts only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the fonts of a font file, every font of a collection (`.ttc`) included,
+    /// under the family names in their name tables, and return those names
+    pub fn add_font(&mut self, data: FontData) -> Result<Vec<String>> {
+        let file = FileRef::new((*data).as_ref())
+            .map_err(|e| ExcaliosaError::Font(format!("Not a font file: {e}")))?;
+        let mut faces = Vec::new();
+        for (index, font) in file.fonts().enumerate() {
+            let font = font.map_err(|e| ExcaliosaError::Font(format!("Invalid font {index}: {e}")))?;
+            let family = get_family_name(&font)
+                .ok_or_else(|| ExcaliosaError::Font(format!("Font {index} has no family name")))?;
+            let attributes = font.attributes();
+            let regular = attributes.weight == Weight::NORMAL && attributes.style == Style::Normal;
+            faces.push((family, regular, index as u32));
+        }
+        if faces.is_empty() {
+            return Err(ExcaliosaError::Font("Font collection has no fonts".to_string()));
+        }
+
+        let mut families = Vec::new();
+        for (family, regular, index) in faces {
+            self.push_face(&family, regular, FontFace { data: data.clone(), index });
+            families.push(family);
+        }
+        Ok(families)
+    }
+
+    /// Read and register a font file; see [`FontSet::add_font`]
+    pub fn add_font_file(&mut self, path: &Path) -> Result<Vec<String>> {
+        let bytes = std::fs::read(path).map_err(|source| ExcaliosaError::Read { path: path.to_path_buf(), source })?;
+        self.add_font(Arc::new(bytes)).map_err(|e| match e {
+            ExcaliosaError::Font(message) => ExcaliosaError::Font(format!("{path:?}: {message}")),
+            e => e,
+        })
+    }
+
+    /// Register every `.ttf`, `.otf` and `.ttc` file under a directory, recursively
+    /// Files that are not fonts are skipped. Returns the number of fonts registered, counting
+    /// each font of a collection.
+    pub fn add_font_dir(&mut self, dir: &Path) -> Result<usize> {
+        let entries = std::fs::read_dir(dir).map_err(|source| ExcaliosaError::Read { path: dir.to_path_buf(), source })?;
+        let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+        paths.sort();
+
+        let mut count = 0;
+        for path in paths {
+            if path.is_dir() {
+                count += self.add_font_dir(&path)?;
+            } else if is_font_file(&path) && let Ok(families) = self.add_font_file(&path) {
+                count += families.len();
+            }
+        }
+        Ok(count)
+    }
+
+    /// Draw text of `family` with the registered family `target`
+    /// `family` is a family name such as "Excalifont", or an Excalidraw font id such as "4".
+    pub fn map_family(&mut self, family: &str, target: impl Into<String>) {
+        match family.trim().parse::<i32>() {
+            Ok(font_id) => {
+                self.font_ids.insert(font_id, target.into());
+            }
+            Err(_) => {
+                self.aliases.insert(family.trim().to_lowercase(), target.into());
+            }
+        }
+    }
+
+    /// Register a font file and draw text of `family` (a family name or an Excalidraw font
+    /// id) with it, as given by `--font family=path.ttf`
+    /// A collection is drawn with its first font, unless `family` names one of its fonts.
+    pub fn map_font_file(&mut self, family: &str, path: &Path) -> Result<()> {
+        let families = self.add_font_file(path)?;
+        if !families.iter().any(|target| family.trim().eq_ignore_ascii_case(target)) {
+            self.map_family(family, families[0].clone());
+        }
+        Ok(())
+    }
+
+    /// Try a family first for characters a text's own font has no glyph for
+    /// Families are tried in the order they were added, before the other fonts of the set.
+    pub fn add_fallback_family(&mut self, family: impl Into<String>) {
+        self.fallback_families.push(family.into());
+    }
+
+    /// Use the system fonts as the last fallbacks, and in resvg's font database
+    /// Fonts are found through fontconfig (or the platform's font directories) by fontdb,
+    /// and read only when a character needs them. Returns the number of system fonts found.
+    pub fn add_system_fonts(&mut self) -> usize {
+        let system = SystemFonts::load();
+        let count = system.db.len();
+        self.system = Some(Arc::new(system));
+        count
+    }
+
+    fn push_face(&mut self, family: &str, regular: bool, face: FontFace) {
+        self.faces.push(Face { family: family.to_string(), regular, face });
+    }
+
+    /// Family a family name is drawn with, after aliases
+    fn resolve_family<'a>(&'a self, family: &'a str) -> &'a str {
+        self.aliases.get(&family.to_lowercase()).map_or(family, String::as_str)
+    }
+}
+
+impl FontProvider for FontSet {
+    fn family_for_id(&self, font_id: Option<i32>) -> String {
+        let family = font_id
+            .and_then(|id| self.font_ids.get(&id))
+            .map_or_else(|| get_font_family(font_id), String::as_str);
+        let family = self.resolve_family(family);
+        if self.font(family).is_some() {
+            return family.to_string();
+        }
+        // Excalidraw families without a font fall back to a bundled family
+        match get_font_family_by_name(family) {
+            Some(registered) => self.resolve_family(registered.fallback).to_string(),
+            None => family.to_string(),
+        }
+    }
+
+    fn font(&self, family: &str) -> Option<FontFace> {
+        let family = self.resolve_family(family);
+        // The latest regular face wins, else the latest face of the family
+        self.faces
+            .iter()
+            .rev()
+            .filter(|face| face.family.eq_ignore_ascii_case(family))
+            .min_by_key(|face| !face.regular)
+            .map(|face| face.face.clone())
+    }
+
+    fn fonts(&self) -> Vec<FontData> {
+        let mut files: Vec<&FontFace> = Vec::new();
+        for face in &self.faces {
+            if !files.iter().any(|file| file.same_file(&face.face)) {
+                files.push(&face.face);
+            }
+        }
+        files.into_iter().map(|face| face.data.clone()).collect()
+    }
+
+    fn fallback_font(&self, ch: char) -> Option<FontFace> {
+        let preferred = self.fallback_families.iter().filter_map(|family| self.font(family));
+        let registered = self.faces.iter().map(|face| face.face.clone());
+        preferred
+            .chain(registered)
+            .find(|face| face.has_glyph(ch))
+            .or_else(|| self.system.as_ref()?.fallback_font(ch))
+    }
+
+    fn uses_system_fonts(&self) -> bool {
+        self.system.is_some()
+    }
+}
+
+/// System fonts, read on demand
+struct SystemFonts {
+    db: fontdb::Database,
+    loaded: Mutex<LoadedSystemFonts>,
+}
+
+#[derive(Default)]
+struct LoadedSystemFonts {
+    /// Fonts read so far, tried before reading new ones
+    faces: Vec<(fontdb::ID, FontFace)>,
+    /// Characters no system font has a glyph for
+    missing: HashSet<char>,
+}
+
+impl SystemFonts {
+    fn load() -> Self {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        Self { db, loaded: Mutex::new(LoadedSystemFonts::default()) }
+    }
+
+    fn fallback_font(&self, ch: char) -> Option<FontFace> {
+        let mut loaded = self.loaded.lock().ok()?;
+        if let Some((_, face)) = loaded.faces.iter().find(|(_, face)| face.has_glyph(ch)) {
+            return Some(face.clone());
+        }
+        if loaded.missing.contains(&ch) {
+            return None;
+        }
+
+        for info in self.db.faces() {
+            if loaded.faces.iter().any(|(id, _)| *id == info.id) {
+                continue;
+            }
+            let face = self.db.with_face_data(info.id, |data, index| {
+                let font = FontRef::from_index(data, index).ok()?;
+                has_outline_glyph(&font, ch).then(|| FontFace { data: Arc::new(data.to_vec()), index })
+            });
+            if let Some(face) = face.flatten() {
+                loaded.faces.push((info.id, face.clone()));
+                return Some(face);
+            }
+        }
+        loaded.missing.insert(ch);
+        None
+    }
+}
+
+/// Fonts to draw a text with, and the characters none of them has a glyph for
+/// The text's own font comes first, followed by a fallback font for each character it lacks.
+/// Both are empty when `primary` is not a font.
+pub fn resolve_text_fonts(fonts: &dyn FontProvider, primary: FontFace, text: &str) -> (Vec<FontFace>, Vec<char>) {
+    let Some(font) = primary.font_ref() else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut seen = HashSet::new();
+    let lacking: Vec<char> = text
+        .chars()
+        .filter(|ch| !is_ignorable(*ch) && !has_outline_glyph(&font, *ch) && seen.insert(*ch))
+        .collect();
+
+    let mut faces = vec![primary];
+    let mut missing = Vec::new();
+    for ch in lacking {
+        if faces.iter().any(|face| face.has_glyph(ch)) {
+            continue;
+        }
+        match fonts.fallback_font(ch) {
+            Some(face) if !faces.iter().any(|known| known.same_font(&face)) => faces.push(face),
+            Some(_) => {}
+            None => missing.push(ch),
+        }
+    }
+    (faces, missing)
+}
+
+/// Fonts to draw a text of `family` with, and the characters none of them has a glyph for
+/// Every backend and the missing-glyph warnings resolve text fonts through this, so the
+/// warnings name exactly the characters left out of the drawing.
+pub fn resolve_family_fonts(fonts: &dyn FontProvider, family: &str, text: &str) -> (Vec<FontFace>, Vec<char>) {
+    resolve_text_fonts(fonts, fonts.family_font(family), text)
+}
+
+/// Warnings for the texts of a render (text elements and frame names) with characters that
+/// neither their font nor a fallback font can draw
+pub(crate) fn get_text_warnings(elements: &[&ExcalidrawElement], fonts: &dyn FontProvider) -> Vec<Warning> {
+    elements
+        .iter()
+        .filter_map(|el| {
+            let (text, family) = match el.element_type {
+                ElementType::Text => (el.text.as_deref()?, fonts.family_for_id(el.font_family)),
+                ElementType::Frame | ElementType::Magicframe => {
+                    (get_frame_name(el), FRAME_NAME_FONT_FAMILY.to_string())
+                }
+                _ => return None,
+            };
+            let (_, characters) = resolve_family_fonts(fonts, &family, text);
+            (!characters.is_empty()).then(|| Warning::MissingGlyphs { element_id: el.id.clone(), characters })
+        })
+        .collect()
+}
+
+/// Whether a font maps a character to a glyph with an outline
+fn has_outline_glyph(font: &FontRef, ch: char) -> bool {
+    font.charmap()
+        .map(ch)
+        .is_some_and(|glyph_id| font.outline_glyphs().get(glyph_id).is_some())
+}
+
+/// Characters that are not drawn themselves: controls, zero-width joiners and spaces, and
+/// variation selectors
+fn is_ignorable(ch: char) -> bool {
+    ch.is_control()
+        || matches!(
+            ch,
+            '\u{200B}'..='\u{200F}' | '\u{2060}'..='\u{2064}' | '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}'
+        )
+}
+
+/// Family name of a font: the typographic family when set, else the legacy family
+fn get_family_name(font: &FontRef) -> Option<String> {
+    [StringId::TYPOGRAPHIC_FAMILY_NAME, StringId::FAMILY_NAME]
+        .into_iter()
+        .find_map(|id| font.localized_strings(id).english_or_first())
+        .map(|name| name.to_string())
+        .filter(|name| !name.is_empty())
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "ttf" | "otf" | "ttc"))
+}
@@ -0,0 +1,123 @@
+//! Frame helpers shared by the SVG and tiny-skia renderers
+use crate::models::{ElementType, ExcalidrawElement, ViewBox};
+use crate::options::FrameOrder;
+use std::collections::HashMap;
+
+// Excalidraw's FRAME_STYLE
+pub const FRAME_STROKE_COLOR: &str = "#bbbbbb";
+pub const FRAME_STROKE_WIDTH: f64 = 2.0;
+pub const FRAME_RADIUS: f64 = 8.0;
+pub const FRAME_NAME_COLOR: &str = "#999999";
+pub const FRAME_NAME_FONT_SIZE: f64 = 14.0;
+pub const FRAME_NAME_FONT_FAMILY: &str = "Liberation Sans";
+pub const FRAME_NAME_OFFSET_Y: f64 = 3.0;
+
+/// Whether the element is a frame (`frame` or `magicframe`)
+pub fn is_frame_like(element: &ExcalidrawElement) -> bool {
+    matches!(element.element_type, ElementType::Frame | ElementType::Magicframe)
+}
+
+/// Title drawn above a frame, falling back to Excalidraw's default names
+pub fn get_frame_name(element: &ExcalidrawElement) -> &str {
+    match element.name.as_deref() {
+        Some(name) => name,
+        None if element.element_type == ElementType::Magicframe => "AI Frame",
+        None => "Frame",
+    }
+}
+
+/// Baseline of the frame title
+/// Excalidraw bottom-aligns the title `nameOffsetY` above the frame; the descent
+/// below the alphabetic baseline is taken as a quarter of the font size.
+pub fn get_frame_name_baseline(element: &ExcalidrawElement) -> f64 {
+    element.y - FRAME_NAME_OFFSET_Y - FRAME_NAME_FONT_SIZE * 0.25
+}
+
+/// Live frames of a scene, keyed by element id
+pub fn collect_frames(elements: &[ExcalidrawElement]) -> HashMap<&str, &ExcalidrawElement> {
+    elements
+        .iter()
+        .filter(|el| !el.is_deleted && is_frame_like(el))
+        .map(|el| (el.id.as_str(), el))
+        .collect()
+}
+
+/// Frame the element is clipped to, if its `frameId` points at a live frame
+pub fn get_containing_frame<'a>(
+    element: &ExcalidrawElement,
+    frames: &HashMap<&str, &'a ExcalidrawElement>,
+) -> Option<&'a ExcalidrawElement> {
+    element
+        .frame_id
+        .as_deref()
+        .and_then(|frame_id| frames.get(frame_id).copied())
+        .filter(|frame| frame.id != element.id)
+}
+
+/// Live frames in scene order
+pub fn get_frames(elements: &[ExcalidrawElement]) -> Vec<&ExcalidrawElement> {
+    elements
+        .iter()
+        .filter(|el| !el.is_deleted && is_frame_like(el))
+        .collect()
+}
+
+/// Live frames sorted by fractional index or by title; ties keep scene order
+pub fn get_ordered_frames(elements: &[ExcalidrawElement], order: FrameOrder) -> Vec<&ExcalidrawElement> {
+    let mut frames = get_frames(elements);
+    match order {
+        FrameOrder::Index => frames.sort_by(|a, b| a.index.cmp(&b.index)),
+        FrameOrder::Name => frames.sort_by(|a, b| get_frame_name(a).cmp(get_frame_name(b))),
+    }
+    frames
+}
+
+/// Find a frame by id, or else by its title
+pub fn find_frame<'a>(elements: &'a [ExcalidrawElement], name_or_id: &str) -> Option<&'a ExcalidrawElement> {
+    let frames = get_frames(elements);
+    frames
+        .iter()
+        .find(|frame| frame.id == name_or_id)
+        .or_else(|| frames.iter().find(|frame| get_frame_name(frame) == name_or_id))
+        .copied()
+}
+
+/// Viewbox covering exactly the frame's bounds, as Excalidraw exports a single frame
+pub fn get_frame_viewbox(frame: &ExcalidrawElement) -> ViewBox {
+    ViewBox {
+        min_x: frame.x,
+        min_y: frame.y,
+        width: frame.width,
+        height: frame.height,
+    }
+}
+
+/// Elements drawn when exporting a frame on its own: its children, without the frame itself
+pub fn get_frame_children<'a>(
+    elements: &'a [ExcalidrawElement],
+    frame: &ExcalidrawElement,
+) -> Vec<&'a ExcalidrawElement> {
+    elements
+        .iter()
+        .filter(|el| !el.is_deleted && el.frame_id.as_deref() == Some(frame.id.as_str()))
+        .collect()
+}
+
+/// File name stem derived from the frame title, e.g. "Login Flow #2" -> "login-flow-2"
+/// Falls back to the frame id when the title has no usable characters.
+pub fn get_frame_file_stem(frame: &ExcalidrawElement) -> String {
+    let mut stem = String::new();
+    for ch in get_frame_name(frame).chars() {
+        if ch.is_alphanumeric() || ch == '_' {
+            stem.extend(ch.to_lowercase());
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
+        }
+    }
+    let stem = stem.trim_end_matches('-');
+    if stem.is_empty() {
+        frame.id.clone()
+    } else {
+        stem.to_string()
+    }
+}
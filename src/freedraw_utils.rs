@@ -0,0 +1,416 @@
+//! Freedraw stroke outlines, ported from perfect-freehand as used by Excalidraw.
+//!
+//! Excalidraw renders pen strokes as filled outlines whose width varies with
+//! pressure (real or simulated). Both renderers share this geometry.
+use crate::models::ExcalidrawElement;
+
+const RATE_OF_PRESSURE_CHANGE: f64 = 0.275;
+const FIXED_PI: f64 = std::f64::consts::PI + 0.0001;
+
+/// Options for perfect-freehand's `getStroke`
+pub struct StrokeOptions {
+    pub size: f64,
+    pub thinning: f64,
+    pub smoothing: f64,
+    pub streamline: f64,
+    pub simulate_pressure: bool,
+    pub easing: fn(f64) -> f64,
+    /// Whether the stroke is complete (Excalidraw sets this once `lastCommittedPoint` exists)
+    pub last: bool,
+}
+
+struct StrokePoint {
+    point: (f64, f64),
+    pressure: f64,
+    vector: (f64, f64),
+    distance: f64,
+    running_length: f64,
+}
+
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn mul(a: (f64, f64), n: f64) -> (f64, f64) {
+    (a.0 * n, a.1 * n)
+}
+
+fn neg(a: (f64, f64)) -> (f64, f64) {
+    (-a.0, -a.1)
+}
+
+/// Perpendicular of a vector
+fn per(a: (f64, f64)) -> (f64, f64) {
+    (a.1, -a.0)
+}
+
+fn dpr(a: (f64, f64), b: (f64, f64)) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn len(a: (f64, f64)) -> f64 {
+    a.0.hypot(a.1)
+}
+
+/// Unit vector (zero vectors yield NaN in JS; we keep them at zero)
+fn uni(a: (f64, f64)) -> (f64, f64) {
+    let l = len(a);
+    if l == 0.0 { (0.0, 0.0) } else { (a.0 / l, a.1 / l) }
+}
+
+fn dist(a: (f64, f64), b: (f64, f64)) -> f64 {
+    len(sub(a, b))
+}
+
+fn dist2(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let d = sub(a, b);
+    d.0 * d.0 + d.1 * d.1
+}
+
+fn lrp(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    add(a, mul(sub(b, a), t))
+}
+
+/// Project a point along a direction by a distance
+fn prj(a: (f64, f64), b: (f64, f64), c: f64) -> (f64, f64) {
+    add(a, mul(b, c))
+}
+
+fn rot_around(a: (f64, f64), c: (f64, f64), r: f64) -> (f64, f64) {
+    let (s, co) = r.sin_cos();
+    let px = a.0 - c.0;
+    let py = a.1 - c.1;
+    (px * co - py * s + c.0, px * s + py * co + c.1)
+}
+
+fn get_stroke_radius(size: f64, thinning: f64, pressure: f64, easing: fn(f64) -> f64) -> f64 {
+    size * easing(0.5 - thinning * (0.5 - pressure))
+}
+
+/// Smooth the input points and annotate them with pressure, direction and length
+fn get_stroke_points(points: &[(f64, f64, f64)], options: &StrokeOptions) -> Vec<StrokePoint> {
+    if points.is_empty() {
+        return vec![];
+    }
+
+    let t = 0.15 + (1.0 - options.streamline) * 0.85;
+    let mut pts: Vec<(f64, f64, f64)> = points.to_vec();
+
+    // Two points: interpolate so the stroke has something to smooth
+    if pts.len() == 2 {
+        let last = pts[1];
+        pts.truncate(1);
+        for i in 1..5 {
+            let (x, y) = lrp((pts[0].0, pts[0].1), (last.0, last.1), i as f64 / 4.0);
+            pts.push((x, y, last.2));
+        }
+    }
+
+    // One point: add a second so the stroke has a direction
+    if pts.len() == 1 {
+        let p = pts[0];
+        pts.push((p.0 + 1.0, p.1 + 1.0, p.2));
+    }
+
+    let mut stroke_points = vec![StrokePoint {
+        point: (pts[0].0, pts[0].1),
+        pressure: if pts[0].2 >= 0.0 { pts[0].2 } else { 0.25 },
+        vector: (1.0, 1.0),
+        distance: 0.0,
+        running_length: 0.0,
+    }];
+
+    let mut has_reached_minimum_length = false;
+    let mut running_length = 0.0;
+    let max = pts.len() - 1;
+
+    for (i, p) in pts.iter().enumerate().skip(1) {
+        let prev_point = stroke_points[stroke_points.len() - 1].point;
+        let point = if options.last && i == max {
+            (p.0, p.1)
+        } else {
+            lrp(prev_point, (p.0, p.1), t)
+        };
+
+        if prev_point == point {
+            continue;
+        }
+
+        let distance = dist(point, prev_point);
+        running_length += distance;
+
+        if i < max && !has_reached_minimum_length {
+            if running_length < options.size {
+                continue;
+            }
+            has_reached_minimum_length = true;
+        }
+
+        stroke_points.push(StrokePoint {
+            point,
+            pressure: if p.2 >= 0.0 { p.2 } else { 0.5 },
+            vector: uni(sub(prev_point, point)),
+            distance,
+            running_length,
+        });
+    }
+
+    stroke_points[0].vector = stroke_points.get(1).map(|p| p.vector).unwrap_or((0.0, 0.0));
+    stroke_points
+}
+
+/// Compute the outline polygon around the smoothed stroke points
+fn get_stroke_outline_points(points: &[StrokePoint], options: &StrokeOptions) -> Vec<(f64, f64)> {
+    let size = options.size;
+    if points.is_empty() || size <= 0.0 {
+        return vec![];
+    }
+
+    let total_length = points[points.len() - 1].running_length;
+    let min_distance = (size * options.smoothing).powi(2);
+
+    let mut left_pts: Vec<(f64, f64)> = Vec::new();
+    let mut right_pts: Vec<(f64, f64)> = Vec::new();
+
+    let simulated_pressure = |acc: f64, distance: f64| {
+        let sp = (distance / size).min(1.0);
+        let rp = (1.0 - sp).min(1.0);
+        (acc + (rp - acc) * (sp * RATE_OF_PRESSURE_CHANGE)).min(1.0)
+    };
+
+    // Average the first few pressures to avoid a blob at the start
+    let mut prev_pressure = points.iter().take(10).fold(points[0].pressure, |acc, curr| {
+        let pressure = if options.simulate_pressure {
+            simulated_pressure(acc, curr.distance)
+        } else {
+            curr.pressure
+        };
+        (acc + pressure) / 2.0
+    });
+
+    let mut radius = get_stroke_radius(size, options.thinning, points[points.len() - 1].pressure, options.easing);
+    let mut first_radius: Option<f64> = None;
+    let mut prev_vector = points[0].vector;
+    let mut pl = points[0].point;
+    let mut pr = pl;
+    let mut is_prev_point_sharp_corner = false;
+
+    for (i, sp) in points.iter().enumerate() {
+        let mut pressure = sp.pressure;
+        let StrokePoint { point, vector, distance, running_length, .. } = *sp;
+
+        // Skip points too close to the end, they distort the end cap
+        if i < points.len() - 1 && total_length - running_length < 3.0 {
+            continue;
+        }
+
+        if options.thinning != 0.0 {
+            if options.simulate_pressure {
+                pressure = simulated_pressure(prev_pressure, distance);
+            }
+            radius = get_stroke_radius(size, options.thinning, pressure, options.easing);
+        } else {
+            radius = size / 2.0;
+        }
+
+        if first_radius.is_none() {
+            first_radius = Some(radius);
+        }
+
+        radius = radius.max(0.01);
+
+        let next_vector = if i < points.len() - 1 { points[i + 1].vector } else { vector };
+        let next_dpr = if i < points.len() - 1 { dpr(vector, next_vector) } else { 1.0 };
+        let prev_dpr = dpr(vector, prev_vector);
+
+        let is_point_sharp_corner = prev_dpr < 0.0 && !is_prev_point_sharp_corner;
+        let is_next_point_sharp_corner = next_dpr < 0.0;
+
+        if is_point_sharp_corner || is_next_point_sharp_corner {
+            // Round the corner with a half circle
+            let offset = mul(per(prev_vector), radius);
+            let step = 1.0 / 13.0;
+            let mut t = 0.0;
+            let mut tl = pl;
+            let mut tr = pr;
+            while t <= 1.0 {
+                tl = rot_around(sub(point, offset), point, FIXED_PI * t);
+                left_pts.push(tl);
+                tr = rot_around(add(point, offset), point, FIXED_PI * -t);
+                right_pts.push(tr);
+                t += step;
+            }
+            pl = tl;
+            pr = tr;
+            if is_next_point_sharp_corner {
+                is_prev_point_sharp_corner = true;
+            }
+            continue;
+        }
+
+        is_prev_point_sharp_corner = false;
+
+        if i == points.len() - 1 {
+            let offset = mul(per(vector), radius);
+            left_pts.push(sub(point, offset));
+            right_pts.push(add(point, offset));
+            continue;
+        }
+
+        let offset = mul(per(lrp(next_vector, vector, next_dpr)), radius);
+
+        let tl = sub(point, offset);
+        if i <= 1 || dist2(pl, tl) > min_distance {
+            left_pts.push(tl);
+            pl = tl;
+        }
+
+        let tr = add(point, offset);
+        if i <= 1 || dist2(pr, tr) > min_distance {
+            right_pts.push(tr);
+            pr = tr;
+        }
+
+        prev_pressure = pressure;
+        prev_vector = vector;
+    }
+
+    let first_point = points[0].point;
+    let last_point = if points.len() > 1 {
+        points[points.len() - 1].point
+    } else {
+        add(points[0].point, (1.0, 1.0))
+    };
+
+    // A single point becomes a dot
+    if points.len() == 1 {
+        let start = prj(
+            first_point,
+            uni(per(sub(first_point, last_point))),
+            -first_radius.unwrap_or(radius),
+        );
+        let step = 1.0 / 13.0;
+        let mut dot_pts = Vec::new();
+        let mut t = step;
+        while t <= 1.0 {
+            dot_pts.push(rot_around(start, first_point, FIXED_PI * 2.0 * t));
+            t += step;
+        }
+        return dot_pts;
+    }
+
+    // Round start cap
+    let mut start_cap = Vec::new();
+    if let Some(&right_start) = right_pts.first() {
+        let step = 1.0 / 13.0;
+        let mut t = step;
+        while t <= 1.0 {
+            start_cap.push(rot_around(right_start, first_point, FIXED_PI * t));
+            t += step;
+        }
+    }
+
+    // Round end cap
+    let mut end_cap = Vec::new();
+    let direction = per(neg(points[points.len() - 1].vector));
+    let start = prj(last_point, direction, radius);
+    let step = 1.0 / 29.0;
+    let mut t = step;
+    while t < 1.0 {
+        end_cap.push(rot_around(start, last_point, FIXED_PI * 3.0 * t));
+        t += step;
+    }
+
+    right_pts.reverse();
+    left_pts
+        .into_iter()
+        .chain(end_cap)
+        .chain(right_pts)
+        .chain(start_cap)
+        .collect()
+}
+
+/// perfect-freehand's `getStroke`: input points are (x, y, pressure)
+pub fn get_stroke(points: &[(f64, f64, f64)], options: &StrokeOptions) -> Vec<(f64, f64)> {
+    get_stroke_outline_points(&get_stroke_points(points, options), options)
+}
+
+/// Compute the outline of a freedraw element in element-local coordinates,
+/// using the same stroke options as Excalidraw's `getFreeDrawSvgPath`
+pub fn get_freedraw_outline(element: &ExcalidrawElement) -> Vec<(f64, f64)> {
+    let simulate_pressure = element.simulate_pressure.unwrap_or(true);
+    let points = element.points.as_deref().unwrap_or(&[]);
+    let pressures = element.pressures.as_deref().unwrap_or(&[]);
+
+    let input: Vec<(f64, f64, f64)> = if points.is_empty() {
+        vec![(0.0, 0.0, 0.5)]
+    } else if simulate_pressure {
+        points.iter().map(|&(x, y)| (x, y, 0.5)).collect()
+    } else {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| (x, y, pressures.get(i).copied().unwrap_or(0.5)))
+            .collect()
+    };
+
+    let options = StrokeOptions {
+        size: element.stroke_width * 4.25,
+        thinning: 0.6,
+        smoothing: 0.5,
+        streamline: 0.5,
+        simulate_pressure,
+        // easeOutSine
+        easing: |t| (t * std::f64::consts::PI / 2.0).sin(),
+        last: element.last_committed_point.is_some(),
+    };
+
+    get_stroke(&input, &options)
+}
+
+/// Whether a freedraw path closes on itself, in which case Excalidraw fills it
+/// with the background color (mirrors `isPathALoop` with LINE_CONFIRM_THRESHOLD)
+pub fn is_path_a_loop(points: &[(f64, f64)]) -> bool {
+    const LINE_CONFIRM_THRESHOLD: f64 = 8.0;
+    if points.len() < 3 {
+        return false;
+    }
+    let first = points[0];
+    let last = points[points.len() - 1];
+    dist(first, last) <= LINE_CONFIRM_THRESHOLD
+}
+
+/// Midpoints between consecutive outline points, closing back to the start.
+/// The outline is drawn as quadratic curves through these midpoints, with
+/// each outline point acting as the control point.
+pub fn get_stroke_midpoints(outline: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    outline
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let next = outline[(i + 1) % outline.len()];
+            ((p.0 + next.0) / 2.0, (p.1 + next.1) / 2.0)
+        })
+        .collect()
+}
+
+/// Convert outline points to an SVG path (perfect-freehand's `getSvgPathFromStroke`)
+/// The `offset` is added to every point to produce absolute coordinates.
+pub fn get_svg_path_from_stroke(outline: &[(f64, f64)], offset: (f64, f64)) -> String {
+    if outline.is_empty() {
+        return String::new();
+    }
+
+    let (ox, oy) = offset;
+    let mids = get_stroke_midpoints(outline);
+    let mut d = format!("M {} {} Q", outline[0].0 + ox, outline[0].1 + oy);
+    for (p, m) in outline.iter().zip(mids.iter()) {
+        d.push_str(&format!(" {} {} {} {}", p.0 + ox, p.1 + oy, m.0 + ox, m.1 + oy));
+    }
+    d.push_str(&format!(" L {} {} Z", outline[0].0 + ox, outline[0].1 + oy));
+    d
+}
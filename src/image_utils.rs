@@ -0,0 +1,115 @@
+//! Image element utilities: data URL decoding, raster/SVG decoding and crop geometry
+use crate::models::{ExcalidrawElement, FileData};
+use base64::Engine;
+use resvg::usvg;
+use tiny_skia::{IntSize, Pixmap, Transform};
+
+/// Split a `data:` URL into its MIME type and decoded bytes
+/// Supports base64 payloads and plain (non-base64) payloads such as `data:image/svg+xml;utf8,<svg...`
+pub fn parse_data_url(data_url: &str) -> Option<(String, Vec<u8>)> {
+    let rest = data_url.strip_prefix("data:")?;
+    let (header, payload) = rest.split_once(',')?;
+
+    let mut parts = header.split(';');
+    let mime_type = parts.next().unwrap_or("").to_string();
+    let is_base64 = parts.any(|p| p.eq_ignore_ascii_case("base64"));
+
+    let bytes = if is_base64 {
+        let cleaned: String = payload.chars().filter(|c| !c.is_whitespace()).collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(cleaned.as_bytes())
+            .or_else(|_| base64::engine::general_purpose::STANDARD_NO_PAD.decode(cleaned.trim_end_matches('=').as_bytes()))
+            .ok()?
+    } else {
+        payload.as_bytes().to_vec()
+    };
+
+    Some((mime_type, bytes))
+}
+
+/// Decode an RGBA8 image into a premultiplied tiny-skia pixmap
+fn rgba_to_pixmap(img: image::RgbaImage) -> Option<Pixmap> {
+    let (width, height) = img.dimensions();
+    let mut data = img.into_raw();
+    for px in data.chunks_exact_mut(4) {
+        let a = px[3] as u16;
+        px[0] = ((px[0] as u16 * a + 127) / 255) as u8;
+        px[1] = ((px[1] as u16 * a + 127) / 255) as u8;
+        px[2] = ((px[2] as u16 * a + 127) / 255) as u8;
+    }
+    Pixmap::from_vec(data, IntSize::from_wh(width, height)?)
+}
+
+/// Rasterize an SVG image at the given pixel size
+fn svg_to_pixmap(bytes: &[u8], size: (u32, u32)) -> Option<Pixmap> {
+    let tree = usvg::Tree::from_data(bytes, &usvg::Options::default()).ok()?;
+    let svg_size = tree.size();
+    let width = size.0.max(1);
+    let height = size.1.max(1);
+
+    let mut pixmap = Pixmap::new(width, height)?;
+    let transform = Transform::from_scale(
+        width as f32 / svg_size.width(),
+        height as f32 / svg_size.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    Some(pixmap)
+}
+
+/// Whether the MIME type or payload denotes an SVG image
+fn is_svg(mime_type: &str, bytes: &[u8]) -> bool {
+    if mime_type.eq_ignore_ascii_case("image/svg+xml") {
+        return true;
+    }
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(256)]);
+    let head = head.trim_start();
+    head.starts_with("<svg") || (head.starts_with("<?xml") && head.contains("<svg"))
+}
+
+/// Decode image bytes (PNG, JPEG, WebP, GIF first frame or SVG) into a pixmap
+/// Raster images decode at their natural size; SVG images are rasterized at `svg_size`
+pub fn decode_image(mime_type: &str, bytes: &[u8], svg_size: (u32, u32)) -> Option<Pixmap> {
+    if is_svg(mime_type, bytes) {
+        return svg_to_pixmap(bytes, svg_size);
+    }
+    let img = image::load_from_memory(bytes).ok()?;
+    rgba_to_pixmap(img.to_rgba8())
+}
+
+/// Decode a scene file entry into a pixmap
+pub fn load_file_image(file: &FileData, svg_size: (u32, u32)) -> Option<Pixmap> {
+    let (url_mime, bytes) = parse_data_url(&file.data_url)?;
+    let mime_type = if file.mime_type.is_empty() { url_mime.as_str() } else { file.mime_type.as_str() };
+    decode_image(mime_type, &bytes, svg_size)
+}
+
+/// Size of the whole (uncropped) image when drawn at the element's scale
+/// Excalidraw's `getUncroppedWidthAndHeight`
+pub fn get_uncropped_size(element: &ExcalidrawElement) -> (f64, f64) {
+    match element.crop {
+        Some(ref crop) if crop.width > 0.0 && crop.height > 0.0 => (
+            element.width / (crop.width / crop.natural_width),
+            element.height / (crop.height / crop.natural_height),
+        ),
+        _ => (element.width, element.height),
+    }
+}
+
+/// Crop rectangle (x, y, width, height) in the pixel space of a decoded image
+/// Falls back to the whole image when the element is not cropped
+pub fn get_image_crop_rect(element: &ExcalidrawElement, image_width: f64, image_height: f64) -> (f64, f64, f64, f64) {
+    match element.crop {
+        Some(ref crop) if crop.natural_width > 0.0 && crop.natural_height > 0.0 => {
+            let rx = image_width / crop.natural_width;
+            let ry = image_height / crop.natural_height;
+            (crop.x * rx, crop.y * ry, crop.width * rx, crop.height * ry)
+        }
+        _ => (0.0, 0.0, image_width, image_height),
+    }
+}
+
+/// Horizontal/vertical flip factors from the element's `scale`
+pub fn get_image_flip(element: &ExcalidrawElement) -> (f64, f64) {
+    let (sx, sy) = element.scale.unwrap_or((1.0, 1.0));
+    (if sx < 0.0 { -1.0 } else { 1.0 }, if sy < 0.0 { -1.0 } else { 1.0 })
+}
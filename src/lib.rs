@@ -1,19 +1,47 @@
 pub mod arrow_utils;
+pub mod backend;
+pub mod bound_text_utils;
 pub mod color_utils;
 pub mod converter;
+pub mod embed_utils;
+pub mod encoder;
+pub mod error;
 pub mod font_utils;
+pub mod fonts;
+pub mod frame_utils;
+pub mod freedraw_utils;
+pub mod image_utils;
+pub mod loader;
+pub mod lz_string;
 pub mod math_utils;
 pub mod models;
+pub mod obsidian;
+pub mod options;
 pub mod rect_utils;
 pub mod renderer;
+pub mod renderer_pdf;
 pub mod renderer_skia;
+pub mod restore;
 pub mod stroke_utils;
+pub mod text_layout;
 pub mod utils;
 
-pub use converter::convert_svg_to_png;
-pub use models::{ExcalidrawData, ExcalidrawElement};
-pub use renderer::generate_svg;
-pub use renderer_skia::render_to_png;
+pub use backend::{PdfRenderer, Renderer, ResvgRenderer, SkiaRenderer, SvgRenderer};
+pub use converter::{
+    convert_svg_to_pixmap, convert_svg_to_pixmap_with_fonts, convert_svg_to_png, convert_svg_to_png_bytes,
+};
+pub use encoder::RasterFormat;
+pub use error::{ExcaliosaError, Warning};
+pub use fonts::{FontData, FontFace, FontProvider, FontSet};
+pub use loader::{load_scene, load_scene_file, load_scene_file_with_warnings, load_scene_with_warnings};
+pub use models::{ExcalidrawData, ExcalidrawElement, FileData, ViewBox};
+pub use options::{FrameOrder, RenderOptions, Theme};
+pub use renderer::{generate_svg, generate_svg_with_options};
+pub use renderer_pdf::{render_to_pdf, render_to_pdf_bytes, render_to_pdf_writer};
+pub use renderer_skia::{
+    render_to_pixmap, render_to_png, render_to_png_bytes, render_to_png_with_options, render_to_raster,
+    render_to_raster_bytes, render_to_raster_writer, render_to_rgba,
+};
 pub use utils::calculate_viewbox;
 
 #[cfg(test)]
@@ -0,0 +1,55 @@
+//! Scene input: Excalidraw JSON, PNG and SVG exports that embed their scene, or Obsidian
+//! `.excalidraw.md` documents
+use crate::embed_utils::{decode_png_scene, decode_svg_scene};
+use crate::error::{ExcaliosaError, Result, Warning};
+use crate::models::ExcalidrawData;
+use crate::obsidian::{is_obsidian_markdown, load_obsidian_markdown};
+use crate::restore::parse_scene;
+use std::path::Path;
+
+/// Parse a scene from file contents
+/// See [`load_scene_with_warnings`]; warnings about dropped elements are discarded.
+pub fn load_scene(bytes: &[u8]) -> Result<ExcalidrawData> {
+    load_scene_with_warnings(bytes).map(|(data, _)| data)
+}
+
+/// Parse a scene from file contents, with a warning for each element that was dropped
+/// PNGs are recognized by their signature, SVGs by their root element and Obsidian documents
+/// by their drawing block; anything else is read as Excalidraw JSON. Missing and legacy
+/// fields are restored like Excalidraw does.
+pub fn load_scene_with_warnings(bytes: &[u8]) -> Result<(ExcalidrawData, Vec<Warning>)> {
+    let mut warnings = Vec::new();
+    let json = if bytes.starts_with(b"\x89PNG") {
+        decode_png_scene(bytes)?
+    } else {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| ExcaliosaError::Parse("Input is neither a PNG nor UTF-8 text".to_string()))?;
+        if is_svg(text) {
+            decode_svg_scene(text)?
+        } else if is_obsidian_markdown(text) {
+            let data = load_obsidian_markdown(text, &mut warnings)?;
+            return Ok((data, warnings));
+        } else {
+            text.to_string()
+        }
+    };
+    let data = parse_scene(&json, &mut warnings)?;
+    Ok((data, warnings))
+}
+
+/// Read and parse a scene file
+pub fn load_scene_file(path: &Path) -> Result<ExcalidrawData> {
+    load_scene_file_with_warnings(path).map(|(data, _)| data)
+}
+
+/// Read and parse a scene file, with a warning for each element that was dropped
+pub fn load_scene_file_with_warnings(path: &Path) -> Result<(ExcalidrawData, Vec<Warning>)> {
+    let bytes = std::fs::read(path).map_err(|source| ExcaliosaError::Read { path: path.to_path_buf(), source })?;
+    load_scene_with_warnings(&bytes)
+}
+
+/// Whether text looks like an SVG document rather than JSON
+fn is_svg(text: &str) -> bool {
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    text.starts_with('<') && text.contains("<svg")
+}
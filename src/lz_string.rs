@@ -0,0 +1,118 @@
+//! LZ-String decompression, as used by Obsidian's Excalidraw plugin for `compressed-json`
+//! A port of `decompressFromBase64` from lz-string 1.4. Entries are UTF-16 code units, like
+//! the JavaScript strings the format was designed for.
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+
+/// Decompress a string produced by `LZString.compressToBase64`
+/// Whitespace is ignored. Returns `None` for malformed input.
+pub fn decompress_from_base64(input: &str) -> Option<String> {
+    let values = input
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .map(|b| BASE64_ALPHABET.iter().position(|&a| a == b).map(|v| v as u32))
+        .collect::<Option<Vec<u32>>>()?;
+    if values.is_empty() {
+        return None;
+    }
+    let units = decompress(&values, 32)?;
+    String::from_utf16(&units).ok()
+}
+
+/// Bit reader over the input values, `reset_value` being the highest bit of a value
+struct BitReader<'a> {
+    values: &'a [u32],
+    reset_value: u32,
+    val: u32,
+    position: u32,
+    index: usize,
+}
+
+impl BitReader<'_> {
+    /// Read `num_bits` bits, least significant first
+    fn read(&mut self, num_bits: u32) -> u32 {
+        let mut bits = 0;
+        for power in 0..num_bits {
+            let bit = self.val & self.position;
+            self.position >>= 1;
+            if self.position == 0 {
+                self.position = self.reset_value;
+                self.val = self.values.get(self.index).copied().unwrap_or(0);
+                self.index += 1;
+            }
+            if bit > 0 {
+                bits |= 1 << power;
+            }
+        }
+        bits
+    }
+}
+
+fn decompress(values: &[u32], reset_value: u32) -> Option<Vec<u16>> {
+    let mut reader = BitReader {
+        values,
+        reset_value,
+        val: values[0],
+        position: reset_value,
+        index: 1,
+    };
+
+    // Codes 0-2 are reserved: an 8-bit char, a 16-bit char, end of stream
+    let mut dictionary: Vec<Vec<u16>> = vec![Vec::new(); 3];
+    let mut enlarge_in: u32 = 4;
+    let mut num_bits: u32 = 3;
+
+    let first = match reader.read(2) {
+        0 => reader.read(8) as u16,
+        1 => reader.read(16) as u16,
+        _ => return Some(Vec::new()),
+    };
+    dictionary.push(vec![first]);
+    let mut w = vec![first];
+    let mut result = w.clone();
+
+    loop {
+        if reader.index > values.len() {
+            return Some(Vec::new());
+        }
+
+        let mut code = reader.read(num_bits) as usize;
+        match code {
+            0 | 1 => {
+                let unit = if code == 0 { reader.read(8) } else { reader.read(16) } as u16;
+                dictionary.push(vec![unit]);
+                code = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            2 => return Some(result),
+            _ => {}
+        }
+
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+
+        let entry = match dictionary.get(code) {
+            Some(entry) => entry.clone(),
+            None if code == dictionary.len() => {
+                let mut entry = w.clone();
+                entry.push(w[0]);
+                entry
+            }
+            None => return None,
+        };
+        result.extend_from_slice(&entry);
+
+        let mut added = w;
+        added.push(entry[0]);
+        dictionary.push(added);
+        enlarge_in -= 1;
+        w = entry;
+
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+    }
+}
@@ -1,19 +1,28 @@
 use anyhow::{Context, Result};
 use clap::Parser;
-use excaliosa::{color_utils::parse_color_result, convert_svg_to_png, generate_svg, render_to_png};
+use excaliosa::color_utils::parse_color_result;
+use excaliosa::frame_utils::{find_frame, get_frame_file_stem, get_frame_name, get_frames};
+use excaliosa::{
+    load_scene_file_with_warnings, ExcalidrawElement, FontSet, FrameOrder, PdfRenderer, RasterFormat, RenderOptions, Renderer, ResvgRenderer, SkiaRenderer,
+    SvgRenderer, Theme, ViewBox,
+};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
 
 #[derive(Parser, Debug)]
 #[command(name = "excaliosa")]
-#[command(about = "Convert Excalidraw JSON to PNG or SVG", long_about = None)]
+#[command(about = "Convert Excalidraw JSON to PNG, JPEG, WebP, SVG or PDF", long_about = None)]
 struct Args {
-    /// Path to the Excalidraw JSON file
+    /// Path to the Excalidraw JSON file, a PNG/SVG export with an embedded scene, or an
+    /// Obsidian .excalidraw.md document
     #[arg(value_name = "FILE")]
     input: PathBuf,
 
-    /// Output file path (defaults to input filename with .png extension)
-    /// Use .svg extension to export as SVG, .png for PNG
+    /// Output file path (defaults to input filename with the --format extension)
+    /// Use .svg extension to export as SVG, .pdf for PDF, .png for PNG, .jpg/.jpeg for JPEG
+    /// and .webp for WebP
     #[arg(short, long, value_name = "FILE")]
     output: Option<PathBuf>,
 
@@ -21,21 +30,218 @@ struct Args {
     #[arg(long)]
     legacy: bool,
 
-    /// Background color hex (e.g. #RRGGBB or #RRGGBBAA). Use "transparent" for full transparency
+    /// Background color hex (e.g. #RRGGBB or #RRGGBBAA). Use "transparent" for full transparency.
+    /// Defaults to the scene's appState.viewBackgroundColor (transparent if exportBackground is off)
     #[arg(short = 'b', long = "background", value_name = "HEX")] 
     background: Option<String>,
 
-    /// PNG compression quality (0-100). Higher values produce smaller files but slower encoding.
-    /// 0-25: Fast encoding, 26-75: Balanced, 76-100: Best compression.
-    /// Only applies to PNG output. Default: 75
-    #[arg(short = 'q', long = "quality", value_name = "0-100", default_value = "75", value_parser = clap::value_parser!(u8).range(0..=100))]
-    quality: u8,
+    /// Raster quality (0-100). For PNG, higher values produce smaller files but slower encoding
+    /// (0-25: Fast encoding, 26-75: Balanced, 76-100: Best compression).
+    /// For JPEG and WebP, the lossy encoder quality. Default: 75
+    #[arg(short = 'q', long = "quality", value_name = "0-100", value_parser = clap::value_parser!(u8).range(0..=100))]
+    quality: Option<u8>,
 
     /// Target DPI for output scaling. Assumes source is 96 DPI.
     /// Lower values reduce output size (e.g., --dpi 48 halves dimensions).
-    /// Only applies to PNG output. Default: None (use original dimensions)
+    /// Overrides --scale. Applies to raster output (PNG, JPEG, WebP); SVG and PDF ignore it.
+    /// Default: None (use original dimensions)
     #[arg(long = "dpi", value_name = "DPI")]
     dpi: Option<u32>,
+
+    /// Scale factor for raster output (e.g. 2 doubles the dimensions); SVG and PDF ignore it.
+    /// Defaults to the scene's appState.exportScale, or 1
+    #[arg(long = "scale", value_name = "FACTOR")]
+    scale: Option<f64>,
+
+    /// Padding around the scene in pixels.
+    /// Defaults to the scene's appState.exportPadding, or 40
+    #[arg(long = "padding", value_name = "PX")]
+    padding: Option<f64>,
+
+    /// Export only the frame with this name or id, cropped to the frame's bounds.
+    /// Can be repeated: --output is then a directory receiving one image per frame,
+    /// named like --all-frames
+    #[arg(long = "frame", value_name = "NAME|ID", conflicts_with = "all_frames")]
+    frames: Vec<String>,
+
+    /// Export only this rectangle of the scene, in scene coordinates, without padding
+    /// (e.g. --region 0,0,800,600)
+    #[arg(long = "region", value_name = "X,Y,WIDTH,HEIGHT", value_parser = parse_region, conflicts_with = "all_frames")]
+    region: Option<ViewBox>,
+
+    /// Export every frame as a separate image into this directory.
+    /// Files are named after the frame titles (e.g. "Login Flow" -> login-flow.png)
+    #[arg(long = "all-frames", value_name = "DIR", conflicts_with = "output")]
+    all_frames: Option<PathBuf>,
+
+    /// Color theme: light or dark (Excalidraw's dark export).
+    /// Defaults to the scene's appState.theme, or light
+    #[arg(long = "theme", value_name = "THEME", value_parser = ["light", "dark"])]
+    theme: Option<String>,
+
+    /// Write a PDF with one page per frame, ordered by the frames' fractional index
+    /// (default) or by their names. A scene without frames becomes a single page
+    #[arg(long = "frame-pages", value_name = "ORDER", num_args = 0..=1, default_missing_value = "index",
+          value_parser = ["index", "name"], conflicts_with_all = ["frames", "all_frames"])]
+    frame_pages: Option<String>,
+
+    /// Output format: png, jpg, jpeg, webp, svg or pdf. Overrides the output file extension.
+    /// Default: the output file extension, or png
+    #[arg(long = "format", value_name = "FORMAT", value_parser = ["png", "jpg", "jpeg", "webp", "svg", "pdf"])]
+    format: Option<String>,
+
+    /// Encode WebP output losslessly, ignoring --quality. WebP output is always lossless
+    /// unless excaliosa is built with the `webp-lossy` feature
+    #[arg(long = "lossless")]
+    lossless: bool,
+
+    /// Embed the scene in PNG or SVG output (PNG tEXt chunk, SVG metadata), so the image
+    /// can be opened and edited again in Excalidraw
+    #[arg(long = "embed-scene")]
+    embed_scene: bool,
+
+    /// Directory of extra .ttf/.otf/.ttc fonts, searched recursively. Text whose family
+    /// matches a font's family name is drawn with it. Can be repeated
+    #[arg(long = "font-dir", value_name = "DIR")]
+    font_dirs: Vec<PathBuf>,
+
+    /// Draw a font family, or an Excalidraw font id such as 4 ("Local font"), with a font
+    /// file (e.g. --font "Excalifont=comic.ttf" or --font 4=corporate.otf). Can be repeated
+    #[arg(long = "font", value_name = "FAMILY=PATH")]
+    fonts: Vec<String>,
+
+    /// Font family tried first for characters the text's font has no glyph for (e.g. CJK or
+    /// emoji), before the other fonts. Can be repeated
+    #[arg(long = "fallback-font", value_name = "FAMILY")]
+    fallback_fonts: Vec<String>,
+
+    /// Also fall back to system fonts (found through fontconfig) for missing characters. The
+    /// output then depends on the fonts installed on the machine
+    #[arg(long = "system-fonts")]
+    system_fonts: bool,
+}
+
+/// Parse a --region value: four comma-separated numbers
+fn parse_region(value: &str) -> std::result::Result<ViewBox, String> {
+    let numbers = value
+        .split(',')
+        .map(|n| n.trim().parse::<f64>())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    match numbers[..] {
+        [min_x, min_y, width, height] if width > 0.0 && height > 0.0 => Ok(ViewBox { min_x, min_y, width, height }),
+        [_, _, _, _] => Err("width and height must be positive".to_string()),
+        _ => Err("expected X,Y,WIDTH,HEIGHT".to_string()),
+    }
+}
+
+/// Embedded fonts plus the --font-dir and --font fonts, with the fallback chain
+fn load_fonts(args: &Args) -> Result<FontSet> {
+    let mut font_set = FontSet::new();
+    for dir in &args.font_dirs {
+        font_set
+            .add_font_dir(dir)
+            .with_context(|| format!("Failed to load fonts from {dir:?}"))?;
+    }
+    for font in &args.fonts {
+        let (family, path) = font
+            .split_once('=')
+            .with_context(|| format!("Invalid --font value {font:?}. Use FAMILY=PATH"))?;
+        font_set.map_font_file(family, Path::new(path))?;
+    }
+    for family in &args.fallback_fonts {
+        font_set.add_fallback_family(family.as_str());
+    }
+    if args.system_fonts {
+        font_set.add_system_fonts();
+    }
+    Ok(font_set)
+}
+
+/// Write one image in the given format ("svg", "pdf" or a raster format)
+fn write_output(
+    data: &excaliosa::ExcalidrawData,
+    output_path: &Path,
+    format: &str,
+    options: &RenderOptions,
+    legacy: bool,
+    lossless: bool,
+) -> Result<()> {
+    if options.embed_scene && format != "png" && format != "svg" {
+        anyhow::bail!("--embed-scene requires PNG or SVG output");
+    }
+
+    match format {
+        "svg" => SvgRenderer
+            .render_to_file(data, output_path, options)
+            .with_context(|| format!("Failed to render SVG: {output_path:?}")),
+        "pdf" => PdfRenderer
+            .render_to_file(data, output_path, options)
+            .with_context(|| format!("Failed to render PDF: {output_path:?}")),
+        _ => {
+            let format = match RasterFormat::from_extension(format).unwrap_or_default() {
+                RasterFormat::WebP if lossless => RasterFormat::WebPLossless,
+                raster_format => raster_format,
+            };
+            let name = format.extension().to_uppercase();
+
+            if legacy {
+                // Legacy SVG + resvg approach
+                ResvgRenderer { format }
+                    .render_to_file(data, output_path, options)
+                    .with_context(|| format!("Failed to convert to {name}: {output_path:?}"))
+            } else {
+                // Use rough_tiny_skia renderer (direct raster output)
+                SkiaRenderer { format }
+                    .render_to_file(data, output_path, options)
+                    .with_context(|| format!("Failed to render {name}: {output_path:?}"))
+            }
+        }
+    }
+}
+
+/// Warn that --quality has no effect on WebP output when lossy WebP is not built in
+fn warn_ignored_quality(quality: Option<u8>, lossless: bool, format: &str) {
+    if format == "webp" && quality.is_some() && !lossless && !RasterFormat::LOSSY_WEBP {
+        eprintln!("Warning: --quality is ignored: lossy WebP needs the `webp-lossy` feature, writing lossless WebP");
+    }
+}
+
+/// Write each frame as its own image into `dir`, named after the frame titles
+fn export_frames(
+    data: &excaliosa::ExcalidrawData,
+    frames: &[&ExcalidrawElement],
+    dir: &Path,
+    args: &Args,
+    options: &RenderOptions,
+    print_warnings: impl Fn(),
+) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create output directory: {dir:?}"))?;
+
+    let format = args.format.as_deref().unwrap_or("png");
+    warn_ignored_quality(args.quality, args.lossless, format);
+    let mut used_stems: HashMap<String, usize> = HashMap::new();
+    for frame in frames {
+        let stem = get_frame_file_stem(frame);
+        let count = used_stems.entry(stem.clone()).or_insert(0);
+        *count += 1;
+        let file_name = if *count == 1 {
+            format!("{stem}.{format}")
+        } else {
+            format!("{stem}-{count}.{format}")
+        };
+        let output_path = dir.join(file_name);
+
+        let frame_options = RenderOptions {
+            frame: Some(frame.id.clone()),
+            ..options.clone()
+        };
+        write_output(data, &output_path, format, &frame_options, args.legacy, args.lossless)?;
+        print_warnings();
+        println!("Exported frame \"{}\" to {}", get_frame_name(frame), output_path.display());
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -49,61 +255,108 @@ fn main() -> Result<()> {
         .transpose()
         .with_context(|| "Invalid --background value. Use #RRGGBB or #RRGGBBAA or 'transparent'.")?;
 
-    // Read the JSON file
-    let json_content = fs::read_to_string(&args.input)
-        .with_context(|| format!("Failed to read input file: {:?}", args.input))?;
-
-    // Parse the JSON
-    let excalidraw_data: excaliosa::ExcalidrawData = serde_json::from_str(&json_content)
-        .context("Failed to parse Excalidraw JSON")?;
-
-    // Determine output path
-    let output_path = args.output.unwrap_or_else(|| {
-        let mut path = args.input.clone();
-        path.set_extension("png");
-        path
-    });
-
-    // Check if output is SVG or PNG based on extension
-    let extension = output_path
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("png");
-
-    match extension.to_lowercase().as_str() {
-        "svg" => {
-            // Generate SVG directly
-            let svg_content = generate_svg(&excalidraw_data, bg_rgba);
-            fs::write(&output_path, svg_content)
-                .with_context(|| format!("Failed to write SVG file: {output_path:?}"))?;
-            
-            println!(
-                "Successfully converted {} to {}",
-                args.input.display(),
-                output_path.display()
-            );
+    let fonts = load_fonts(&args)?;
+
+    // Read the scene: Excalidraw JSON, a PNG/SVG export with an embedded scene, or Obsidian markdown
+    let (excalidraw_data, load_warnings) = load_scene_file_with_warnings(&args.input)
+        .with_context(|| format!("Failed to load scene from {:?}", args.input))?;
+    for warning in load_warnings {
+        eprintln!("Warning: {warning}");
+    }
+
+    // Elements the renderers leave out are reported after each export
+    let (warning_sender, warning_receiver) = mpsc::channel();
+    let print_warnings = || {
+        for warning in warning_receiver.try_iter() {
+            eprintln!("Warning: {warning}");
         }
-        _ => {
-            // Convert to PNG
-            if args.legacy {
-                // Legacy SVG + resvg approach
-                // Avoid double background: rasterizer will fill background; keep SVG transparent
-                let svg_content = generate_svg(&excalidraw_data, None);
-                convert_svg_to_png(&svg_content, &output_path, bg_rgba, args.quality, args.dpi)
-                    .with_context(|| format!("Failed to convert to PNG: {output_path:?}"))?;
-            } else {
-                // Use rough_tiny_skia renderer (direct PNG output)
-                render_to_png(&excalidraw_data, &output_path, bg_rgba, args.quality, args.dpi)
-                    .with_context(|| format!("Failed to render PNG: {output_path:?}"))?;
-            }
+    };
+
+    let options = RenderOptions {
+        background: bg_rgba,
+        quality: args.quality.unwrap_or(75),
+        dpi: args.dpi,
+        frame: match args.frames.as_slice() {
+            [frame] => Some(frame.clone()),
+            _ => None,
+        },
+        region: args.region,
+        theme: args.theme.as_deref().and_then(Theme::from_name),
+        scale: args.scale,
+        padding: args.padding,
+        frame_pages: args.frame_pages.as_deref().and_then(FrameOrder::from_name),
+        embed_scene: args.embed_scene,
+        warnings: Some(warning_sender),
+        fonts: Arc::new(fonts),
+    };
 
-            println!(
-                "Successfully converted {} to {}",
-                args.input.display(),
-                output_path.display()
-            );
+    // One image per frame, named after the frame titles
+    if let Some(ref dir) = args.all_frames {
+        let frames = get_frames(&excalidraw_data.elements);
+        if frames.is_empty() {
+            anyhow::bail!("No frames found in {}", args.input.display());
         }
+        export_frames(&excalidraw_data, &frames, dir, &args, &options, print_warnings)?;
+        return Ok(());
     }
+    if args.frames.len() > 1 {
+        let Some(ref dir) = args.output else {
+            anyhow::bail!("Exporting several frames needs --output DIR");
+        };
+        let frames = args
+            .frames
+            .iter()
+            .map(|name_or_id| {
+                find_frame(&excalidraw_data.elements, name_or_id)
+                    .with_context(|| format!("Frame not found: {name_or_id}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        export_frames(&excalidraw_data, &frames, dir, &args, &options, print_warnings)?;
+        return Ok(());
+    }
+
+    // Determine output path and format: --format wins over the output extension
+    // Frame pages only exist in PDFs, so they default to PDF output
+    let default_format = if options.frame_pages.is_some() { "pdf" } else { "png" };
+    let output_path = match args.output {
+        Some(path) => path,
+        None => {
+            let mut path = args.input.clone();
+            // Obsidian documents drop their double extension: drawing.excalidraw.md -> drawing.png
+            if path.to_string_lossy().ends_with(".excalidraw.md") {
+                path.set_extension("");
+            }
+            path.set_extension(args.format.as_deref().unwrap_or(default_format));
+            // Re-rendering an exported PNG/SVG must not replace it
+            if path == args.input {
+                anyhow::bail!("Output would overwrite the input {:?}; pass --output", args.input);
+            }
+            path
+        }
+    };
+    let format = match args.format {
+        Some(format) => format,
+        None => output_path
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase())
+            .filter(|s| s == "svg" || s == "pdf" || RasterFormat::from_extension(s).is_some())
+            .unwrap_or_else(|| default_format.to_string()),
+    };
+
+    if options.frame_pages.is_some() && format != "pdf" {
+        anyhow::bail!("--frame-pages requires PDF output (use a .pdf extension or --format pdf)");
+    }
+
+    warn_ignored_quality(args.quality, args.lossless, &format);
+    write_output(&excalidraw_data, &output_path, &format, &options, args.legacy, args.lossless)?;
+    print_warnings();
+
+    println!(
+        "Successfully converted {} to {}",
+        args.input.display(),
+        output_path.display()
+    );
 
     Ok(())
 }
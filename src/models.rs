@@ -1,12 +1,16 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
+/// Excalidraw element, stored flat as in the scene JSON
+/// Fields that only some types have are `Option`s here; [`ExcalidrawElement::kind`] gives
+/// the type-specific view with those fields resolved.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExcalidrawElement {
     pub id: String,
     #[serde(rename = "type")]
-    pub element_type: String,
+    pub element_type: ElementType,
     pub x: f64,
     pub y: f64,
     pub width: f64,
@@ -14,42 +18,286 @@ pub struct ExcalidrawElement {
     pub angle: f64,
     pub stroke_color: String,
     pub background_color: String,
-    pub fill_style: String,
+    pub fill_style: FillStyle,
     pub stroke_width: f64,
-    pub stroke_style: String,
+    pub stroke_style: StrokeStyle,
     pub roughness: f64,
     pub opacity: f64,
     pub group_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub frame_id: Option<String>,
     pub index: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub roundness: Option<RoundnessType>,
     pub seed: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version_nonce: Option<i32>,
     pub is_deleted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub bound_elements: Option<Vec<BoundElement>>,
     pub updated: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub link: Option<String>,
     pub locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub font_size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub font_family: Option<i32>,
-    pub text_align: Option<String>,
-    pub vertical_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_align: Option<TextAlign>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertical_align: Option<VerticalAlign>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub container_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub original_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub line_height: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_resize: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub baseline: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub start_binding: Option<Binding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub end_binding: Option<Binding>,
-    pub start_arrow_type: Option<String>,
-    pub end_arrow_type: Option<String>,
-    pub start_arrowhead: Option<String>,
-    pub end_arrowhead: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_arrow_type: Option<Arrowhead>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_arrow_type: Option<Arrowhead>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_arrowhead: Option<Arrowhead>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_arrowhead: Option<Arrowhead>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub points: Option<Vec<(f64, f64)>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_committed_point: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub elbowed: Option<bool>,
-    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressures: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulate_pressure: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<(f64, f64)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<ImageCrop>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub version: Option<i32>,
+    /// Fields the model does not know (e.g. `customData`), kept so scenes round-trip
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Excalidraw element type
+/// Types this crate does not know deserialize to [`ElementType::Unknown`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ElementType {
+    Rectangle,
+    Diamond,
+    Ellipse,
+    Text,
+    Freedraw,
+    Image,
+    Line,
+    Arrow,
+    Frame,
+    Magicframe,
+    Embeddable,
+    Iframe,
+    #[serde(other)]
+    Unknown,
+}
+
+impl ElementType {
+    /// Excalidraw's name of the type
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ElementType::Rectangle => "rectangle",
+            ElementType::Diamond => "diamond",
+            ElementType::Ellipse => "ellipse",
+            ElementType::Text => "text",
+            ElementType::Freedraw => "freedraw",
+            ElementType::Image => "image",
+            ElementType::Line => "line",
+            ElementType::Arrow => "arrow",
+            ElementType::Frame => "frame",
+            ElementType::Magicframe => "magicframe",
+            ElementType::Embeddable => "embeddable",
+            ElementType::Iframe => "iframe",
+            ElementType::Unknown => "unknown",
+        }
+    }
+}
+
+impl std::fmt::Display for ElementType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Fill pattern of a shape's background
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FillStyle {
+    Hachure,
+    CrossHatch,
+    #[default]
+    Solid,
+    Zigzag,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Dash pattern of a stroke
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StrokeStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Horizontal alignment of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Vertical alignment of text inside its container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Arrowhead at either end of an arrow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Arrowhead {
+    Arrow,
+    Bar,
+    Dot,
+    Circle,
+    CircleOutline,
+    Triangle,
+    TriangleOutline,
+    Diamond,
+    DiamondOutline,
+    CrowfootOne,
+    CrowfootMany,
+    CrowfootOneOrMany,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Type-specific view of an element, for matching exhaustively on its type
+/// Borrowed from the flat [`ExcalidrawElement`], with Excalidraw's defaults for missing fields.
+/// The element itself stays the storage, so loading, restoring and embedding round-trip the
+/// JSON unchanged; the renderers draw each element from this view.
+#[derive(Debug, Clone, Copy)]
+pub enum ElementKind<'a> {
+    Rectangle,
+    Diamond,
+    Ellipse,
+    Text {
+        text: &'a str,
+        font_size: f64,
+        font_family: Option<i32>,
+        text_align: TextAlign,
+        vertical_align: VerticalAlign,
+        container_id: Option<&'a str>,
+    },
+    Freedraw {
+        points: &'a [(f64, f64)],
+        pressures: &'a [f64],
+    },
+    Image {
+        file_id: Option<&'a str>,
+        crop: Option<&'a ImageCrop>,
+    },
+    Line {
+        points: &'a [(f64, f64)],
+    },
+    Arrow {
+        points: &'a [(f64, f64)],
+        start_arrowhead: Option<Arrowhead>,
+        end_arrowhead: Option<Arrowhead>,
+        elbowed: bool,
+    },
+    Frame {
+        name: Option<&'a str>,
+    },
+    Magicframe {
+        name: Option<&'a str>,
+    },
+    Embeddable,
+    Iframe,
+    Unknown,
+}
+
+impl ExcalidrawElement {
+    /// Type-specific view of the element
+    pub fn kind(&self) -> ElementKind<'_> {
+        let points = self.points.as_deref().unwrap_or(&[]);
+        match self.element_type {
+            ElementType::Rectangle => ElementKind::Rectangle,
+            ElementType::Diamond => ElementKind::Diamond,
+            ElementType::Ellipse => ElementKind::Ellipse,
+            ElementType::Text => ElementKind::Text {
+                text: self.text.as_deref().unwrap_or(""),
+                font_size: self.font_size.unwrap_or(20.0),
+                font_family: self.font_family,
+                text_align: self.text_align.unwrap_or_default(),
+                vertical_align: self.vertical_align.unwrap_or_default(),
+                container_id: self.container_id.as_deref(),
+            },
+            ElementType::Freedraw => ElementKind::Freedraw {
+                points,
+                pressures: self.pressures.as_deref().unwrap_or(&[]),
+            },
+            ElementType::Image => ElementKind::Image {
+                file_id: self.file_id.as_deref(),
+                crop: self.crop.as_ref(),
+            },
+            ElementType::Line => ElementKind::Line { points },
+            ElementType::Arrow => ElementKind::Arrow {
+                points,
+                start_arrowhead: self.start_arrowhead.or(self.start_arrow_type),
+                end_arrowhead: self.end_arrowhead.or(self.end_arrow_type),
+                elbowed: self.elbowed.unwrap_or(false),
+            },
+            ElementType::Frame => ElementKind::Frame { name: self.name.as_deref() },
+            ElementType::Magicframe => ElementKind::Magicframe { name: self.name.as_deref() },
+            ElementType::Embeddable => ElementKind::Embeddable,
+            ElementType::Iframe => ElementKind::Iframe,
+            ElementType::Unknown => ElementKind::Unknown,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,15 +305,28 @@ pub struct ExcalidrawElement {
 pub struct RoundnessType {
     #[serde(rename = "type")]
     pub roundness_type: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub value: Option<f64>,
 }
 
+/// Crop rectangle of an image element, in the image's natural pixel coordinates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageCrop {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub natural_width: f64,
+    pub natural_height: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BoundElement {
     pub id: String,
     #[serde(rename = "type")]
-    pub element_type: String,
+    pub element_type: ElementType,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,14 +342,34 @@ pub struct Binding {
 pub struct ExcalidrawData {
     #[serde(rename = "type")]
     pub data_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version_nonce: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
     pub elements: Vec<ExcalidrawElement>,
     #[serde(default)]
-    pub app_state: HashMap<String, serde_json::Value>,
+    pub app_state: HashMap<String, Value>,
     #[serde(default)]
-    pub files: HashMap<String, serde_json::Value>,
+    pub files: HashMap<String, FileData>,
+}
+
+/// Binary file referenced by image elements through `fileId`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub id: String,
+    /// Empty while the image is still being uploaded
+    #[serde(default, rename = "dataURL")]
+    pub data_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_retrieved: Option<i64>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,110 @@
+//! Obsidian Excalidraw plugin documents (`.excalidraw.md`)
+//! The scene sits in a `compressed-json` (LZ-String) or `json` code block under the
+//! `Drawing` heading. The `Text Elements` section holds the text of each text element,
+//! followed by a ` ^elementId` block reference; the plugin treats it as the source of truth.
+use crate::error::{ExcaliosaError, Result, Warning};
+use crate::lz_string::decompress_from_base64;
+use crate::models::{ElementType, ExcalidrawData};
+use crate::restore::parse_scene;
+use std::collections::HashMap;
+
+/// Whether text is an Obsidian Excalidraw document rather than plain JSON
+pub fn is_obsidian_markdown(text: &str) -> bool {
+    !text.trim_start().starts_with('{') && find_drawing_block(text).is_some()
+}
+
+/// Parse an Obsidian Excalidraw document into a scene, with the text elements updated
+/// from the `Text Elements` section
+pub fn load_obsidian_markdown(text: &str, warnings: &mut Vec<Warning>) -> Result<ExcalidrawData> {
+    let (compressed, block) = find_drawing_block(text)
+        .ok_or_else(|| ExcaliosaError::Parse("Obsidian document has no drawing block".to_string()))?;
+    let json = if compressed {
+        decompress_from_base64(block)
+            .ok_or_else(|| ExcaliosaError::Parse("Failed to decompress the compressed-json drawing".to_string()))?
+    } else {
+        block.to_string()
+    };
+    let mut data = parse_scene(&json, warnings)?;
+
+    let texts = parse_text_elements(text);
+    for element in data.elements.iter_mut().filter(|el| el.element_type == ElementType::Text) {
+        if let Some(text) = texts.get(element.id.as_str()) {
+            element.text = Some(text.clone());
+            element.original_text = Some(text.clone());
+        }
+    }
+    Ok(data)
+}
+
+/// Body of the drawing code block, and whether it is LZ-String compressed
+/// The block is searched after the `Drawing` heading when there is one.
+fn find_drawing_block(text: &str) -> Option<(bool, &str)> {
+    let start = find_heading(text, "Drawing").unwrap_or(0);
+    let rest = &text[start..];
+    for (fence, compressed) in [("```compressed-json", true), ("```json", false)] {
+        if let Some(open) = rest.find(fence) {
+            let body_start = open + fence.len();
+            let body_end = rest[body_start..].find("```")? + body_start;
+            return Some((compressed, rest[body_start..body_end].trim()));
+        }
+    }
+    None
+}
+
+/// Sections the plugin writes after `Text Elements`, which end it
+const SECTION_TITLES: &[&str] = &["Element Links", "Embedded Files", "Drawing", "Excalidraw Data"];
+
+/// Title of a `#` or `##` heading line, or `None` for any other line
+/// Text elements such as `#todo` are not headings: the hashes must be followed by a space.
+fn heading_title(line: &str) -> Option<&str> {
+    let title = line
+        .strip_prefix("## ")
+        .or_else(|| line.strip_prefix("# "))?;
+    Some(title.trim())
+}
+
+/// Byte offset just past a `#` or `##` heading line with the given title
+fn find_heading(text: &str, title: &str) -> Option<usize> {
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        offset += line.len();
+        if heading_title(line.trim_end()) == Some(title) {
+            return Some(offset);
+        }
+    }
+    None
+}
+
+/// Text of each element in the `Text Elements` section, keyed by element id
+/// Entries are separated by blank lines and end with ` ^elementId`; their text may span lines.
+fn parse_text_elements(text: &str) -> HashMap<String, String> {
+    let mut texts = HashMap::new();
+    let Some(start) = find_heading(text, "Text Elements") else {
+        return texts;
+    };
+
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text[start..].lines() {
+        let is_section = heading_title(line).is_some_and(|title| SECTION_TITLES.contains(&title));
+        if is_section || line.trim() == "%%" {
+            break;
+        }
+        if lines.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+        match line.rsplit_once(" ^") {
+            Some((last, id)) if is_block_id(id.trim_end()) => {
+                lines.push(last);
+                texts.insert(id.trim_end().to_string(), lines.join("\n"));
+                lines.clear();
+            }
+            _ => lines.push(line),
+        }
+    }
+    texts
+}
+
+/// Obsidian block references are letters, digits and dashes
+fn is_block_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
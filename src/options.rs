@@ -0,0 +1,203 @@
+//! Options shared by the SVG and PNG renderers
+use crate::color_utils::{get_themed_rgba, parse_color_result};
+use crate::embed_utils::get_exported_scene;
+use crate::error::{get_element_warnings, Result, Warning};
+use crate::fonts::{get_text_warnings, FontProvider, FontSet};
+use crate::models::{ExcalidrawData, ExcalidrawElement, FileData, ViewBox};
+use crate::utils::DEFAULT_EXPORT_PADDING;
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+/// Color theme of the exported image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    #[default]
+    Light,
+    /// Excalidraw's dark export: colors pass through `invert(93%) hue-rotate(180deg)`
+    Dark,
+}
+
+impl Theme {
+    /// Parse an Excalidraw theme name ("light" or "dark")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "light" => Some(Theme::Light),
+            "dark" => Some(Theme::Dark),
+            _ => None,
+        }
+    }
+}
+
+/// Order of frames when each frame becomes its own page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameOrder {
+    /// Excalidraw's fractional `index`, i.e. the frames' stacking order
+    #[default]
+    Index,
+    /// Frame titles, alphabetically
+    Name,
+}
+
+impl FrameOrder {
+    /// Parse a frame order name ("index" or "name")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "index" => Some(FrameOrder::Index),
+            "name" => Some(FrameOrder::Name),
+            _ => None,
+        }
+    }
+}
+
+/// How a scene is rendered and exported
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Background color; `None` keeps the renderer default (white for PNG, none for SVG)
+    pub background: Option<(u8, u8, u8, u8)>,
+    /// Raster quality (0-100): the compression level for PNG, the encoder quality for JPEG
+    /// and lossy WebP
+    pub quality: u8,
+    /// Target DPI for raster output, assuming a 96 DPI source
+    pub dpi: Option<u32>,
+    /// Export only this frame (matched by id, then by name): the image covers the frame's
+    /// bounds and contains only the frame's children
+    pub frame: Option<String>,
+    /// Export only this rectangle, in scene coordinates: the image covers exactly the
+    /// region, without padding, and elements outside it are cut off. Combines with `frame`,
+    /// whose children are then drawn inside the region. Ignored by `frame_pages`
+    pub region: Option<ViewBox>,
+    /// Color theme; `None` uses the scene's `appState.theme`, or light
+    pub theme: Option<Theme>,
+    /// Raster scale factor; `None` uses `appState.exportScale`, or 1. `dpi` takes precedence
+    pub scale: Option<f64>,
+    /// Padding around the scene; `None` uses `appState.exportPadding`, or 40
+    pub padding: Option<f64>,
+    /// PDF only: one page per frame, in this order, instead of `frame`. A scene without
+    /// frames still produces a single page.
+    pub frame_pages: Option<FrameOrder>,
+    /// Embed the scene JSON in PNG and SVG output so Excalidraw can open the export again
+    pub embed_scene: bool,
+    /// Receives a [`Warning`] for each element that is left out of the output; `None`
+    /// drops them
+    pub warnings: Option<Sender<Warning>>,
+    /// Fonts text is drawn with; the embedded fonts by default
+    pub fonts: Arc<dyn FontProvider>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            background: None,
+            quality: 75,
+            dpi: None,
+            frame: None,
+            region: None,
+            theme: None,
+            scale: None,
+            padding: None,
+            frame_pages: None,
+            embed_scene: false,
+            warnings: None,
+            fonts: Arc::new(FontSet::default()),
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Send a warning to `warnings`, if set
+    pub(crate) fn warn(&self, warning: Warning) {
+        if let Some(ref sender) = self.warnings {
+            // A dropped receiver just means nobody is listening
+            sender.send(warning).ok();
+        }
+    }
+
+    /// Warn about the elements of a render that cannot be drawn
+    pub(crate) fn report_element_warnings(&self, elements: &[&ExcalidrawElement], files: &HashMap<String, FileData>) {
+        if self.warnings.is_some() {
+            for warning in get_element_warnings(elements, files) {
+                self.warn(warning);
+            }
+        }
+    }
+
+    /// Warn about texts of a render with characters no font can draw
+    /// Only the backends that draw glyphs themselves report these; SVG viewers pick their
+    /// own fonts.
+    pub(crate) fn report_text_warnings(&self, elements: &[&ExcalidrawElement]) {
+        if self.warnings.is_some() {
+            for warning in get_text_warnings(elements, self.fonts.as_ref()) {
+                self.warn(warning);
+            }
+        }
+    }
+
+    /// Background color: the explicit option, else the scene's `viewBackgroundColor`, or
+    /// transparent when `appState.exportBackground` is off. `None` keeps the renderer default.
+    pub fn resolve_background(&self, data: &ExcalidrawData) -> Option<(u8, u8, u8, u8)> {
+        if self.background.is_some() {
+            return self.background;
+        }
+        if data.app_state.get("exportBackground").and_then(|v| v.as_bool()) == Some(false) {
+            return Some((0, 0, 0, 0));
+        }
+        data.app_state
+            .get("viewBackgroundColor")
+            .and_then(|v| v.as_str())
+            .and_then(|color| parse_color_result(color).ok())
+    }
+
+    /// Raster scale factor: `dpi` relative to 96 DPI, else the explicit scale, else the
+    /// scene's `appState.exportScale`
+    pub fn resolve_scale(&self, data: &ExcalidrawData) -> f64 {
+        const SOURCE_DPI: f64 = 96.0;
+        if let Some(dpi) = self.dpi {
+            return dpi as f64 / SOURCE_DPI;
+        }
+        self.scale
+            .or_else(|| data.app_state.get("exportScale").and_then(|v| v.as_f64()))
+            .filter(|scale| *scale > 0.0)
+            .unwrap_or(1.0)
+    }
+
+    /// Padding around the scene: the explicit option, else the scene's `appState.exportPadding`
+    pub fn resolve_padding(&self, data: &ExcalidrawData) -> f64 {
+        self.padding
+            .or_else(|| data.app_state.get("exportPadding").and_then(|v| v.as_f64()))
+            .filter(|padding| *padding >= 0.0)
+            .unwrap_or(DEFAULT_EXPORT_PADDING)
+    }
+
+    /// Opaque color that formats without alpha (JPEG) are flattened onto, and that outline
+    /// arrowheads are filled with: the themed background, or the themed white default when
+    /// the background is transparent
+    pub fn resolve_matte(&self, data: &ExcalidrawData) -> (u8, u8, u8) {
+        let background = self
+            .resolve_background(data)
+            .filter(|(_, _, _, a)| *a > 0)
+            .unwrap_or((255, 255, 255, 255));
+        let (r, g, b, _) = get_themed_rgba(background, self.resolve_theme(data));
+        (r, g, b)
+    }
+
+    /// Scene to embed in the output when `embed_scene` is set: only what is exported, so a
+    /// `frame` export embeds the frame and its children
+    pub fn resolve_embedded_scene(&self, data: &ExcalidrawData) -> Result<Option<ExcalidrawData>> {
+        self.embed_scene
+            .then(|| get_exported_scene(data, self.frame.as_deref()))
+            .transpose()
+    }
+
+    /// Theme to render with: the explicit option, else the scene's `appState.theme`
+    pub fn resolve_theme(&self, data: &ExcalidrawData) -> Theme {
+        self.theme
+            .or_else(|| {
+                data.app_state
+                    .get("theme")
+                    .and_then(|theme| theme.as_str())
+                    .and_then(Theme::from_name)
+            })
+            .unwrap_or_default()
+    }
+}
@@ -1,14 +1,27 @@
-use crate::arrow_utils::{calc_arrowhead_points, build_elbow_arrow_path, calculate_arrowhead_direction};
-use crate::color_utils::{has_fill, has_stroke};
+use crate::arrow_utils::{calc_arrowhead_points, build_elbow_arrow_path, calculate_arrowhead_direction, is_curved_linear};
+use crate::bound_text_utils::index_elements;
+use crate::color_utils::{get_themed_color, get_themed_rgba, has_fill, has_stroke};
+use crate::embed_utils::svg_scene_metadata;
+use crate::error::Result;
+use crate::frame_utils::{
+    collect_frames, get_containing_frame, get_frame_name, get_frame_name_baseline, FRAME_NAME_COLOR,
+    FRAME_NAME_FONT_FAMILY, FRAME_NAME_FONT_SIZE, FRAME_RADIUS, FRAME_STROKE_COLOR, FRAME_STROKE_WIDTH,
+};
+use crate::freedraw_utils::{get_freedraw_outline, get_svg_path_from_stroke, is_path_a_loop};
+use crate::image_utils::get_image_flip;
 use crate::math_utils::{calculate_center, catmull_rom_cubics, create_svg_rotate_transform};
-use crate::models::{ExcalidrawData, ExcalidrawElement, ViewBox};
+use crate::models::{Arrowhead, ElementKind, ExcalidrawData, ExcalidrawElement, FileData, FillStyle, ViewBox};
 use crate::rect_utils::{get_corner_radius, generate_rounded_rect_path};
 use crate::font_utils::{
     calculate_text_x_position, get_font_family, get_line_height, get_svg_text_anchor,
     get_vertical_offset,
 };
+use crate::fonts::FontProvider;
+use crate::options::{RenderOptions, Theme};
 use crate::stroke_utils::get_stroke_dasharray_attr;
-use crate::utils::calculate_viewbox;
+use crate::text_layout::{get_arrow_label_mask_box, layout_text_element};
+use crate::utils::{calculate_viewbox_with_padding, get_element_center, resolve_export_scope};
+use std::collections::HashMap;
 
 // Simple deterministic RNG (LCG) for jitter, seeded by element.seed
 struct LcgRng {
@@ -38,20 +51,25 @@ impl LcgRng {
 }
 
 
+// Return straight-segment SVG path through absolute points (x,y).
+fn polyline_path(points: &[(f64, f64)]) -> String {
+    format!(
+        "M {}",
+        points
+            .iter()
+            .map(|(x, y)| format!("{x},{y}"))
+            .collect::<Vec<_>>()
+            .join(" L ")
+    )
+}
+
 // Return smoothed SVG path using Catmull–Rom to cubic Bezier conversion.
 // Expects absolute points (x,y). If less than 3 points, fall back to straight segments.
 // This matches Excalidraw's "perfect shape" rendering for curved lines.
 fn catmull_rom_path(points: &[(f64, f64)]) -> String {
     if points.len() <= 2 {
         // fallback to polyline
-        return format!(
-            "M {}",
-            points
-                .iter()
-                .map(|(x, y)| format!("{x},{y}"))
-                .collect::<Vec<_>>()
-                .join(" L ")
-        );
+        return polyline_path(points);
     }
 
     let cubics = catmull_rom_cubics(points, 0.5);
@@ -518,43 +536,56 @@ fn generate_rough_rect_paths(
     paths
 }
 
-fn render_element(el: &ExcalidrawElement, _viewbox: &ViewBox) -> String {
+fn render_element(
+    el: &ExcalidrawElement,
+    _viewbox: &ViewBox,
+    files: &HashMap<String, FileData>,
+    theme: Theme,
+    canvas_color: &str,
+    fonts: &dyn FontProvider,
+) -> String {
     if el.is_deleted {
         return String::new();
     }
 
     let should_stroke = has_stroke(el);
     let should_fill = has_fill(el);
+
+    // Element colors as drawn in the export theme
+    let themed_stroke_color = get_themed_color(&el.stroke_color, theme);
+    let themed_background_color = get_themed_color(&el.background_color, theme);
     
-    let stroke_color = if should_stroke {
-        &el.stroke_color
+    let stroke_color: &str = if should_stroke {
+        &themed_stroke_color
     } else {
         "none"
     };
     
-    let background_color = if should_fill {
-        &el.background_color
+    let background_color: &str = if should_fill {
+        &themed_background_color
     } else {
         "none"
     };
 
     let opacity = el.opacity / 100.0;
-    let (center_x, center_y) = calculate_center(el.x, el.y, el.width, el.height);
-    let transform = create_svg_rotate_transform(el.angle, center_x, center_y);
+    // Excalidraw stores angles in radians and rotates around the element's bounds center
+    let (center_x, center_y) = get_element_center(el);
+    let transform = create_svg_rotate_transform(el.angle.to_degrees(), center_x, center_y);
     
-    let stroke_dasharray = get_stroke_dasharray_attr(&el.stroke_style, el.stroke_width);
+    let stroke_dasharray = get_stroke_dasharray_attr(el.stroke_style, el.stroke_width);
     let dasharray_attr = if stroke_dasharray != "none" {
         format!(r#" stroke-dasharray="{stroke_dasharray}""#)
     } else {
         String::new()
     };
 
-    match el.element_type.as_str() {
-        "rectangle" => {
-            let fill_style = if el.fill_style.is_empty() {
-                "solid"
-            } else {
-                el.fill_style.as_str()
+    let kind = el.kind();
+    match kind {
+        ElementKind::Rectangle => {
+            // Fill styles this crate does not know render solid
+            let fill_style = match el.fill_style {
+                FillStyle::Unknown => FillStyle::Solid,
+                fill_style => fill_style,
             };
             
             // Calculate corner radius using Excalidraw's algorithm
@@ -565,9 +596,9 @@ fn render_element(el: &ExcalidrawElement, _viewbox: &ViewBox) -> String {
             };
             
             // For non-solid fills, we need two paths: one for the pattern, one for the stroke
-            if fill_style != "solid" && should_fill {
-                let pattern_path = if fill_style == "hachure" {
-                    generate_hachure_pattern(el.x, el.y, el.width, el.height, el.angle)
+            if fill_style != FillStyle::Solid && should_fill {
+                let pattern_path = if fill_style == FillStyle::Hachure {
+                    generate_hachure_pattern(el.x, el.y, el.width, el.height)
                 } else {
                     // TODO: implement cross-hatch, zigzag patterns
                     String::new()
@@ -577,7 +608,7 @@ fn render_element(el: &ExcalidrawElement, _viewbox: &ViewBox) -> String {
                 let pattern_svg = if !pattern_path.is_empty() {
                     format!(
                         r#"<path d="{}" fill="none" stroke="{}" stroke-width="1" opacity="{}" transform="{}"/>"#,
-                        pattern_path, &el.background_color, opacity, transform
+                        pattern_path, background_color, opacity, transform
                     )
                 } else {
                     String::new()
@@ -656,7 +687,7 @@ fn render_element(el: &ExcalidrawElement, _viewbox: &ViewBox) -> String {
                 }
             }
         }
-        "diamond" => {
+        ElementKind::Diamond => {
             let points = [
                 (el.x + el.width / 2.0, el.y),
                 (el.x + el.width, el.y + el.height / 2.0),
@@ -708,7 +739,7 @@ fn render_element(el: &ExcalidrawElement, _viewbox: &ViewBox) -> String {
                 )
             }
         }
-        "ellipse" => {
+        ElementKind::Ellipse => {
             let (cx, cy) = calculate_center(el.x, el.y, el.width, el.height);
             let rx = el.width / 2.0;
             let ry = el.height / 2.0;
@@ -747,190 +778,184 @@ fn render_element(el: &ExcalidrawElement, _viewbox: &ViewBox) -> String {
                 )
             }
         }
-        "line" | "arrow" => {
-            if let Some(ref points) = el.points {
-                if !points.is_empty() {
-                    // Absolute points
-                    let abs_points: Vec<(f64, f64)> = points.iter().map(|(px, py)| (el.x + px, el.y + py)).collect();
+        ElementKind::Line { points } | ElementKind::Arrow { points, .. } => {
+            // Only arrows have arrowheads and elbows
+            let (start_arrowhead, end_arrowhead, elbowed) = match kind {
+                ElementKind::Arrow { start_arrowhead, end_arrowhead, elbowed, .. } => {
+                    (start_arrowhead, end_arrowhead, elbowed)
+                }
+                _ => (None, None, false),
+            };
+            if !points.is_empty() {
+                // Absolute points
+                let abs_points: Vec<(f64, f64)> = points.iter().map(|(px, py)| (el.x + px, el.y + py)).collect();
 
-                    // When not elbowed, render as smoothed Catmull–Rom spline, else as rounded elbow path
-                    let elbowed = el.elbowed.unwrap_or(false);
-                    let path_data = if elbowed {
-                        build_elbow_arrow_path(&abs_points, 16.0)
-                            .unwrap_or_else(|| {
-                                // Fallback to simple polyline if function fails
-                                format!(
-                                    "M {}",
-                                    abs_points
-                                        .iter()
-                                        .map(|(x, y)| format!("{x},{y}"))
-                                        .collect::<Vec<_>>()
-                                        .join(" L ")
-                                )
-                            })
-                    } else {
-                        catmull_rom_path(&abs_points)
-                    };
+                // Elbow arrows get a rounded elbow path, rounded lines and arrows a smoothed
+                // Catmull–Rom spline, and sharp ones straight segments
+                let curved = is_curved_linear(el);
+                let path_data = if elbowed {
+                    build_elbow_arrow_path(&abs_points, 16.0)
+                        .unwrap_or_else(|| {
+                            // Fallback to simple polyline if function fails
+                            format!(
+                                "M {}",
+                                abs_points
+                                    .iter()
+                                    .map(|(x, y)| format!("{x},{y}"))
+                                    .collect::<Vec<_>>()
+                                    .join(" L ")
+                            )
+                        })
+                } else if curved {
+                    catmull_rom_path(&abs_points)
+                } else {
+                    polyline_path(&abs_points)
+                };
 
-                    // Build optional arrowheads at start/end
-                    let mut arrowheads_svg = String::new();
+                // Build optional arrowheads at start/end
+                let mut arrowheads_svg = String::new();
 
-                    // Helper to convert shared arrowhead points to Vec<(f64, f64)> format
-                    fn convert_arrowhead_points(arrowhead: &str, vals: Vec<f64>) -> Vec<(f64, f64)> {
-                        match arrowhead {
-                            "dot" | "circle" | "circle_outline" => {
-                                if vals.len() >= 3 {
-                                    vec![(vals[0], vals[1]), (vals[2], 0.0)]
-                                } else {
-                                    vec![]
-                                }
+                // Helper to convert shared arrowhead points to Vec<(f64, f64)> format
+                fn convert_arrowhead_points(arrowhead: Arrowhead, vals: Vec<f64>) -> Vec<(f64, f64)> {
+                    match arrowhead {
+                        Arrowhead::Dot | Arrowhead::Circle | Arrowhead::CircleOutline => {
+                            if vals.len() >= 3 {
+                                vec![(vals[0], vals[1]), (vals[2], 0.0)]
+                            } else {
+                                vec![]
                             }
-                            "bar" => {
-                                if vals.len() >= 4 {
-                                    vec![(vals[0], vals[1]), (vals[2], vals[3])]
-                                } else {
-                                    vec![]
-                                }
+                        }
+                        Arrowhead::Bar => {
+                            if vals.len() >= 4 {
+                                vec![(vals[0], vals[1]), (vals[2], vals[3])]
+                            } else {
+                                vec![]
                             }
-                            "arrow" | "triangle" | "triangle_outline" => {
-                                if vals.len() >= 6 {
-                                    vec![(vals[0], vals[1]), (vals[2], vals[3]), (vals[4], vals[5])]
-                                } else {
-                                    vec![]
-                                }
+                        }
+                        Arrowhead::Arrow | Arrowhead::Triangle | Arrowhead::TriangleOutline => {
+                            if vals.len() >= 6 {
+                                vec![(vals[0], vals[1]), (vals[2], vals[3]), (vals[4], vals[5])]
+                            } else {
+                                vec![]
                             }
-                            "diamond" | "diamond_outline" => {
-                                if vals.len() >= 8 {
-                                    vec![(vals[0], vals[1]), (vals[2], vals[3]), (vals[4], vals[5]), (vals[6], vals[7])]
-                                } else {
-                                    vec![]
-                                }
+                        }
+                        Arrowhead::Diamond | Arrowhead::DiamondOutline => {
+                            if vals.len() >= 8 {
+                                vec![(vals[0], vals[1]), (vals[2], vals[3]), (vals[4], vals[5]), (vals[6], vals[7])]
+                            } else {
+                                vec![]
                             }
-                            _ => vec![],
                         }
+                        _ => vec![],
                     }
+                }
 
-                    // Render arrowhead helper function
-                    #[allow(clippy::too_many_arguments)]
-                    fn render_arrowhead(
-                        arrowhead_type: &str,
-                        points_vec: Vec<(f64, f64)>,
-                        stroke_color: &str,
-                        background_color: &str,
-                        stroke_width: f64,
-                        opacity: f64,
-                        transform: &str,
-                    ) -> String {
-                        if points_vec.is_empty() {
-                            return String::new();
-                        }
+                // Render arrowhead helper function
+                #[allow(clippy::too_many_arguments)]
+                fn render_arrowhead(
+                    arrowhead_type: Arrowhead,
+                    points_vec: Vec<(f64, f64)>,
+                    stroke_color: &str,
+                    canvas_color: &str,
+                    stroke_width: f64,
+                    opacity: f64,
+                    transform: &str,
+                ) -> String {
+                    if points_vec.is_empty() {
+                        return String::new();
+                    }
 
-                        match arrowhead_type {
-                            "dot" | "circle" | "circle_outline" => {
-                                if points_vec.len() >= 2 {
-                                    let (cx, cy) = points_vec[0];
-                                    let (diameter, _) = points_vec[1];
-                                    let fill = if arrowhead_type == "circle_outline" {
-                                        background_color
-                                    } else {
-                                        stroke_color
-                                    };
-                                    format!(
-                                        r#"<circle cx="{cx}" cy="{cy}" r="{}" fill="{fill}" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}"/>"#,
-                                        diameter / 2.0
-                                    )
+                    match arrowhead_type {
+                        Arrowhead::Dot | Arrowhead::Circle | Arrowhead::CircleOutline => {
+                            if points_vec.len() >= 2 {
+                                let (cx, cy) = points_vec[0];
+                                let (diameter, _) = points_vec[1];
+                                let fill = if arrowhead_type == Arrowhead::CircleOutline {
+                                    canvas_color
                                 } else {
-                                    String::new()
-                                }
+                                    stroke_color
+                                };
+                                format!(
+                                    r#"<circle cx="{cx}" cy="{cy}" r="{}" fill="{fill}" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}"/>"#,
+                                    diameter / 2.0
+                                )
+                            } else {
+                                String::new()
                             }
-                            "bar" => {
-                                if points_vec.len() >= 2 {
-                                    let (x1, y1) = points_vec[0];
-                                    let (x2, y2) = points_vec[1];
-                                    format!(
-                                        r#"<path d="M {x1} {y1} L {x2} {y2}" fill="none" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}" stroke-linecap="round"/>"#
-                                    )
-                                } else {
-                                    String::new()
-                                }
+                        }
+                        Arrowhead::Bar => {
+                            if points_vec.len() >= 2 {
+                                let (x1, y1) = points_vec[0];
+                                let (x2, y2) = points_vec[1];
+                                format!(
+                                    r#"<path d="M {x1} {y1} L {x2} {y2}" fill="none" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}" stroke-linecap="round"/>"#
+                                )
+                            } else {
+                                String::new()
                             }
-                            "arrow" => {
-                                if points_vec.len() >= 3 {
-                                    let (tip_x, tip_y) = points_vec[0];
-                                    let (x3, y3) = points_vec[1];
-                                    let (x4, y4) = points_vec[2];
-                                    format!(
-                                        r#"<path d="M {x3} {y3} L {tip_x} {tip_y}" fill="none" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}" stroke-linecap="round"/>"#
-                                    ) + "\n" + &format!(
-                                        r#"<path d="M {x4} {y4} L {tip_x} {tip_y}" fill="none" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}" stroke-linecap="round"/>"#
-                                    )
-                                } else {
-                                    String::new()
-                                }
+                        }
+                        Arrowhead::Arrow => {
+                            if points_vec.len() >= 3 {
+                                let (tip_x, tip_y) = points_vec[0];
+                                let (x3, y3) = points_vec[1];
+                                let (x4, y4) = points_vec[2];
+                                format!(
+                                    r#"<path d="M {x3} {y3} L {tip_x} {tip_y}" fill="none" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}" stroke-linecap="round"/>"#
+                                ) + "\n" + &format!(
+                                    r#"<path d="M {x4} {y4} L {tip_x} {tip_y}" fill="none" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}" stroke-linecap="round"/>"#
+                                )
+                            } else {
+                                String::new()
                             }
-                            "triangle" | "triangle_outline" => {
-                                if points_vec.len() >= 3 {
-                                    let fill = if arrowhead_type == "triangle_outline" {
-                                        background_color
-                                    } else {
-                                        stroke_color
-                                    };
-                                    let path_points = points_vec.iter()
-                                        .map(|(x, y)| format!("{x},{y}"))
-                                        .collect::<Vec<_>>()
-                                        .join(" ");
-                                    format!(
-                                        r#"<polygon points="{path_points}" fill="{fill}" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}"/>"#
-                                    )
+                        }
+                        Arrowhead::Triangle | Arrowhead::TriangleOutline => {
+                            if points_vec.len() >= 3 {
+                                let fill = if arrowhead_type == Arrowhead::TriangleOutline {
+                                    canvas_color
                                 } else {
-                                    String::new()
-                                }
+                                    stroke_color
+                                };
+                                let path_points = points_vec.iter()
+                                    .map(|(x, y)| format!("{x},{y}"))
+                                    .collect::<Vec<_>>()
+                                    .join(" ");
+                                format!(
+                                    r#"<polygon points="{path_points}" fill="{fill}" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}"/>"#
+                                )
+                            } else {
+                                String::new()
                             }
-                            "diamond" | "diamond_outline" => {
-                                if points_vec.len() >= 4 {
-                                    let fill = if arrowhead_type == "diamond_outline" {
-                                        background_color
-                                    } else {
-                                        stroke_color
-                                    };
-                                    let path_points = points_vec.iter()
-                                        .map(|(x, y)| format!("{x},{y}"))
-                                        .collect::<Vec<_>>()
-                                        .join(" ");
-                                    format!(
-                                        r#"<polygon points="{path_points}" fill="{fill}" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}"/>"#
-                                    )
+                        }
+                        Arrowhead::Diamond | Arrowhead::DiamondOutline => {
+                            if points_vec.len() >= 4 {
+                                let fill = if arrowhead_type == Arrowhead::DiamondOutline {
+                                    canvas_color
                                 } else {
-                                    String::new()
-                                }
+                                    stroke_color
+                                };
+                                let path_points = points_vec.iter()
+                                    .map(|(x, y)| format!("{x},{y}"))
+                                    .collect::<Vec<_>>()
+                                    .join(" ");
+                                format!(
+                                    r#"<polygon points="{path_points}" fill="{fill}" stroke="{stroke_color}" stroke-width="{stroke_width}" opacity="{opacity}" transform="{transform}"/>"#
+                                )
+                            } else {
+                                String::new()
                             }
-                            _ => String::new(),
                         }
+                        _ => String::new(),
                     }
+                }
 
-                    // END arrowhead
-                    if (el.end_arrowhead.is_some() || el.end_arrow_type.is_some()) && points.len() >= 2 {
-                        let arrowhead_type = el.end_arrowhead.as_deref()
-                            .or(el.end_arrow_type.as_deref())
-                            .unwrap_or("arrow");
-                        
-                        let (tail_x, tail_y, tip_x, tip_y, segment_length) = if !elbowed && points.len() >= 2 {
-                            // Use Catmull-Rom curve tangent for accurate direction
-                            if let Some(dir) = calculate_arrowhead_direction(points, el.x, el.y, "end", 0.5) {
-                                dir
-                            } else {
-                                // Fallback to straight line
-                                let (last_rel_x, last_rel_y) = points[points.len() - 1];
-                                let (prev_rel_x, prev_rel_y) = points[points.len() - 2];
-                                let tip_x = el.x + last_rel_x;
-                                let tip_y = el.y + last_rel_y;
-                                let tail_x = el.x + prev_rel_x;
-                                let tail_y = el.y + prev_rel_y;
-                                let seg_len = ((tip_x - tail_x).powi(2) + (tip_y - tail_y).powi(2)).sqrt();
-                                (tail_x, tail_y, tip_x, tip_y, seg_len)
-                            }
+                // END arrowhead
+                if let Some(arrowhead_type) = end_arrowhead && points.len() >= 2 {
+                    let (tail_x, tail_y, tip_x, tip_y, segment_length) = if curved && points.len() >= 2 {
+                        // Use Catmull-Rom curve tangent for accurate direction
+                        if let Some(dir) = calculate_arrowhead_direction(points, el.x, el.y, "end", 0.5) {
+                            dir
                         } else {
-                            // Elbowed or insufficient points - use straight line
+                            // Fallback to straight line
                             let (last_rel_x, last_rel_y) = points[points.len() - 1];
                             let (prev_rel_x, prev_rel_y) = points[points.len() - 2];
                             let tip_x = el.x + last_rel_x;
@@ -939,73 +964,69 @@ fn render_element(el: &ExcalidrawElement, _viewbox: &ViewBox) -> String {
                             let tail_y = el.y + prev_rel_y;
                             let seg_len = ((tip_x - tail_x).powi(2) + (tip_y - tail_y).powi(2)).sqrt();
                             (tail_x, tail_y, tip_x, tip_y, seg_len)
-                        };
-                        
-                        let pts_vals = calc_arrowhead_points(tail_x, tail_y, tip_x, tip_y, arrowhead_type, el.stroke_width, segment_length);
-                        let pts = convert_arrowhead_points(arrowhead_type, pts_vals);
-                        
-                        let arrowhead_svg = render_arrowhead(
+                        }
+                    } else {
+                        // Elbowed, sharp or insufficient points - use straight line
+                        let (last_rel_x, last_rel_y) = points[points.len() - 1];
+                        let (prev_rel_x, prev_rel_y) = points[points.len() - 2];
+                        let tip_x = el.x + last_rel_x;
+                        let tip_y = el.y + last_rel_y;
+                        let tail_x = el.x + prev_rel_x;
+                        let tail_y = el.y + prev_rel_y;
+                        let seg_len = ((tip_x - tail_x).powi(2) + (tip_y - tail_y).powi(2)).sqrt();
+                        (tail_x, tail_y, tip_x, tip_y, seg_len)
+                    };
+                    
+                    let pts_vals = calc_arrowhead_points(tail_x, tail_y, tip_x, tip_y, arrowhead_type, el.stroke_width, segment_length);
+                    let pts = convert_arrowhead_points(arrowhead_type, pts_vals);
+                    
+                    let arrowhead_svg = render_arrowhead(
+                        arrowhead_type,
+                        pts.clone(),
+                        stroke_color,
+                        canvas_color,
+                        el.stroke_width,
+                        opacity,
+                        &transform,
+                    );
+                    arrowheads_svg.push_str(&arrowhead_svg);
+
+                    // Rough imperfect second pass for arrowhead if roughness > 0
+                    if el.roughness > 0.0 && arrowhead_type != Arrowhead::Dot {
+                        let mut rng = LcgRng::new(el.seed);
+                        let jitter = (0.6 + 0.2 * el.stroke_width) * el.roughness.max(0.0);
+                        let jx = rng.range(-jitter, jitter);
+                        let jy = rng.range(-jitter, jitter);
+                        let pts_rough_vals = calc_arrowhead_points(
+                            tail_x + jx, tail_y + jy, 
+                            tip_x + jx, tip_y + jy, 
+                            arrowhead_type, 
+                            el.stroke_width * rng.range(0.95, 1.05), 
+                            segment_length
+                        );
+                        let pts_rough = convert_arrowhead_points(arrowhead_type, pts_rough_vals);
+                        let opacity2 = (opacity * 0.9).min(1.0);
+                        arrowheads_svg.push('\n');
+                        arrowheads_svg.push_str(&render_arrowhead(
                             arrowhead_type,
-                            pts.clone(),
+                            pts_rough,
                             stroke_color,
-                            background_color,
+                            canvas_color,
                             el.stroke_width,
-                            opacity,
+                            opacity2,
                             &transform,
-                        );
-                        arrowheads_svg.push_str(&arrowhead_svg);
-
-                        // Rough imperfect second pass for arrowhead if roughness > 0
-                        if el.roughness > 0.0 && arrowhead_type != "dot" {
-                            let mut rng = LcgRng::new(el.seed);
-                            let jitter = (0.6 + 0.2 * el.stroke_width) * el.roughness.max(0.0);
-                            let jx = rng.range(-jitter, jitter);
-                            let jy = rng.range(-jitter, jitter);
-                            let pts_rough_vals = calc_arrowhead_points(
-                                tail_x + jx, tail_y + jy, 
-                                tip_x + jx, tip_y + jy, 
-                                arrowhead_type, 
-                                el.stroke_width * rng.range(0.95, 1.05), 
-                                segment_length
-                            );
-                            let pts_rough = convert_arrowhead_points(arrowhead_type, pts_rough_vals);
-                            let opacity2 = (opacity * 0.9).min(1.0);
-                            arrowheads_svg.push('\n');
-                            arrowheads_svg.push_str(&render_arrowhead(
-                                arrowhead_type,
-                                pts_rough,
-                                stroke_color,
-                                background_color,
-                                el.stroke_width,
-                                opacity2,
-                                &transform,
-                            ));
-                        }
+                        ));
                     }
+                }
 
-                    // START arrowhead
-                    if (el.start_arrowhead.is_some() || el.start_arrow_type.is_some()) && points.len() >= 2 {
-                        let arrowhead_type = el.start_arrowhead.as_deref()
-                            .or(el.start_arrow_type.as_deref())
-                            .unwrap_or("arrow");
-                        
-                        let (tail_x, tail_y, tip_x, tip_y, segment_length) = if !elbowed && points.len() >= 2 {
-                            // Use Catmull-Rom curve tangent for accurate direction
-                            if let Some(dir) = calculate_arrowhead_direction(points, el.x, el.y, "start", 0.5) {
-                                dir
-                            } else {
-                                // Fallback to straight line
-                                let (first_rel_x, first_rel_y) = points[0];
-                                let (second_rel_x, second_rel_y) = points[1];
-                                let tip_x = el.x + first_rel_x;
-                                let tip_y = el.y + first_rel_y;
-                                let tail_x = el.x + second_rel_x;
-                                let tail_y = el.y + second_rel_y;
-                                let seg_len = ((tip_x - tail_x).powi(2) + (tip_y - tail_y).powi(2)).sqrt();
-                                (tail_x, tail_y, tip_x, tip_y, seg_len)
-                            }
+                // START arrowhead
+                if let Some(arrowhead_type) = start_arrowhead && points.len() >= 2 {
+                    let (tail_x, tail_y, tip_x, tip_y, segment_length) = if curved && points.len() >= 2 {
+                        // Use Catmull-Rom curve tangent for accurate direction
+                        if let Some(dir) = calculate_arrowhead_direction(points, el.x, el.y, "start", 0.5) {
+                            dir
                         } else {
-                            // Elbowed or insufficient points - use straight line
+                            // Fallback to straight line
                             let (first_rel_x, first_rel_y) = points[0];
                             let (second_rel_x, second_rel_y) = points[1];
                             let tip_x = el.x + first_rel_x;
@@ -1014,127 +1035,135 @@ fn render_element(el: &ExcalidrawElement, _viewbox: &ViewBox) -> String {
                             let tail_y = el.y + second_rel_y;
                             let seg_len = ((tip_x - tail_x).powi(2) + (tip_y - tail_y).powi(2)).sqrt();
                             (tail_x, tail_y, tip_x, tip_y, seg_len)
-                        };
-                        
-                        let pts_vals = calc_arrowhead_points(tail_x, tail_y, tip_x, tip_y, arrowhead_type, el.stroke_width, segment_length);
-                        let pts = convert_arrowhead_points(arrowhead_type, pts_vals);
-                        
+                        }
+                    } else {
+                        // Elbowed, sharp or insufficient points - use straight line
+                        let (first_rel_x, first_rel_y) = points[0];
+                        let (second_rel_x, second_rel_y) = points[1];
+                        let tip_x = el.x + first_rel_x;
+                        let tip_y = el.y + first_rel_y;
+                        let tail_x = el.x + second_rel_x;
+                        let tail_y = el.y + second_rel_y;
+                        let seg_len = ((tip_x - tail_x).powi(2) + (tip_y - tail_y).powi(2)).sqrt();
+                        (tail_x, tail_y, tip_x, tip_y, seg_len)
+                    };
+                    
+                    let pts_vals = calc_arrowhead_points(tail_x, tail_y, tip_x, tip_y, arrowhead_type, el.stroke_width, segment_length);
+                    let pts = convert_arrowhead_points(arrowhead_type, pts_vals);
+                    
+                    arrowheads_svg.push('\n');
+                    arrowheads_svg.push_str(&render_arrowhead(
+                        arrowhead_type,
+                        pts.clone(),
+                        stroke_color,
+                        canvas_color,
+                        el.stroke_width,
+                        opacity,
+                        &transform,
+                    ));
+
+                    // Rough imperfect second pass for start arrowhead if roughness > 0
+                    if el.roughness > 0.0 && arrowhead_type != Arrowhead::Dot {
+                        let mut rng = LcgRng::new(el.seed ^ 0xABCDEF);
+                        let jitter = (0.6 + 0.2 * el.stroke_width) * el.roughness.max(0.0);
+                        let jx = rng.range(-jitter, jitter);
+                        let jy = rng.range(-jitter, jitter);
+                        let pts_rough_vals = calc_arrowhead_points(
+                            tail_x + jx, tail_y + jy, 
+                            tip_x + jx, tip_y + jy, 
+                            arrowhead_type, 
+                            el.stroke_width * rng.range(0.95, 1.05), 
+                            segment_length
+                        );
+                        let pts_rough = convert_arrowhead_points(arrowhead_type, pts_rough_vals);
+                        let opacity2 = (opacity * 0.9).min(1.0);
                         arrowheads_svg.push('\n');
                         arrowheads_svg.push_str(&render_arrowhead(
                             arrowhead_type,
-                            pts.clone(),
+                            pts_rough,
                             stroke_color,
-                            background_color,
+                            canvas_color,
                             el.stroke_width,
-                            opacity,
+                            opacity2,
                             &transform,
                         ));
-
-                        // Rough imperfect second pass for start arrowhead if roughness > 0
-                        if el.roughness > 0.0 && arrowhead_type != "dot" {
-                            let mut rng = LcgRng::new(el.seed ^ 0xABCDEF);
-                            let jitter = (0.6 + 0.2 * el.stroke_width) * el.roughness.max(0.0);
-                            let jx = rng.range(-jitter, jitter);
-                            let jy = rng.range(-jitter, jitter);
-                            let pts_rough_vals = calc_arrowhead_points(
-                                tail_x + jx, tail_y + jy, 
-                                tip_x + jx, tip_y + jy, 
-                                arrowhead_type, 
-                                el.stroke_width * rng.range(0.95, 1.05), 
-                                segment_length
-                            );
-                            let pts_rough = convert_arrowhead_points(arrowhead_type, pts_rough_vals);
-                            let opacity2 = (opacity * 0.9).min(1.0);
-                            arrowheads_svg.push('\n');
-                            arrowheads_svg.push_str(&render_arrowhead(
-                                arrowhead_type,
-                                pts_rough,
-                                stroke_color,
-                                background_color,
-                                el.stroke_width,
-                                opacity2,
-                                &transform,
-                            ));
-                        }
                     }
+                }
 
-                    // Main shaft path with rounded caps/joins
-                    let shaft_svg = format!(
-                        r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}" opacity="{}"{} transform="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
-                        path_data, stroke_color, el.stroke_width, opacity, dasharray_attr, transform
-                    );
+                // Main shaft path with rounded caps/joins
+                let shaft_svg = format!(
+                    r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}" opacity="{}"{} transform="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                    path_data, stroke_color, el.stroke_width, opacity, dasharray_attr, transform
+                );
 
-                    // Rough multi-pass rendering for shaft if roughness > 0
-                    let mut rough_passes = vec![shaft_svg];
+                // Rough multi-pass rendering for shaft if roughness > 0
+                let mut rough_passes = vec![shaft_svg];
+                
+                if el.roughness > 0.0 {
+                    let mut rng = LcgRng::new(el.seed);
+                    let amplitude = (1.2 + 0.3 * el.stroke_width) * el.roughness.max(0.0);
                     
-                    if el.roughness > 0.0 {
-                        let mut rng = LcgRng::new(el.seed);
-                        let amplitude = (1.2 + 0.3 * el.stroke_width) * el.roughness.max(0.0);
-                        
-                        // Secondary pass - main jitter
-                        let jittered = jitter_polyline(&abs_points, &mut rng, amplitude);
-                        let jitter_path = if elbowed {
+                    // Secondary pass - main jitter
+                    let jittered = jitter_polyline(&abs_points, &mut rng, amplitude);
+                    let jitter_path = if elbowed {
+                        format!(
+                            "M {}",
+                            jittered
+                                .iter()
+                                .map(|(x, y)| format!("{x},{y}"))
+                                .collect::<Vec<_>>()
+                                .join(" L ")
+                        )
+                    } else {
+                        catmull_rom_path(&jittered)
+                    };
+                    let opacity2 = (opacity * 0.85).min(1.0);
+                    rough_passes.push(format!(
+                        r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}" opacity="{}"{} transform="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                        jitter_path, stroke_color, el.stroke_width, opacity2, dasharray_attr, transform
+                    ));
+                    
+                    // Tertiary pass for high roughness
+                    if el.roughness > 1.0 {
+                        let mut rng3 = LcgRng::new(el.seed.wrapping_add(0x55555555_u32 as i32));
+                        let amplitude3 = amplitude * 0.6;
+                        let jittered3 = jitter_polyline(&abs_points, &mut rng3, amplitude3);
+                        let jitter_path3 = if elbowed {
                             format!(
                                 "M {}",
-                                jittered
+                                jittered3
                                     .iter()
                                     .map(|(x, y)| format!("{x},{y}"))
                                     .collect::<Vec<_>>()
                                     .join(" L ")
                             )
                         } else {
-                            catmull_rom_path(&jittered)
+                            catmull_rom_path(&jittered3)
                         };
-                        let opacity2 = (opacity * 0.85).min(1.0);
+                        let opacity3 = (opacity * 0.7).min(1.0);
                         rough_passes.push(format!(
                             r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}" opacity="{}"{} transform="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
-                            jitter_path, stroke_color, el.stroke_width, opacity2, dasharray_attr, transform
+                            jitter_path3, stroke_color, el.stroke_width, opacity3, dasharray_attr, transform
                         ));
-                        
-                        // Tertiary pass for high roughness
-                        if el.roughness > 1.0 {
-                            let mut rng3 = LcgRng::new(el.seed.wrapping_add(0x55555555_u32 as i32));
-                            let amplitude3 = amplitude * 0.6;
-                            let jittered3 = jitter_polyline(&abs_points, &mut rng3, amplitude3);
-                            let jitter_path3 = if elbowed {
-                                format!(
-                                    "M {}",
-                                    jittered3
-                                        .iter()
-                                        .map(|(x, y)| format!("{x},{y}"))
-                                        .collect::<Vec<_>>()
-                                        .join(" L ")
-                                )
-                            } else {
-                                catmull_rom_path(&jittered3)
-                            };
-                            let opacity3 = (opacity * 0.7).min(1.0);
-                            rough_passes.push(format!(
-                                r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}" opacity="{}"{} transform="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
-                                jitter_path3, stroke_color, el.stroke_width, opacity3, dasharray_attr, transform
-                            ));
-                        }
                     }
-
-                    let all_shafts = rough_passes.join("\n");
-                    
-                    return if arrowheads_svg.is_empty() {
-                        all_shafts
-                    } else {
-                        format!("{all_shafts}\n{arrowheads_svg}")
-                    };
                 }
+
+                let all_shafts = rough_passes.join("\n");
+                
+                return if arrowheads_svg.is_empty() {
+                    all_shafts
+                } else {
+                    format!("{all_shafts}\n{arrowheads_svg}")
+                };
             }
             String::new()
         }
-        "text" => {
-            let font_size = el.font_size.unwrap_or(16.0);
-            let text = el.text.as_deref().unwrap_or("");
-            let font_family = get_font_family(el.font_family);
+        ElementKind::Text { text, font_size, font_family, text_align, .. } => {
+            let font_family = get_svg_font_family(fonts, font_family);
             let line_height_px = get_line_height(font_size, el.line_height);
             
-            let x_pos = calculate_text_x_position(el.x, el.width, el.text_align.as_deref());
-            let alignment_anchor = get_svg_text_anchor(el.text_align.as_deref());
+            let x_pos = calculate_text_x_position(el.x, el.width, Some(text_align));
+            let alignment_anchor = get_svg_text_anchor(Some(text_align));
             
             // Calculate vertical offset based on font metrics
             let vertical_offset = get_vertical_offset(None, font_size);
@@ -1162,7 +1191,97 @@ fn render_element(el: &ExcalidrawElement, _viewbox: &ViewBox) -> String {
                 tspan_elements.join("\n")
             )
         }
-        _ => String::new(),
+        ElementKind::Freedraw { points, .. } => {
+            let mut svg_parts = Vec::new();
+
+            // Closed pen loops get a background fill, like Excalidraw's rough curve
+            if should_fill && is_path_a_loop(points) {
+                let points_str = points
+                    .iter()
+                    .map(|(px, py)| format!("{},{}", el.x + px, el.y + py))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                svg_parts.push(format!(
+                    r#"<polygon points="{points_str}" fill="{background_color}" stroke="none" opacity="{opacity}" transform="{transform}"/>"#
+                ));
+            }
+
+            // The stroke itself is a filled, pressure-variable outline
+            if should_stroke {
+                let outline = get_freedraw_outline(el);
+                let path_data = get_svg_path_from_stroke(&outline, (el.x, el.y));
+                if !path_data.is_empty() {
+                    svg_parts.push(format!(
+                        r#"<path d="{path_data}" fill="{stroke_color}" stroke="none" opacity="{opacity}" transform="{transform}"/>"#
+                    ));
+                }
+            }
+
+            svg_parts.join("\n")
+        }
+        ElementKind::Image { file_id, crop } => {
+            // Like Excalidraw's SVG export, images without file data are skipped
+            let Some(file) = file_id
+                .filter(|_| el.status.as_deref() != Some("error"))
+                .and_then(|file_id| files.get(file_id))
+                .filter(|file| !file.data_url.is_empty())
+            else {
+                return String::new();
+            };
+
+            let (flip_x, flip_y) = get_image_flip(el);
+            let flip_transform = if flip_x != 1.0 || flip_y != 1.0 {
+                format!(
+                    " translate({center_x} {center_y}) scale({flip_x} {flip_y}) translate({} {})",
+                    -center_x, -center_y
+                )
+            } else {
+                String::new()
+            };
+            let href = escape_xml(&file.data_url);
+
+            // Cropped images use a nested viewport over the natural image coordinates
+            let image_svg = match crop {
+                Some(crop) if crop.width > 0.0 && crop.height > 0.0 => format!(
+                    r#"<svg x="{}" y="{}" width="{}" height="{}" viewBox="{} {} {} {}" preserveAspectRatio="none"><image width="{}" height="{}" href="{}" preserveAspectRatio="none"/></svg>"#,
+                    el.x, el.y, el.width, el.height,
+                    crop.x, crop.y, crop.width, crop.height,
+                    crop.natural_width, crop.natural_height, href
+                ),
+                _ => format!(
+                    r#"<image x="{}" y="{}" width="{}" height="{}" href="{}" preserveAspectRatio="none"/>"#,
+                    el.x, el.y, el.width, el.height, href
+                ),
+            };
+
+            format!(r#"<g opacity="{opacity}" transform="{transform}{flip_transform}">{image_svg}</g>"#)
+        }
+        ElementKind::Frame { .. } | ElementKind::Magicframe { .. } => {
+            // Frames are never rough: a rounded outline with the title above the top-left corner
+            let frame_stroke_color = get_themed_color(FRAME_STROKE_COLOR, theme);
+            let frame_name_color = get_themed_color(FRAME_NAME_COLOR, theme);
+            format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{FRAME_RADIUS}" ry="{FRAME_RADIUS}" fill="none" stroke="{frame_stroke_color}" stroke-width="{FRAME_STROKE_WIDTH}" opacity="{opacity}"/>
+<text x="{}" y="{}" font-size="{FRAME_NAME_FONT_SIZE}" font-family="{FRAME_NAME_FONT_FAMILY}" fill="{frame_name_color}" opacity="{opacity}" dominant-baseline="alphabetic" style="white-space: pre;">{}</text>"#,
+                el.x, el.y, el.width, el.height,
+                el.x, get_frame_name_baseline(el), escape_xml(get_frame_name(el))
+            )
+        }
+        // Reported as warnings before rendering
+        ElementKind::Embeddable | ElementKind::Iframe | ElementKind::Unknown => String::new(),
+    }
+}
+
+/// `font-family` of a text: the Excalidraw family, followed by the family the other backends
+/// draw with when that differs (e.g. "Nunito, Liberation Sans"), so viewers that have the
+/// real font use it
+fn get_svg_font_family(fonts: &dyn FontProvider, font_id: Option<i32>) -> String {
+    let family = get_font_family(font_id);
+    let drawn = fonts.family_for_id(font_id);
+    if drawn.eq_ignore_ascii_case(family) {
+        drawn
+    } else {
+        format!("{family}, {drawn}")
     }
 }
 
@@ -1175,12 +1294,12 @@ fn escape_xml(s: &str) -> String {
 }
 
 /// Generate hachure pattern (diagonal lines) for a rectangle
-fn generate_hachure_pattern(x: f64, y: f64, width: f64, height: f64, angle: f64) -> String {
+/// The pattern is built in unrotated space; the element transform rotates it with the shape
+fn generate_hachure_pattern(x: f64, y: f64, width: f64, height: f64) -> String {
     let gap = 4.0; // spacing between hachure lines
-    let hachure_angle = -45.0; // diagonal lines at -45 degrees
+    let hachure_angle: f64 = -45.0; // diagonal lines at -45 degrees
     
-    // Calculate the angle in radians accounting for both shape rotation and hachure angle
-    let rad = (angle + hachure_angle).to_radians();
+    let rad = hachure_angle.to_radians();
     let cos_angle = rad.cos();
     let sin_angle = rad.sin();
     
@@ -1286,19 +1405,107 @@ fn clip_line_to_rect(x1: f64, y1: f64, x2: f64, y2: f64, rx: f64, ry: f64, rw: f
 }
 
 pub fn generate_svg(data: &ExcalidrawData, background: Option<(u8,u8,u8,u8)>) -> String {
-    let viewbox = calculate_viewbox(&data.elements);
+    let options = RenderOptions {
+        background,
+        ..RenderOptions::default()
+    };
+    let viewbox = calculate_viewbox_with_padding(&data.elements, options.resolve_padding(data));
+    let elements: Vec<&ExcalidrawElement> = data.elements.iter().collect();
+    build_svg(
+        data,
+        &viewbox,
+        &elements,
+        options.resolve_background(data),
+        options.resolve_matte(data),
+        options.resolve_theme(data),
+        options.fonts.as_ref(),
+        "",
+    )
+}
 
-    let elements_svg = data
-        .elements
+/// Generate an SVG document with the given options
+/// Fails when `options.frame` does not name a frame of the scene, or `options.region` is empty.
+pub fn generate_svg_with_options(data: &ExcalidrawData, options: &RenderOptions) -> Result<String> {
+    let padding = options.resolve_padding(data);
+    let (viewbox, elements) = resolve_export_scope(data, options.frame.as_deref(), options.region, padding)?;
+    options.report_element_warnings(&elements, &data.files);
+    let metadata = match options.resolve_embedded_scene(data)? {
+        Some(scene) => format!("  {}\n", svg_scene_metadata(&scene)?),
+        None => String::new(),
+    };
+    Ok(build_svg(
+        data,
+        &viewbox,
+        &elements,
+        options.resolve_background(data),
+        options.resolve_matte(data),
+        options.resolve_theme(data),
+        options.fonts.as_ref(),
+        &metadata,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_svg(
+    data: &ExcalidrawData,
+    viewbox: &ViewBox,
+    elements: &[&ExcalidrawElement],
+    background: Option<(u8,u8,u8,u8)>,
+    canvas: (u8, u8, u8),
+    theme: Theme,
+    fonts: &dyn FontProvider,
+    metadata: &str,
+) -> String {
+    // Children of a frame are clipped to the frame's bounds, and arrow strokes are masked
+    // behind their labels
+    let frames = collect_frames(&data.elements);
+    let mut extra_defs = String::new();
+    let mut clipped_frames = std::collections::HashSet::new();
+
+    // Text is wrapped to its available width, and bound text positioned inside its container
+    let elements_by_id = index_elements(&data.elements);
+    // Outline arrowheads are filled with the canvas color
+    let canvas_color = format!("#{:02x}{:02x}{:02x}", canvas.0, canvas.1, canvas.2);
+
+    let elements_svg = elements
         .iter()
-        .map(|el| render_element(el, &viewbox))
+        .map(|el| {
+            let el = layout_text_element(el, &elements_by_id, fonts);
+            let element_svg = render_element(&el, viewbox, &data.files, theme, &canvas_color, fonts);
+            let element_svg = match get_arrow_label_mask_box(&el, &elements_by_id, fonts) {
+                Some((mask_x, mask_y, mask_w, mask_h)) if !element_svg.is_empty() => {
+                    let mask_id = format!("mask-{}", escape_xml(&el.id));
+                    extra_defs.push_str(&format!(
+                        "    <mask id=\"{}\" maskUnits=\"userSpaceOnUse\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\">\n      <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#fff\"/>\n      <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#000\"/>\n    </mask>\n",
+                        mask_id, viewbox.min_x, viewbox.min_y, viewbox.width, viewbox.height,
+                        viewbox.min_x, viewbox.min_y, viewbox.width, viewbox.height,
+                        mask_x, mask_y, mask_w, mask_h
+                    ));
+                    format!("<g mask=\"url(#{mask_id})\">{element_svg}</g>")
+                }
+                _ => element_svg,
+            };
+            match get_containing_frame(&el, &frames) {
+                Some(frame) if !element_svg.is_empty() => {
+                    let clip_id = format!("clip-frame-{}", escape_xml(&frame.id));
+                    if clipped_frames.insert(frame.id.as_str()) {
+                        extra_defs.push_str(&format!(
+                            "    <clipPath id=\"{}\">\n      <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{FRAME_RADIUS}\" ry=\"{FRAME_RADIUS}\"/>\n    </clipPath>\n",
+                            clip_id, frame.x, frame.y, frame.width, frame.height
+                        ));
+                    }
+                    format!("<g clip-path=\"url(#{clip_id})\">{element_svg}</g>")
+                }
+                _ => element_svg,
+            }
+        })
         .collect::<Vec<_>>()
         .join("\n");
 
     let fill_color = "#000000";
 
     // Optional background rect
-    let bg_rect = if let Some((r,g,b,a)) = background {
+    let bg_rect = if let Some((r,g,b,a)) = background.map(|rgba| get_themed_rgba(rgba, theme)) {
         if a == 0 { String::new() } else {
             let hex = format!("#{r:02x}{g:02x}{b:02x}");
             let opacity = (a as f64) / 255.0;
@@ -1312,7 +1519,7 @@ pub fn generate_svg(data: &ExcalidrawData, background: Option<(u8,u8,u8,u8)>) ->
     };
 
     format!(
-        "<svg viewBox=\"{} {} {} {}\" xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\">\n  <defs>\n    <marker id=\"arrowhead\" markerWidth=\"10\" markerHeight=\"10\" refX=\"9\" refY=\"3\" orient=\"auto\">\n      <polygon points=\"0 0, 10 3, 0 6\" fill=\"{}\"/>\n    </marker>\n  </defs>\n{}  {}\n</svg>",
-        viewbox.min_x, viewbox.min_y, viewbox.width, viewbox.height, fill_color, bg_rect, elements_svg
+        "<svg viewBox=\"{} {} {} {}\" xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\">\n{}  <defs>\n    <marker id=\"arrowhead\" markerWidth=\"10\" markerHeight=\"10\" refX=\"9\" refY=\"3\" orient=\"auto\">\n      <polygon points=\"0 0, 10 3, 0 6\" fill=\"{}\"/>\n    </marker>\n{}  </defs>\n{}  {}\n</svg>",
+        viewbox.min_x, viewbox.min_y, viewbox.width, viewbox.height, metadata, fill_color, extra_defs, bg_rect, elements_svg
     )
 }
@@ -0,0 +1,870 @@
+//! PDF backend
+//! Draws the same rough.js paths and glyph outlines as `renderer_skia`, but as vector
+//! content: text is outlined from the embedded fonts, so pages need no font resources.
+//! Element links become clickable link annotations.
+use crate::bound_text_utils::index_elements;
+use crate::color_utils::{get_themed_rgba, has_fill, has_stroke, parse_color};
+use crate::fonts::{resolve_family_fonts, FontFace, FontProvider};
+use crate::frame_utils::{
+    collect_frames, get_containing_frame, get_frame_name, get_frame_name_baseline, get_ordered_frames, FRAME_NAME_COLOR,
+    FRAME_NAME_FONT_FAMILY, FRAME_NAME_FONT_SIZE, FRAME_RADIUS, FRAME_STROKE_COLOR, FRAME_STROKE_WIDTH,
+};
+use crate::freedraw_utils::{get_freedraw_outline, is_path_a_loop};
+use crate::image_utils::{get_image_crop_rect, get_image_flip, get_uncropped_size, load_file_image};
+use crate::math_utils::calculate_center;
+use crate::models::{Arrowhead, ElementKind, ExcalidrawData, ExcalidrawElement as Element, FileData, TextAlign, ViewBox};
+use crate::options::{RenderOptions, Theme};
+use crate::rect_utils::{generate_rounded_rect_path, get_corner_radius};
+use crate::renderer_skia::{
+    build_freedraw_path, build_linear_path_d, build_rough_options, build_rounded_rect_path,
+    exca_arrowhead_points, get_element_rotation, get_layer_opacity, position_glyphs,
+};
+use crate::text_layout::{get_arrow_label_mask_box, layout_text_element};
+use crate::utils::{get_rotated_element_bounds, resolve_export_scope};
+use crate::error::{ExcaliosaError, Result};
+use euclid::default::Point2D;
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use pdf_writer::types::{ActionType, AnnotationType, LineCapStyle, LineJoinStyle};
+use pdf_writer::writers::{ExtGraphicsState, Resources};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect as PdfRect, Ref, Str};
+use roughr::core::{Drawable, Op, OpSetType, OpType, Options};
+use roughr::generator::Generator;
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::{DrawSettings, OutlinePen};
+use skrifa::raw::FontRef as ReadFontsRef;
+use skrifa::MetadataProvider;
+use std::collections::HashMap;
+use tiny_skia::{IntRect, Path, PathBuilder, PathSegment, Pixmap, Transform};
+
+/// PDF points per scene pixel: scenes are laid out at 96 DPI, PDF user space is 72 DPI
+const PT_PER_PX: f32 = 0.75;
+
+/// SVG images are rasterized at this multiple of their displayed size, for print
+const SVG_IMAGE_SCALE: f64 = 3.0;
+
+/// zlib level for content streams and image data
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// A PDF under construction
+/// Allocates object ids and collects the resources shared by every content stream:
+/// alpha graphics states, images and transparency groups. They are written once, as a
+/// single indirect resource dictionary, when the document is finished.
+struct PdfDocument {
+    pdf: Pdf,
+    next_id: i32,
+    resources_id: Ref,
+    alpha_states: Vec<u8>,
+    x_objects: Vec<Ref>,
+}
+
+impl PdfDocument {
+    fn new() -> Self {
+        Self {
+            pdf: Pdf::new(),
+            next_id: 2,
+            resources_id: Ref::new(1),
+            alpha_states: Vec::new(),
+            x_objects: Vec::new(),
+        }
+    }
+
+    fn alloc(&mut self) -> Ref {
+        let id = Ref::new(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Resource name of a graphics state with the given stroke and fill alpha
+    fn alpha_state(&mut self, alpha: u8) -> String {
+        let index = match self.alpha_states.iter().position(|a| *a == alpha) {
+            Some(index) => index,
+            None => {
+                self.alpha_states.push(alpha);
+                self.alpha_states.len() - 1
+            }
+        };
+        format!("A{index}")
+    }
+
+    fn add_x_object(&mut self, id: Ref) -> String {
+        self.x_objects.push(id);
+        format!("X{}", self.x_objects.len() - 1)
+    }
+
+    /// Embed a pixmap as an RGB image with a soft mask for its alpha
+    fn add_image(&mut self, pixmap: &Pixmap) -> String {
+        let mut rgb = Vec::with_capacity(pixmap.pixels().len() * 3);
+        let mut alpha = Vec::with_capacity(pixmap.pixels().len());
+        for pixel in pixmap.pixels() {
+            let color = pixel.demultiply();
+            rgb.extend([color.red(), color.green(), color.blue()]);
+            alpha.push(color.alpha());
+        }
+        let rgb = compress_to_vec_zlib(&rgb, COMPRESSION_LEVEL);
+        let alpha = compress_to_vec_zlib(&alpha, COMPRESSION_LEVEL);
+
+        let image_id = self.alloc();
+        let mask_id = self.alloc();
+        let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+
+        let mut image = self.pdf.image_xobject(image_id, &rgb);
+        image.filter(Filter::FlateDecode);
+        image.width(width).height(height).bits_per_component(8).s_mask(mask_id);
+        image.color_space().device_rgb();
+        image.finish();
+
+        let mut mask = self.pdf.image_xobject(mask_id, &alpha);
+        mask.filter(Filter::FlateDecode);
+        mask.width(width).height(height).bits_per_component(8);
+        mask.color_space().device_gray();
+        mask.finish();
+
+        self.add_x_object(image_id)
+    }
+
+    /// Wrap content in a transparency group, so it can be composited as a unit
+    fn add_group(&mut self, content: Content, bbox: PdfRect) -> String {
+        let id = self.alloc();
+        let resources_id = self.resources_id;
+        let data = compress_to_vec_zlib(&content.finish(), COMPRESSION_LEVEL);
+        let mut form = self.pdf.form_xobject(id, &data);
+        form.filter(Filter::FlateDecode);
+        form.bbox(bbox);
+        form.group().transparency().isolated(true);
+        form.pair(Name(b"Resources"), resources_id);
+        form.finish();
+        self.add_x_object(id)
+    }
+
+    /// Write a page with the given content and links to the page tree
+    fn add_page(
+        &mut self,
+        page_tree_id: Ref,
+        size: (f32, f32),
+        content: Content,
+        links: &[(PdfRect, String)],
+    ) -> Ref {
+        let page_id = self.alloc();
+        let content_id = self.alloc();
+        let resources_id = self.resources_id;
+
+        let data = compress_to_vec_zlib(&content.finish(), COMPRESSION_LEVEL);
+        self.pdf.stream(content_id, &data).filter(Filter::FlateDecode);
+
+        let mut page = self.pdf.page(page_id);
+        page.parent(page_tree_id)
+            .media_box(PdfRect::new(0.0, 0.0, size.0, size.1))
+            .contents(content_id);
+        page.pair(Name(b"Resources"), resources_id);
+        if !links.is_empty() {
+            let mut annotations = page.annotations();
+            for (rect, uri) in links {
+                let mut annotation = annotations.push();
+                annotation.subtype(AnnotationType::Link).rect(*rect).border(0.0, 0.0, 0.0, None);
+                annotation.action().action_type(ActionType::Uri).uri(Str(uri.as_bytes()));
+            }
+        }
+        page.finish();
+        page_id
+    }
+
+    /// Write the shared resources, page tree and catalog, and return the file bytes
+    fn finish(mut self, page_tree_id: Ref, page_ids: &[Ref]) -> Vec<u8> {
+        let catalog_id = self.alloc();
+
+        let mut resources = self.pdf.indirect(self.resources_id).start::<Resources>();
+        let mut states = resources.ext_g_states();
+        for (index, alpha) in self.alpha_states.iter().enumerate() {
+            let name = format!("A{index}");
+            let alpha = *alpha as f32 / 255.0;
+            states
+                .insert(Name(name.as_bytes()))
+                .start::<ExtGraphicsState>()
+                .stroking_alpha(alpha)
+                .non_stroking_alpha(alpha);
+        }
+        states.finish();
+        let mut x_objects = resources.x_objects();
+        for (index, id) in self.x_objects.iter().enumerate() {
+            let name = format!("X{index}");
+            x_objects.pair(Name(name.as_bytes()), *id);
+        }
+        x_objects.finish();
+        resources.finish();
+
+        self.pdf
+            .pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+        self.pdf.catalog(catalog_id).pages(page_tree_id);
+        self.pdf.finish()
+    }
+}
+
+/// How a path is painted
+#[derive(Clone, Copy)]
+enum PaintOp<'a> {
+    Fill { even_odd: bool },
+    Stroke { width: f32, dash: Option<&'a [f64]> },
+}
+
+/// Paint the path built by `build` with a color, applying its alpha through a graphics state
+fn paint_path(
+    content: &mut Content,
+    doc: &mut PdfDocument,
+    rgba: (u8, u8, u8, u8),
+    op: PaintOp,
+    build: impl FnOnce(&mut Content),
+) {
+    let (r, g, b, a) = rgba;
+    if a == 0 {
+        return;
+    }
+    content.save_state();
+    if a < 255 {
+        let state = doc.alpha_state(a);
+        content.set_parameters(Name(state.as_bytes()));
+    }
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    match op {
+        PaintOp::Fill { .. } => {
+            content.set_fill_rgb(r, g, b);
+        }
+        PaintOp::Stroke { width, dash } => {
+            content
+                .set_stroke_rgb(r, g, b)
+                .set_line_width(width)
+                .set_line_cap(LineCapStyle::RoundCap)
+                .set_line_join(LineJoinStyle::RoundJoin);
+            if let Some(dash) = dash {
+                content.set_dash_pattern(dash.iter().map(|d| *d as f32), 0.0);
+            }
+        }
+    }
+    build(content);
+    match op {
+        PaintOp::Fill { even_odd: true } => content.fill_even_odd(),
+        PaintOp::Fill { even_odd: false } => content.fill_nonzero(),
+        PaintOp::Stroke { .. } => content.stroke(),
+    };
+    content.restore_state();
+}
+
+/// Append a tiny-skia path; quadratic segments become cubics
+fn push_path(content: &mut Content, path: &Path) {
+    let mut start = (0.0, 0.0);
+    let mut last = (0.0, 0.0);
+    for segment in path.segments() {
+        match segment {
+            PathSegment::MoveTo(p) => {
+                content.move_to(p.x, p.y);
+                start = (p.x, p.y);
+                last = start;
+            }
+            PathSegment::LineTo(p) => {
+                content.line_to(p.x, p.y);
+                last = (p.x, p.y);
+            }
+            PathSegment::QuadTo(c, p) => {
+                push_quad(content, last, (c.x, c.y), (p.x, p.y));
+                last = (p.x, p.y);
+            }
+            PathSegment::CubicTo(c1, c2, p) => {
+                content.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+                last = (p.x, p.y);
+            }
+            PathSegment::Close => {
+                content.close_path();
+                last = start;
+            }
+        }
+    }
+}
+
+/// Append a quadratic Bézier as the equivalent cubic
+fn push_quad(content: &mut Content, from: (f32, f32), control: (f32, f32), to: (f32, f32)) {
+    let c1 = (from.0 + (control.0 - from.0) * 2.0 / 3.0, from.1 + (control.1 - from.1) * 2.0 / 3.0);
+    let c2 = (to.0 + (control.0 - to.0) * 2.0 / 3.0, to.1 + (control.1 - to.1) * 2.0 / 3.0);
+    content.cubic_to(c1.0, c1.1, c2.0, c2.1, to.0, to.1);
+}
+
+/// Append rough.js path operations
+fn push_rough_ops(content: &mut Content, ops: &[Op<f32>]) {
+    for op in ops {
+        let d = &op.data;
+        match op.op {
+            OpType::Move => content.move_to(d[0], d[1]),
+            OpType::LineTo => content.line_to(d[0], d[1]),
+            OpType::BCurveTo => content.cubic_to(d[0], d[1], d[2], d[3], d[4], d[5]),
+        };
+    }
+}
+
+fn srgba_to_rgba8(color: Option<palette::Srgba>) -> (u8, u8, u8, u8) {
+    let Some(color) = color else {
+        return (0, 0, 0, 0);
+    };
+    let color: palette::Srgba<u8> = color.into_format();
+    (color.red, color.green, color.blue, color.alpha)
+}
+
+/// Paint a rough.js drawable the way `rough_tiny_skia` does: outlines stroked with the
+/// stroke color, solid fills filled, and sketch fills (hachure lines) stroked with the fill color
+fn draw_rough(content: &mut Content, doc: &mut PdfDocument, drawable: &Drawable<f32>) {
+    let options = &drawable.options;
+    let stroke_width = options.stroke_width.unwrap_or(1.0);
+    for set in &drawable.sets {
+        match set.op_set_type {
+            OpSetType::Path => {
+                let op = PaintOp::Stroke {
+                    width: stroke_width,
+                    dash: options.stroke_line_dash.as_deref(),
+                };
+                paint_path(content, doc, srgba_to_rgba8(options.stroke), op, |c| push_rough_ops(c, &set.ops));
+            }
+            OpSetType::FillPath => {
+                let even_odd = matches!(drawable.shape.as_str(), "curve" | "polygon" | "path");
+                let op = PaintOp::Fill { even_odd };
+                paint_path(content, doc, srgba_to_rgba8(options.fill), op, |c| push_rough_ops(c, &set.ops));
+            }
+            OpSetType::FillSketch => {
+                let mut fill_weight = options.fill_weight.unwrap_or_default();
+                if fill_weight < 0.0 {
+                    fill_weight = stroke_width / 2.0;
+                }
+                let op = PaintOp::Stroke {
+                    width: fill_weight,
+                    dash: options.fill_line_dash.as_deref(),
+                };
+                paint_path(content, doc, srgba_to_rgba8(options.fill), op, |c| push_rough_ops(c, &set.ops));
+            }
+        }
+    }
+}
+
+/// Glyph outlines written into a content stream, flipping font units to y-down
+struct PdfPen<'a> {
+    content: &'a mut Content,
+    x: f32,
+    y: f32,
+    last: (f32, f32),
+}
+
+impl OutlinePen for PdfPen<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.last = (self.x + x, self.y - y);
+        self.content.move_to(self.last.0, self.last.1);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.last = (self.x + x, self.y - y);
+        self.content.line_to(self.last.0, self.last.1);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        let to = (self.x + x, self.y - y);
+        push_quad(self.content, self.last, (self.x + cx0, self.y - cy0), to);
+        self.last = to;
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.last = (self.x + x, self.y - y);
+        self.content.cubic_to(
+            self.x + cx0,
+            self.y - cy0,
+            self.x + cx1,
+            self.y - cy1,
+            self.last.0,
+            self.last.1,
+        );
+    }
+
+    fn close(&mut self) {
+        self.content.close_path();
+    }
+}
+
+/// Draw text as filled glyph outlines; `y` is the first line's baseline
+/// `fonts` starts with the text's own font, followed by fallbacks for the characters it lacks.
+#[allow(clippy::too_many_arguments)]
+fn draw_text(
+    content: &mut Content,
+    doc: &mut PdfDocument,
+    text: &str,
+    x: f32,
+    y: f32,
+    font_size: f32,
+    color: (u8, u8, u8, u8),
+    fonts: &[FontFace],
+    text_align: Option<TextAlign>,
+    container_width: f32,
+) {
+    let font_refs: Vec<ReadFontsRef> = fonts.iter().filter_map(FontFace::font_ref).collect();
+    if font_refs.is_empty() {
+        return;
+    }
+    let outlines: Vec<_> = font_refs.iter().map(|font| font.outline_glyphs()).collect();
+    let glyphs = position_glyphs(text, x, y, font_size, &font_refs, text_align, container_width);
+    paint_path(content, doc, color, PaintOp::Fill { even_odd: false }, |content| {
+        for glyph in glyphs {
+            if let Some(outline) = outlines[glyph.font].get(glyph.id) {
+                let mut pen = PdfPen {
+                    content: &mut *content,
+                    x: glyph.x,
+                    y: glyph.y,
+                    last: (glyph.x, glyph.y),
+                };
+                let settings = DrawSettings::unhinted(Size::new(font_size), LocationRef::default());
+                outline.draw(settings, &mut pen).ok();
+            }
+        }
+    });
+}
+
+/// Fill a tiny-skia path, then stroke it, as arrowheads are drawn
+fn fill_and_stroke(
+    content: &mut Content,
+    doc: &mut PdfDocument,
+    path: &Path,
+    fill_rgba: (u8, u8, u8, u8),
+    stroke_rgba: (u8, u8, u8, u8),
+    stroke_width: f32,
+) {
+    paint_path(content, doc, fill_rgba, PaintOp::Fill { even_odd: false }, |c| push_path(c, path));
+    let stroke = PaintOp::Stroke { width: stroke_width, dash: None };
+    paint_path(content, doc, stroke_rgba, stroke, |c| push_path(c, path));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_arrowhead(
+    content: &mut Content,
+    doc: &mut PdfDocument,
+    points: &[(f64, f64)],
+    x: f32,
+    y: f32,
+    stroke_rgba: (u8, u8, u8, u8),
+    canvas_rgba: (u8, u8, u8, u8),
+    stroke_width: f32,
+    arrowhead: Arrowhead,
+    position: &str,
+    cap_options: &Options,
+) {
+    let Some(vals) = exca_arrowhead_points(points, x, y, stroke_width, arrowhead, position) else {
+        return;
+    };
+    // Outline variants are filled with the canvas color
+    let fill_rgba = if matches!(arrowhead, Arrowhead::CircleOutline | Arrowhead::TriangleOutline | Arrowhead::DiamondOutline) { canvas_rgba } else { stroke_rgba };
+    let cap_line = |content: &mut Content, doc: &mut PdfDocument, x1: f32, y1: f32, x2: f32, y2: f32| {
+        let line = Generator::default().line::<f32>(x1, y1, x2, y2, &Some(cap_options.clone()));
+        draw_rough(content, doc, &line);
+    };
+
+    match arrowhead {
+        Arrowhead::Dot | Arrowhead::Circle | Arrowhead::CircleOutline => {
+            if let Some(path) = PathBuilder::from_circle(vals[0], vals[1], vals[2] / 2.0) {
+                fill_and_stroke(content, doc, &path, fill_rgba, stroke_rgba, stroke_width);
+            }
+        }
+        Arrowhead::Triangle | Arrowhead::TriangleOutline => {
+            let mut pb = PathBuilder::new();
+            pb.move_to(vals[0], vals[1]);
+            pb.line_to(vals[2], vals[3]);
+            pb.line_to(vals[4], vals[5]);
+            pb.close();
+            if let Some(path) = pb.finish() {
+                fill_and_stroke(content, doc, &path, fill_rgba, stroke_rgba, stroke_width);
+            }
+        }
+        Arrowhead::Diamond | Arrowhead::DiamondOutline => {
+            let mut pb = PathBuilder::new();
+            pb.move_to(vals[0], vals[1]);
+            pb.line_to(vals[2], vals[3]);
+            pb.line_to(vals[4], vals[5]);
+            pb.line_to(vals[6], vals[7]);
+            pb.close();
+            if let Some(path) = pb.finish() {
+                fill_and_stroke(content, doc, &path, fill_rgba, stroke_rgba, stroke_width);
+            }
+        }
+        Arrowhead::CrowfootOne | Arrowhead::Bar => {
+            cap_line(content, doc, vals[2], vals[3], vals[4], vals[5]);
+        }
+        _ => {
+            // default/arrow/crowfoot_many/crowfoot_one_or_many -> two lines to tip
+            cap_line(content, doc, vals[2], vals[3], vals[0], vals[1]);
+            cap_line(content, doc, vals[4], vals[5], vals[0], vals[1]);
+            if arrowhead == Arrowhead::CrowfootOneOrMany && let Some(bar) = exca_arrowhead_points(points, x, y, stroke_width, Arrowhead::CrowfootOne, position) {
+                cap_line(content, doc, bar[2], bar[3], bar[4], bar[5]);
+            }
+        }
+    }
+}
+
+/// PDF matrix of a tiny-skia transform
+fn to_pdf_matrix(transform: Transform) -> [f32; 6] {
+    [transform.sx, transform.ky, transform.kx, transform.sy, transform.tx, transform.ty]
+}
+
+/// Draw an image element, cropped and flipped, with its rotation and opacity
+/// Missing or undecodable files render as Excalidraw's gray placeholder.
+#[allow(clippy::too_many_arguments)]
+fn draw_image(
+    content: &mut Content,
+    doc: &mut PdfDocument,
+    element: &Element,
+    files: &HashMap<String, FileData>,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+) {
+    if width <= 0.0 || height <= 0.0 {
+        return;
+    }
+
+    let (cx, cy) = calculate_center(x, y, width, height);
+    let (flip_x, flip_y) = get_image_flip(element);
+    let base_transform = Transform::from_rotate_at(element.angle.to_degrees() as f32, cx, cy)
+        .pre_translate(cx, cy)
+        .pre_scale(flip_x as f32, flip_y as f32)
+        .pre_translate(-cx, -cy);
+    let opacity = (element.opacity / 100.0).clamp(0.0, 1.0);
+    let alpha = (opacity * 255.0).round() as u8;
+
+    let (uncropped_w, uncropped_h) = get_uncropped_size(element);
+    let svg_size = (
+        (uncropped_w * SVG_IMAGE_SCALE).ceil().max(1.0) as u32,
+        (uncropped_h * SVG_IMAGE_SCALE).ceil().max(1.0) as u32,
+    );
+    let image = element
+        .file_id
+        .as_deref()
+        .filter(|_| element.status.as_deref() != Some("error"))
+        .and_then(|file_id| files.get(file_id))
+        .and_then(|file| load_file_image(file, svg_size));
+
+    content.save_state();
+    content.transform(to_pdf_matrix(base_transform));
+
+    let Some(image) = image else {
+        paint_path(content, doc, (0xE7, 0xE7, 0xE7, alpha), PaintOp::Fill { even_odd: false }, |c| {
+            c.rect(x, y, width, height);
+        });
+        content.restore_state();
+        return;
+    };
+
+    // Crop in the decoded image's pixel space, clamped to the image bounds
+    let cropped = if element.crop.is_some() {
+        let (image_w, image_h) = (image.width() as f64, image.height() as f64);
+        let (crop_x, crop_y, crop_w, crop_h) = get_image_crop_rect(element, image_w, image_h);
+        let left = crop_x.round().clamp(0.0, image_w - 1.0);
+        let top = crop_y.round().clamp(0.0, image_h - 1.0);
+        let crop_w = crop_w.round().clamp(1.0, image_w - left);
+        let crop_h = crop_h.round().clamp(1.0, image_h - top);
+        IntRect::from_xywh(left as i32, top as i32, crop_w as u32, crop_h as u32)
+            .and_then(|rect| image.clone_rect(rect))
+            .unwrap_or(image)
+    } else {
+        image
+    };
+
+    let name = doc.add_image(&cropped);
+    if alpha < 255 {
+        let state = doc.alpha_state(alpha);
+        content.set_parameters(Name(state.as_bytes()));
+    }
+    // Image space is the unit square with its first row at the top; the page is y-down
+    content.transform([width, 0.0, 0.0, -height, x, y + height]);
+    content.x_object(Name(name.as_bytes()));
+    content.restore_state();
+}
+
+/// Draw one element in scene pixels relative to `offset`
+#[allow(clippy::too_many_arguments)]
+fn render_element(
+    content: &mut Content,
+    doc: &mut PdfDocument,
+    element: &Element,
+    offset: (f32, f32),
+    files: &HashMap<String, FileData>,
+    theme: Theme,
+    canvas_rgba: (u8, u8, u8, u8),
+    fonts: &dyn FontProvider,
+) {
+    let x = (element.x - offset.0 as f64) as f32;
+    let y = (element.y - offset.1 as f64) as f32;
+    let width = element.width as f32;
+    let height = element.height as f32;
+
+    // Element colors as drawn in the export theme
+    let stroke_rgba = get_themed_rgba(parse_color(&element.stroke_color), theme);
+    let fill_rgba = get_themed_rgba(parse_color(&element.background_color), theme);
+    let stroke_width = element.stroke_width as f32;
+
+    let rough = build_rough_options(element, stroke_rgba, fill_rgba, 1.0);
+    let generator = Generator::default();
+    let shape_options = Some(rough.shape.clone());
+    let stroke_only_options = Some(rough.stroke_only.clone());
+
+    match element.kind() {
+        ElementKind::Rectangle => {
+            let drawable = if element.roundness.is_some() {
+                let r = get_corner_radius(width.min(height) as f64, element);
+                let path_d = generate_rounded_rect_path(x as f64, y as f64, width as f64, height as f64, r);
+                generator.path::<f32>(path_d, &shape_options)
+            } else {
+                generator.rectangle::<f32>(x, y, width, height, &shape_options)
+            };
+            draw_rough(content, doc, &drawable);
+        }
+        ElementKind::Ellipse => {
+            let (cx, cy) = calculate_center(x, y, width, height);
+            let drawable = generator.ellipse::<f32>(cx, cy, width, height, &shape_options);
+            draw_rough(content, doc, &drawable);
+        }
+        ElementKind::Diamond => {
+            let (cx, cy) = calculate_center(x, y, width, height);
+            let points = vec![
+                Point2D::new(cx, y),
+                Point2D::new(x + width, cy),
+                Point2D::new(cx, y + height),
+                Point2D::new(x, cy),
+            ];
+            let drawable = generator.polygon(&points, &shape_options);
+            draw_rough(content, doc, &drawable);
+        }
+        ElementKind::Line { points } => {
+            if let Some(path_d) = build_linear_path_d(element, points, x, y, 1.0) {
+                let drawable = generator.path::<f32>(path_d, &stroke_only_options);
+                draw_rough(content, doc, &drawable);
+            }
+        }
+        ElementKind::Arrow { points, start_arrowhead, end_arrowhead, .. } => {
+            if let Some(path_d) = build_linear_path_d(element, points, x, y, 1.0) {
+                let drawable = generator.path::<f32>(path_d, &stroke_only_options);
+                draw_rough(content, doc, &drawable);
+            }
+            if points.len() >= 2 {
+                let arrowheads = [(start_arrowhead, "start"), (end_arrowhead, "end")];
+                for (arrowhead, position) in arrowheads {
+                    if let Some(arrowhead) = arrowhead {
+                        draw_arrowhead(
+                            content, doc, points, x, y, stroke_rgba, canvas_rgba, stroke_width, arrowhead, position, &rough.cap,
+                        );
+                    }
+                }
+            }
+        }
+        ElementKind::Text { text, font_size, font_family, text_align, .. } => {
+            if !text.is_empty() {
+                let font_size = font_size as f32;
+                let (text_fonts, _) = resolve_family_fonts(fonts, &fonts.family_for_id(font_family), text);
+                draw_text(
+                    content,
+                    doc,
+                    text,
+                    x,
+                    y + font_size,
+                    font_size,
+                    stroke_rgba,
+                    &text_fonts,
+                    Some(text_align),
+                    width,
+                );
+            }
+        }
+        ElementKind::Freedraw { points, .. } => {
+            // Closed pen loops get a rough background fill, like Excalidraw
+            if has_fill(element) && is_path_a_loop(points) {
+                let polygon_points: Vec<Point2D<f32>> = points
+                    .iter()
+                    .map(|p| Point2D::new(x + p.0 as f32, y + p.1 as f32))
+                    .collect();
+                let drawable = generator.polygon(&polygon_points, &Some(rough.loop_fill.clone()));
+                draw_rough(content, doc, &drawable);
+            }
+
+            // The stroke itself is a filled, pressure-variable outline
+            if has_stroke(element) {
+                let outline = get_freedraw_outline(element);
+                if let Some(path) = build_freedraw_path(&outline, x, y, 1.0) {
+                    paint_path(content, doc, stroke_rgba, PaintOp::Fill { even_odd: false }, |c| push_path(c, &path));
+                }
+            }
+        }
+        ElementKind::Image { .. } => {
+            draw_image(content, doc, element, files, x, y, width, height);
+        }
+        ElementKind::Frame { .. } | ElementKind::Magicframe { .. } => {
+            if let Some(path) = build_rounded_rect_path(x, y, width, height, FRAME_RADIUS as f32) {
+                let rgba = get_themed_rgba(parse_color(FRAME_STROKE_COLOR), theme);
+                let stroke = PaintOp::Stroke { width: FRAME_STROKE_WIDTH as f32, dash: None };
+                paint_path(content, doc, rgba, stroke, |c| push_path(c, &path));
+            }
+            let name = get_frame_name(element);
+            let (name_fonts, _) = resolve_family_fonts(fonts, FRAME_NAME_FONT_FAMILY, name);
+            draw_text(
+                content,
+                doc,
+                name,
+                x,
+                (get_frame_name_baseline(element) - offset.1 as f64) as f32,
+                FRAME_NAME_FONT_SIZE as f32,
+                get_themed_rgba(parse_color(FRAME_NAME_COLOR), theme),
+                &name_fonts,
+                None,
+                width,
+            );
+        }
+        // Reported as warnings before rendering
+        ElementKind::Embeddable | ElementKind::Iframe | ElementKind::Unknown => {}
+    }
+}
+
+/// Link annotation area of an element, in PDF points on a page of `page_height`
+fn get_link_rect(element: &Element, offset: (f32, f32), page_height: f32) -> PdfRect {
+    let (x1, y1, x2, y2) = get_rotated_element_bounds(element);
+    let to_x = |x: f64| (x - offset.0 as f64) as f32 * PT_PER_PX;
+    let to_y = |y: f64| page_height - (y - offset.1 as f64) as f32 * PT_PER_PX;
+    PdfRect::new(to_x(x1), to_y(y2), to_x(x2), to_y(y1))
+}
+
+/// Render the elements inside `viewbox` as one page
+/// Each element is clipped to its frame, masked behind an arrow label, rotated around its
+/// center and composited at its opacity, like the raster backend's layers.
+#[allow(clippy::too_many_arguments)]
+fn write_page(
+    doc: &mut PdfDocument,
+    page_tree_id: Ref,
+    data: &ExcalidrawData,
+    viewbox: &ViewBox,
+    elements: &[&Element],
+    background: (u8, u8, u8, u8),
+    canvas_rgba: (u8, u8, u8, u8),
+    theme: Theme,
+    fonts: &dyn FontProvider,
+) -> Ref {
+    let width = viewbox.width as f32;
+    let height = viewbox.height as f32;
+    let page_size = (width * PT_PER_PX, height * PT_PER_PX);
+    let offset = (viewbox.min_x as f32, viewbox.min_y as f32);
+
+    let mut content = Content::new();
+    // Scene pixels, y-down, from the top-left corner of the page
+    content.transform([PT_PER_PX, 0.0, 0.0, -PT_PER_PX, 0.0, page_size.1]);
+    paint_path(&mut content, doc, get_themed_rgba(background, theme), PaintOp::Fill { even_odd: false }, |c| {
+        c.rect(0.0, 0.0, width, height);
+    });
+
+    let frames = collect_frames(&data.elements);
+    let elements_by_id = index_elements(&data.elements);
+    let mut links = Vec::new();
+
+    for element in elements {
+        if element.is_deleted || element.opacity <= 0.0 {
+            continue;
+        }
+        let element = layout_text_element(element, &elements_by_id, fonts);
+        let element = &*element;
+
+        content.save_state();
+        if let Some(frame) = get_containing_frame(element, &frames) {
+            let x = (frame.x - offset.0 as f64) as f32;
+            let y = (frame.y - offset.1 as f64) as f32;
+            if let Some(path) = build_rounded_rect_path(x, y, frame.width as f32, frame.height as f32, FRAME_RADIUS as f32) {
+                push_path(&mut content, &path);
+                content.clip_nonzero().end_path();
+            }
+        }
+        if let Some((box_x, box_y, box_w, box_h)) = get_arrow_label_mask_box(element, &elements_by_id, fonts) {
+            // Even-odd clip of the whole page plus the label box leaves a hole at the label
+            content.rect(0.0, 0.0, width, height);
+            content.rect((box_x - offset.0 as f64) as f32, (box_y - offset.1 as f64) as f32, box_w as f32, box_h as f32);
+            content.clip_even_odd().end_path();
+        }
+        if let Some(transform) = get_element_rotation(element, offset, 1.0) {
+            content.transform(to_pdf_matrix(transform));
+        }
+
+        match get_layer_opacity(element) {
+            Some(opacity) => {
+                let mut group = Content::new();
+                render_element(&mut group, doc, element, offset, &data.files, theme, canvas_rgba, fonts);
+                let name = doc.add_group(group, PdfRect::new(0.0, 0.0, width, height));
+                let state = doc.alpha_state((opacity * 255.0).round() as u8);
+                content.set_parameters(Name(state.as_bytes()));
+                content.x_object(Name(name.as_bytes()));
+            }
+            None => render_element(&mut content, doc, element, offset, &data.files, theme, canvas_rgba, fonts),
+        }
+        content.restore_state();
+
+        if let Some(link) = element.link.as_deref().filter(|link| !link.is_empty()) {
+            links.push((get_link_rect(element, offset, page_size.1), link.to_string()));
+        }
+    }
+
+    doc.add_page(page_tree_id, page_size, content, &links)
+}
+
+/// Render a scene to PDF bytes
+/// Pages are sized at 96 DPI, so one scene pixel is 0.75pt. With `options.frame_pages`,
+/// every frame becomes a page sized to the frame; otherwise the scene (or `options.frame`,
+/// or `options.region`) is a single page. Fails when `options.frame` does not name a frame
+/// of the scene, or `options.region` is empty.
+pub fn render_to_pdf_bytes(data: &ExcalidrawData, options: &RenderOptions) -> Result<Vec<u8>> {
+    let padding = options.resolve_padding(data);
+    let theme = options.resolve_theme(data);
+    let background = options.resolve_background(data).unwrap_or((255, 255, 255, 255));
+    // Outline arrowheads are filled with the canvas color
+    let (r, g, b) = options.resolve_matte(data);
+    let canvas_rgba = (r, g, b, 255);
+
+    let frames = options
+        .frame_pages
+        .map(|order| get_ordered_frames(&data.elements, order))
+        .unwrap_or_default();
+    let scopes: Vec<Option<&str>> = if frames.is_empty() {
+        vec![options.frame.as_deref()]
+    } else {
+        frames.iter().map(|frame| Some(frame.id.as_str())).collect()
+    };
+    // Frame pages are each sized to their frame
+    let region = if frames.is_empty() { options.region } else { None };
+
+    let mut doc = PdfDocument::new();
+    let page_tree_id = doc.alloc();
+    let mut page_ids = Vec::with_capacity(scopes.len());
+    for scope in scopes {
+        let (viewbox, elements) = resolve_export_scope(data, scope, region, padding)?;
+        options.report_element_warnings(&elements, &data.files);
+        options.report_text_warnings(&elements);
+        page_ids.push(write_page(
+            &mut doc,
+            page_tree_id,
+            data,
+            &viewbox,
+            &elements,
+            background,
+            canvas_rgba,
+            theme,
+            options.fonts.as_ref(),
+        ));
+    }
+    Ok(doc.finish(page_tree_id, &page_ids))
+}
+
+/// Render a scene as PDF into any writer
+pub fn render_to_pdf_writer<W: std::io::Write>(data: &ExcalidrawData, writer: &mut W, options: &RenderOptions) -> Result<()> {
+    let bytes = render_to_pdf_bytes(data, options)?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Render a scene to a PDF file
+pub fn render_to_pdf(data: &ExcalidrawData, output_path: &std::path::Path, options: &RenderOptions) -> Result<()> {
+    let bytes = render_to_pdf_bytes(data, options)?;
+    std::fs::write(output_path, bytes).map_err(|source| ExcaliosaError::Write { path: output_path.to_path_buf(), source })?;
+    Ok(())
+}
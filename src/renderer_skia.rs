@@ -1,18 +1,33 @@
 use crate::arrow_utils::{calc_arrowhead_points, build_elbow_arrow_path, calculate_arrowhead_direction};
-use crate::color_utils::{has_fill, has_stroke, parse_color};
+use crate::bound_text_utils::index_elements;
+use crate::color_utils::{get_themed_rgba, has_fill, has_stroke, parse_color};
+use crate::frame_utils::{
+    collect_frames, get_containing_frame, get_frame_name, get_frame_name_baseline, FRAME_NAME_COLOR,
+    FRAME_NAME_FONT_FAMILY, FRAME_NAME_FONT_SIZE, FRAME_RADIUS, FRAME_STROKE_COLOR, FRAME_STROKE_WIDTH,
+};
+use crate::freedraw_utils::{get_freedraw_outline, get_stroke_midpoints, is_path_a_loop};
+use crate::image_utils::{get_image_crop_rect, get_image_flip, get_uncropped_size, load_file_image};
 use crate::math_utils::{calculate_center, catmull_rom_cubics};
-use crate::models::{ExcalidrawData, ExcalidrawElement as Element};
-use crate::converter::{EXCALIFONT_REGULAR, LIBERATION_SANS_REGULAR, CASCADIA_CODE};
+use crate::models::{
+    Arrowhead, ElementKind, ElementType, ExcalidrawData, ExcalidrawElement as Element, FileData, FillStyle as ExcalidrawFillStyle,
+    StrokeStyle, TextAlign,
+};
 use crate::rect_utils::{get_corner_radius, generate_rounded_rect_path};
-use crate::font_utils::{calculate_text_x_position_for_line, get_font_family};
+use crate::font_utils::calculate_text_x_position_for_line;
+use crate::fonts::{resolve_family_fonts, FontFace, FontProvider};
+use crate::text_layout::{get_arrow_label_mask_box, layout_text_element};
 use crate::stroke_utils::{get_stroke_dash_array, get_dotted_cap_dash_array};
-use crate::utils::{calculate_viewbox, save_png_with_quality};
-use anyhow::Result;
+use crate::options::{RenderOptions, Theme};
+use crate::error::{ExcaliosaError, Result};
+use crate::encoder::{encode_pixmap, pixmap_to_rgba, save_pixmap, write_pixmap, RasterFormat};
+use crate::utils::{get_element_center, resolve_export_scope};
 use euclid::default::Point2D;
 use palette::Srgba;
+use parley::fontique::Blob;
 use parley::{FontContext, LayoutContext, StyleProperty};
-use rough_tiny_skia::SkiaGenerator;
-use roughr::core::{FillStyle, OptionsBuilder};
+use rough_tiny_skia::{SkiaDrawable, SkiaGenerator};
+use roughr::core::{FillStyle, Options, OptionsBuilder};
+use std::collections::HashMap;
 use skrifa::{GlyphId, MetadataProvider, OutlineGlyph, instance::{LocationRef, NormalizedCoord, Size}, outline::{DrawSettings, OutlinePen}, raw::FontRef as ReadFontsRef};
 use tiny_skia::*;
 
@@ -30,12 +45,12 @@ fn catmull_rom_cubics_abs(points: &[(f64, f64)], x: f32, y: f32) -> Vec<CubicBez
 }
 
 // Compute arrowhead points as per Excalidraw using Catmull-Rom cubics for accurate direction
-fn exca_arrowhead_points(
+pub(crate) fn exca_arrowhead_points(
     points: &[(f64,f64)],
     x: f32,
     y: f32,
     stroke_width: f32,
-    arrowhead: &str,
+    arrowhead: Arrowhead,
     position: &str, // "start" or "end"
 ) -> Option<Vec<f32>> {
     if points.is_empty() { return None; }
@@ -73,79 +88,79 @@ fn draw_arrowhead_ex(
     x: f32,
     y: f32,
     stroke_rgba: (u8,u8,u8,u8),
+    canvas_rgba: (u8,u8,u8,u8),
     stroke_width: f32,
-    arrowhead: &str,
+    arrowhead: Arrowhead,
     position: &str,
     cap_gen: &SkiaGenerator,
+    transform: Transform,
 ) {
     if let Some(vals) = exca_arrowhead_points(points, x, y, stroke_width, arrowhead, position) {
         match arrowhead {
-            "dot" | "circle" | "circle_outline" => {
+            Arrowhead::Dot | Arrowhead::Circle | Arrowhead::CircleOutline => {
                 let cx = vals[0]; let cy = vals[1]; let diameter = vals[2];
                 // Render solid/outline circle with tiny-skia
                 let mut path_builder = PathBuilder::new();
                 path_builder.push_circle(cx, cy, diameter / 2.0);
                 if let Some(path) = path_builder.finish() {
                     let mut paint = Paint::default();
-                    // Fill color: outline => white background, else stroke color
-                    let (fill_r,fill_g,fill_b,fill_a) = if arrowhead == "circle_outline" { (255,255,255,255) } else { stroke_rgba };
+                    // Fill color: outline => canvas background, else stroke color
+                    let (fill_r,fill_g,fill_b,fill_a) = if arrowhead == Arrowhead::CircleOutline { canvas_rgba } else { stroke_rgba };
                     paint.set_color_rgba8(fill_r,fill_g,fill_b,fill_a);
-                    pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+                    pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
                     // Stroke outline
                     let mut spaint = Paint::default(); spaint.set_color_rgba8(stroke_rgba.0, stroke_rgba.1, stroke_rgba.2, stroke_rgba.3);
                     let stroke = Stroke { width: stroke_width, line_cap: LineCap::Round, line_join: LineJoin::Round, ..Default::default() };
-                    pixmap.stroke_path(&path, &spaint, &stroke, Transform::identity(), None);
+                    pixmap.stroke_path(&path, &spaint, &stroke, transform, None);
                 }
             }
-            "triangle" | "triangle_outline" => {
+            Arrowhead::Triangle | Arrowhead::TriangleOutline => {
                 let x0=vals[0]; let y0=vals[1]; let x1=vals[2]; let y1=vals[3]; let x2p=vals[4]; let y2p=vals[5];
                 let mut pb = PathBuilder::new(); pb.move_to(x0,y0); pb.line_to(x1,y1); pb.line_to(x2p,y2p); pb.close();
                 if let Some(path) = pb.finish() {
                     // Fill
-                    let (fr,fg,fb,fa) = if arrowhead.ends_with("_outline") { (255,255,255,255) } else { stroke_rgba };
+                    let (fr,fg,fb,fa) = if matches!(arrowhead, Arrowhead::TriangleOutline | Arrowhead::DiamondOutline) { canvas_rgba } else { stroke_rgba };
                     let mut fp = Paint::default(); fp.set_color_rgba8(fr,fg,fb,fa);
-                    pixmap.fill_path(&path, &fp, FillRule::Winding, Transform::identity(), None);
+                    pixmap.fill_path(&path, &fp, FillRule::Winding, transform, None);
                     // Stroke
                     let mut sp = Paint::default(); sp.set_color_rgba8(stroke_rgba.0, stroke_rgba.1, stroke_rgba.2, stroke_rgba.3);
                     let st = Stroke { width: stroke_width, line_cap: LineCap::Round, line_join: LineJoin::Round, ..Default::default() };
-                    pixmap.stroke_path(&path, &sp, &st, Transform::identity(), None);
+                    pixmap.stroke_path(&path, &sp, &st, transform, None);
                 }
             }
-            "diamond" | "diamond_outline" => {
+            Arrowhead::Diamond | Arrowhead::DiamondOutline => {
                 let x0=vals[0]; let y0=vals[1]; let x1=vals[2]; let y1=vals[3]; let ox=vals[4]; let oy=vals[5]; let x2p=vals[6]; let y2p=vals[7];
                 let mut pb = PathBuilder::new(); pb.move_to(x0,y0); pb.line_to(x1,y1); pb.line_to(ox,oy); pb.line_to(x2p,y2p); pb.close();
                 if let Some(path) = pb.finish() {
-                    let (fr,fg,fb,fa) = if arrowhead.ends_with("_outline") { (255,255,255,255) } else { stroke_rgba };
+                    let (fr,fg,fb,fa) = if matches!(arrowhead, Arrowhead::TriangleOutline | Arrowhead::DiamondOutline) { canvas_rgba } else { stroke_rgba };
                     let mut fp = Paint::default(); fp.set_color_rgba8(fr,fg,fb,fa);
-                    pixmap.fill_path(&path, &fp, FillRule::Winding, Transform::identity(), None);
+                    pixmap.fill_path(&path, &fp, FillRule::Winding, transform, None);
                     let mut sp = Paint::default(); sp.set_color_rgba8(stroke_rgba.0, stroke_rgba.1, stroke_rgba.2, stroke_rgba.3);
                     let st = Stroke { width: stroke_width, line_cap: LineCap::Round, line_join: LineJoin::Round, ..Default::default() };
-                    pixmap.stroke_path(&path, &sp, &st, Transform::identity(), None);
+                    pixmap.stroke_path(&path, &sp, &st, transform, None);
                 }
             }
-            "crowfoot_one" => {
+            Arrowhead::CrowfootOne => {
                 // vals: [x2,y2,x3,y3,x4,y4] per getArrowheadPoints, but here we use x3,y3,x4,y4 line
                 let x3=vals[2]; let y3=vals[3]; let x4=vals[4]; let y4=vals[5];
                 let line = cap_gen.line::<f32>(x3,y3,x4,y4);
-                line.draw(pixmap);
+                draw_rough(pixmap, line, transform);
             }
-            "bar" => {
+            Arrowhead::Bar => {
                 // Draw only the perpendicular bar line x3,y3 -> x4,y4
                 let x3=vals[2]; let y3=vals[3]; let x4=vals[4]; let y4=vals[5];
                 let line = cap_gen.line::<f32>(x3,y3,x4,y4);
-                line.draw(pixmap);
+                draw_rough(pixmap, line, transform);
             }
             _ => {
                 // default/arrow/bar/crowfoot_many/one_or_many -> two lines to tip
                 let x2=vals[0]; let y2=vals[1]; let x3=vals[2]; let y3=vals[3]; let x4=vals[4]; let y4=vals[5];
-                let l1 = cap_gen.line::<f32>(x3,y3,x2,y2); l1.draw(pixmap);
-                let l2 = cap_gen.line::<f32>(x4,y4,x2,y2); l2.draw(pixmap);
+                let l1 = cap_gen.line::<f32>(x3,y3,x2,y2); draw_rough(pixmap, l1, transform);
+                let l2 = cap_gen.line::<f32>(x4,y4,x2,y2); draw_rough(pixmap, l2, transform);
                 // extra for crowfoot_one_or_many: add crowfoot_one bar
-                if arrowhead == "crowfoot_one_or_many" {
-                    if let Some(bar) = exca_arrowhead_points(points,x,y,stroke_width,"crowfoot_one",position) {
-                        let bx3=bar[2]; let by3=bar[3]; let bx4=bar[4]; let by4=bar[5];
-                        let bl = cap_gen.line::<f32>(bx3,by3,bx4,by4); bl.draw(pixmap);
-                    }
+                if arrowhead == Arrowhead::CrowfootOneOrMany && let Some(bar) = exca_arrowhead_points(points,x,y,stroke_width,Arrowhead::CrowfootOne,position) {
+                    let bx3=bar[2]; let by3=bar[3]; let bx4=bar[4]; let by4=bar[5];
+                    let bl = cap_gen.line::<f32>(bx3,by3,bx4,by4); draw_rough(pixmap, bl, transform);
                 }
             }
         }
@@ -153,6 +168,18 @@ fn draw_arrowhead_ex(
 }
 
 
+/// Draw a rough.js drawable with its paths mapped through `transform`
+fn draw_rough(pixmap: &mut PixmapMut, mut drawable: SkiaDrawable<f32>, transform: Transform) {
+    if !transform.is_identity() {
+        for set in &mut drawable.sets {
+            if let Some(path) = set.ops.clone().transform(transform) {
+                set.ops = path;
+            }
+        }
+    }
+    drawable.draw(pixmap);
+}
+
 /// Build Catmull–Rom cubic path d-string (M + C segments)
 fn build_catmull_rom_cubic_path(points: &[(f64,f64)], x:f32, y:f32) -> Option<String> {
     let segs = catmull_rom_cubics_abs(points, x, y);
@@ -165,23 +192,189 @@ fn build_catmull_rom_cubic_path(points: &[(f64,f64)], x:f32, y:f32) -> Option<St
     Some(d)
 }
 
+/// Build a fillable freedraw path: quadratic curves through the outline midpoints,
+/// mapped from element-local coordinates to pixmap coordinates
+pub(crate) fn build_freedraw_path(outline: &[(f64, f64)], x: f32, y: f32, scale: f32) -> Option<Path> {
+    if outline.is_empty() {
+        return None;
+    }
+    let to_px = |p: (f64, f64)| (x + p.0 as f32 * scale, y + p.1 as f32 * scale);
+    let mids = get_stroke_midpoints(outline);
+
+    let mut pb = PathBuilder::new();
+    let (sx, sy) = to_px(outline[0]);
+    pb.move_to(sx, sy);
+    for (p, m) in outline.iter().zip(mids.iter()) {
+        let (cx, cy) = to_px(*p);
+        let (ex, ey) = to_px(*m);
+        pb.quad_to(cx, cy, ex, ey);
+    }
+    pb.close();
+    pb.finish()
+}
+
+/// Build a rounded rectangle path in pixel space
+pub(crate) fn build_rounded_rect_path(x: f32, y: f32, width: f32, height: f32, radius: f32) -> Option<Path> {
+    let r = radius.min(width / 2.0).min(height / 2.0).max(0.0);
+    let mut pb = PathBuilder::new();
+    pb.move_to(x + r, y);
+    pb.line_to(x + width - r, y);
+    pb.quad_to(x + width, y, x + width, y + r);
+    pb.line_to(x + width, y + height - r);
+    pb.quad_to(x + width, y + height, x + width - r, y + height);
+    pb.line_to(x + r, y + height);
+    pb.quad_to(x, y + height, x, y + height - r);
+    pb.line_to(x, y + r);
+    pb.quad_to(x, y, x + r, y);
+    pb.close();
+    pb.finish()
+}
+
+/// Mask covering a frame's bounds, used to clip the frame's children
+fn build_frame_mask(frame: &Element, offset: (f32, f32), scale: f32, width: u32, height: u32) -> Option<Mask> {
+    let x = ((frame.x - offset.0 as f64) * scale as f64) as f32;
+    let y = ((frame.y - offset.1 as f64) * scale as f64) as f32;
+    let path = build_rounded_rect_path(
+        x,
+        y,
+        (frame.width * scale as f64) as f32,
+        (frame.height * scale as f64) as f32,
+        FRAME_RADIUS as f32 * scale,
+    )?;
+    let mut mask = Mask::new(width, height)?;
+    mask.fill_path(&path, FillRule::Winding, true, Transform::identity());
+    Some(mask)
+}
+
+/// Mask hiding an arrow's stroke behind its label, combined with the arrow's frame clip
+fn build_arrow_label_mask(
+    clip: Option<&Mask>,
+    label_box: (f64, f64, f64, f64),
+    offset: (f32, f32),
+    scale: f32,
+    width: u32,
+    height: u32,
+) -> Option<Mask> {
+    let (box_x, box_y, box_w, box_h) = label_box;
+    let label_rect = Rect::from_xywh(
+        ((box_x - offset.0 as f64) * scale as f64) as f32,
+        ((box_y - offset.1 as f64) * scale as f64) as f32,
+        (box_w * scale as f64) as f32,
+        (box_h * scale as f64) as f32,
+    )?;
+
+    // Even-odd fill of the whole canvas plus the label box leaves a hole at the label
+    let mut pb = PathBuilder::new();
+    pb.push_rect(Rect::from_xywh(0.0, 0.0, width as f32, height as f32)?);
+    pb.push_rect(label_rect);
+    let path = pb.finish()?;
+
+    let mut mask = match clip {
+        Some(clip) => clip.clone(),
+        None => {
+            let mut mask = Mask::new(width, height)?;
+            mask.invert();
+            mask
+        }
+    };
+    mask.intersect_path(&path, FillRule::EvenOdd, true, Transform::identity());
+    Some(mask)
+}
+
+/// Draw an image element from the scene's `files` map
+/// Applies crop, flip, rotation around the element center and opacity.
+/// Missing or undecodable files render as Excalidraw's gray placeholder.
+#[allow(clippy::too_many_arguments)]
+fn draw_image(
+    pixmap: &mut PixmapMut,
+    element: &Element,
+    files: &HashMap<String, FileData>,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    scale: f32,
+) {
+    if width <= 0.0 || height <= 0.0 {
+        return;
+    }
+
+    let (cx, cy) = calculate_center(x, y, width, height);
+    let (flip_x, flip_y) = get_image_flip(element);
+    let base_transform = Transform::from_rotate_at(element.angle.to_degrees() as f32, cx, cy)
+        .pre_translate(cx, cy)
+        .pre_scale(flip_x as f32, flip_y as f32)
+        .pre_translate(-cx, -cy);
+    let opacity = (element.opacity / 100.0).clamp(0.0, 1.0) as f32;
+
+    // SVG files are rasterized at the size the whole image is displayed at
+    let (uncropped_w, uncropped_h) = get_uncropped_size(element);
+    let svg_size = (
+        (uncropped_w * scale as f64).ceil().max(1.0) as u32,
+        (uncropped_h * scale as f64).ceil().max(1.0) as u32,
+    );
+
+    let image = element
+        .file_id
+        .as_deref()
+        .filter(|_| element.status.as_deref() != Some("error"))
+        .and_then(|file_id| files.get(file_id))
+        .and_then(|file| load_file_image(file, svg_size));
+
+    let Some(image) = image else {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(0xE7, 0xE7, 0xE7, (opacity * 255.0) as u8);
+        if let Some(rect) = Rect::from_xywh(x, y, width, height) {
+            pixmap.fill_rect(rect, &paint, base_transform, None);
+        }
+        return;
+    };
+
+    // Crop in the decoded image's pixel space, clamped to the image bounds
+    let cropped = if element.crop.is_some() {
+        let (image_w, image_h) = (image.width() as f64, image.height() as f64);
+        let (crop_x, crop_y, crop_w, crop_h) = get_image_crop_rect(element, image_w, image_h);
+        let left = crop_x.round().clamp(0.0, image_w - 1.0);
+        let top = crop_y.round().clamp(0.0, image_h - 1.0);
+        let crop_w = crop_w.round().clamp(1.0, image_w - left);
+        let crop_h = crop_h.round().clamp(1.0, image_h - top);
+        IntRect::from_xywh(left as i32, top as i32, crop_w as u32, crop_h as u32)
+            .and_then(|rect| image.clone_rect(rect))
+            .unwrap_or(image)
+    } else {
+        image
+    };
+
+    let transform = base_transform
+        .pre_translate(x, y)
+        .pre_scale(width / cropped.width() as f32, height / cropped.height() as f32);
+    let paint = PixmapPaint {
+        opacity,
+        blend_mode: BlendMode::SourceOver,
+        quality: FilterQuality::Bicubic,
+    };
+    pixmap.draw_pixmap(0, 0, cropped.as_ref(), &paint, transform, None);
+}
+
 /// Helper struct for rendering glyphs with tiny-skia (implements OutlinePen)
-struct TinySkiaPen<'a> {
-    pixmap: &'a mut PixmapMut<'a>,
+struct TinySkiaPen<'a, 'p> {
+    pixmap: &'a mut PixmapMut<'p>,
     x: f32,
     y: f32,
     paint: Paint<'static>,
     open_path: PathBuilder,
+    transform: Transform,
 }
 
-impl<'a> TinySkiaPen<'a> {
-    fn new(pixmap: &'a mut PixmapMut<'a>) -> TinySkiaPen<'a> {
+impl<'a, 'p> TinySkiaPen<'a, 'p> {
+    fn new(pixmap: &'a mut PixmapMut<'p>, transform: Transform) -> TinySkiaPen<'a, 'p> {
         TinySkiaPen {
             pixmap,
             x: 0.0,
             y: 0.0,
             paint: Paint::default(),
             open_path: PathBuilder::new(),
+            transform,
         }
     }
 
@@ -211,14 +404,14 @@ impl<'a> TinySkiaPen<'a> {
                 &path,
                 &self.paint,
                 FillRule::Winding,
-                Transform::identity(),
+                self.transform,
                 None,
             );
         }
     }
 }
 
-impl OutlinePen for TinySkiaPen<'_> {
+impl OutlinePen for TinySkiaPen<'_, '_> {
     fn move_to(&mut self, x: f32, y: f32) {
         self.open_path.move_to(self.x + x, self.y - y);
     }
@@ -252,7 +445,7 @@ impl OutlinePen for TinySkiaPen<'_> {
 struct TextRenderContext<'a> {
     font_cx: &'a mut FontContext,
     layout_cx: &'a mut LayoutContext,
-    custom_fonts: &'a std::collections::HashMap<String, Vec<u8>>,
+    fonts: &'a dyn FontProvider,
 }
 
 /// Properties for rendering text
@@ -262,41 +455,44 @@ struct TextProperties<'a> {
     y: f32,
     font_size: f32,
     color: (u8, u8, u8, u8),
-    font_family: &'static str,
-    text_align: Option<&'a str>,
+    font_family: &'a str,
+    text_align: Option<TextAlign>,
     container_width: f32,
 }
 
 /// Render text using Parley and tiny-skia
-fn render_text<'a>(
-    pixmap: &'a mut PixmapMut<'a>,
-    props: &TextProperties<'a>,
-    text_ctx: &mut TextRenderContext<'a>,
+fn render_text(
+    pixmap: &mut PixmapMut,
+    props: &TextProperties,
+    text_ctx: &mut TextRenderContext,
+    transform: Transform,
 ) {
     // Skip empty text
     if props.text.is_empty() {
         return;
     }
     
-    // Check if we have a custom font for this family
-    if let Some(font_data) = text_ctx.custom_fonts.get(props.font_family) {
-        // Use skrifa to render directly with our custom font
-        if let Ok(font_ref) = ReadFontsRef::new(font_data.as_slice()) {
-            render_text_with_skrifa(
-                pixmap, 
-                props.text, 
-                props.x, 
-                props.y, 
-                props.font_size, 
-                props.color, 
-                &font_ref, 
-                props.text_align, 
-                props.container_width
-            );
-            return;
-        }
+    // Use skrifa to render directly with the provider's font, and fallback fonts for the
+    // characters it lacks
+    let (faces, _) = resolve_family_fonts(text_ctx.fonts, props.font_family, props.text);
+    let font_refs: Vec<ReadFontsRef> = faces.iter().filter_map(FontFace::font_ref).collect();
+    if !font_refs.is_empty() {
+        render_text_with_skrifa(
+            pixmap, 
+            props.text, 
+            props.x, 
+            props.y, 
+            props.font_size, 
+            props.color, 
+            &font_refs, 
+            props.text_align, 
+            props.container_width,
+            transform,
+        );
+        return;
     }
     
+    // The font data could not be parsed: let parley pick a font
     let display_scale = 1.0;
     
     // Create a layout builder with parley (fallback to system fonts)
@@ -311,7 +507,7 @@ fn render_text<'a>(
     layout.break_all_lines(None);
     
     // Create pen for rendering
-    let mut pen = TinySkiaPen::new(pixmap);
+    let mut pen = TinySkiaPen::new(pixmap, transform);
     let text_color = Color::from_rgba8(props.color.0, props.color.1, props.color.2, props.color.3);
     
     // Render each glyph run
@@ -355,63 +551,68 @@ fn render_text<'a>(
     }
 }
 
-/// Render text directly using skrifa without parley
+/// A glyph placed by [`position_glyphs`]
+pub(crate) struct PositionedGlyph {
+    /// Index of the font the glyph belongs to
+    pub font: usize,
+    pub id: GlyphId,
+    pub x: f32,
+    /// Baseline
+    pub y: f32,
+}
+
+/// Glyphs of multi-line text, each from the first of `fonts` that has it
+/// `fonts` starts with the text's own font, which also sets the line height; characters no
+/// font has are left out. `y` is the first line's baseline; lines are aligned within
+/// `container_width`. Shared with the PDF backend so both place the same glyph outlines.
 #[allow(clippy::too_many_arguments)]
-fn render_text_with_skrifa<'a>(
-    pixmap: &'a mut PixmapMut<'a>,
+pub(crate) fn position_glyphs(
     text: &str,
     x: f32,
     y: f32,
     font_size: f32,
-    color: (u8, u8, u8, u8),
-    font_ref: &ReadFontsRef,
-    text_align: Option<&str>,
+    fonts: &[ReadFontsRef],
+    text_align: Option<TextAlign>,
     container_width: f32,
-) {
-    let mut pen = TinySkiaPen::new(pixmap);
-    let text_color = Color::from_rgba8(color.0, color.1, color.2, color.3);
-    
-    let outlines = font_ref.outline_glyphs();
-    let charmap = font_ref.charmap();
-    let glyph_metrics = font_ref.glyph_metrics(Size::new(font_size), LocationRef::default());
+) -> Vec<PositionedGlyph> {
+    let Some(primary) = fonts.first() else {
+        return Vec::new();
+    };
+    let charmaps: Vec<_> = fonts.iter().map(|font| font.charmap()).collect();
+    let glyph_metrics: Vec<_> = fonts
+        .iter()
+        .map(|font| font.glyph_metrics(Size::new(font_size), LocationRef::default()))
+        .collect();
+    // First font with a glyph for a character, with the glyph and its advance
+    let map_char = |ch: char| {
+        charmaps.iter().enumerate().find_map(|(font, charmap)| {
+            let glyph_id = charmap.map(ch)?;
+            Some((font, glyph_id, glyph_metrics[font].advance_width(glyph_id).unwrap_or(0.0)))
+        })
+    };
     
     // Get font metrics for line height calculation
-    let metrics = font_ref.metrics(Size::new(font_size), LocationRef::default());
+    let metrics = primary.metrics(Size::new(font_size), LocationRef::default());
     let line_height = (metrics.ascent - metrics.descent + metrics.leading) * 1.25; // 1.25 is typical line height multiplier
     
+    let mut glyphs = Vec::new();
     let mut cursor_y = y;
     
-    // Split text by newlines and render each line
+    // Split text by newlines and place each line
     let lines: Vec<&str> = text.split('\n').collect();
     
     for (line_idx, line) in lines.iter().enumerate() {
         // Calculate line width for alignment
-        let mut line_width = 0.0f32;
-        for ch in line.chars() {
-            if let Some(glyph_id) = charmap.map(ch) {
-                if let Some(advance) = glyph_metrics.advance_width(glyph_id) {
-                    line_width += advance;
-                }
-            }
-        }
+        let line_width: f32 = line.chars().filter_map(map_char).map(|(_, _, advance)| advance).sum();
         
         let start_x = calculate_text_x_position_for_line(x, container_width, line_width, text_align);
         let mut cursor_x = start_x;
         
-        // Render each character in the line
-        for ch in line.chars() {
-            if let Some(glyph_id) = charmap.map(ch) {
-                if let Some(glyph_outline) = outlines.get(glyph_id) {
-                    pen.set_origin(cursor_x, cursor_y);
-                    pen.set_color(text_color);
-                    pen.draw_glyph(&glyph_outline, font_size, &[]);
-                    pen.finish_path();
-                }
-                // Advance cursor horizontally
-                if let Some(advance) = glyph_metrics.advance_width(glyph_id) {
-                    cursor_x += advance;
-                }
-            }
+        // Place each character in the line
+        for (font, id, advance) in line.chars().filter_map(map_char) {
+            glyphs.push(PositionedGlyph { font, id, x: cursor_x, y: cursor_y });
+            // Advance cursor horizontally
+            cursor_x += advance;
         }
         
         // Move to next line if not the last line
@@ -419,40 +620,67 @@ fn render_text_with_skrifa<'a>(
             cursor_y += line_height;
         }
     }
+    glyphs
 }
 
-fn render_element<'a, 'b: 'a>(
-    pixmap: &'a mut PixmapMut<'a>, 
-    element: &'b Element,
-    offset: (f32, f32),
-    text_ctx: &mut TextRenderContext<'a>,
+/// Render text directly using skrifa without parley
+/// `fonts` starts with the text's own font, followed by fallbacks for the characters it lacks.
+#[allow(clippy::too_many_arguments)]
+fn render_text_with_skrifa(
+    pixmap: &mut PixmapMut,
+    text: &str,
+    x: f32,
+    y: f32,
+    font_size: f32,
+    color: (u8, u8, u8, u8),
+    fonts: &[ReadFontsRef],
+    text_align: Option<TextAlign>,
+    container_width: f32,
     transform: Transform,
 ) {
-    if element.is_deleted {
-        return;
+    let mut pen = TinySkiaPen::new(pixmap, transform);
+    let text_color = Color::from_rgba8(color.0, color.1, color.2, color.3);
+    let outlines: Vec<_> = fonts.iter().map(|font| font.outline_glyphs()).collect();
+
+    for glyph in position_glyphs(text, x, y, font_size, fonts, text_align, container_width) {
+        if let Some(glyph_outline) = outlines[glyph.font].get(glyph.id) {
+            pen.set_origin(glyph.x, glyph.y);
+            pen.set_color(text_color);
+            pen.draw_glyph(&glyph_outline, font_size, &[]);
+            pen.finish_path();
+        }
     }
-    
-    // Extract scale factor from transform (sx for uniform scaling)
-    let scale = transform.sx;
-    
-    // Transform coordinates relative to viewbox and apply scale
-    let x = ((element.x - offset.0 as f64) * scale as f64) as f32;
-    let y = ((element.y - offset.1 as f64) * scale as f64) as f32;
-    let width = (element.width * scale as f64) as f32;
-    let height = (element.height * scale as f64) as f32;
+}
 
-    let stroke_rgba = parse_color(&element.stroke_color);
-    let fill_rgba = parse_color(&element.background_color);
+/// Rough.js options for one element, shared with the PDF backend so that both draw
+/// the same sketchy paths for the same seed
+pub(crate) struct RoughOptions {
+    /// Outline and fill of closed shapes
+    pub shape: Options,
+    /// Stroke only, for line and arrow bodies
+    pub stroke_only: Options,
+    /// Arrowhead caps: solid, or dotted with a shorter gap for dotted arrows
+    pub cap: Options,
+    /// Background fill of closed freedraw loops
+    pub loop_fill: Options,
+}
 
+/// Build the rough.js options for an element drawn at `scale`
+pub(crate) fn build_rough_options(
+    element: &Element,
+    stroke_rgba: (u8, u8, u8, u8),
+    fill_rgba: (u8, u8, u8, u8),
+    scale: f32,
+) -> RoughOptions {
     let should_stroke = has_stroke(element);
     let should_fill = has_fill(element);
 
     // Map Excalidraw fill styles to roughr FillStyle
-    let fill_style = match element.fill_style.as_str() {
-        "hachure" => FillStyle::Hachure,
-        "cross-hatch" => FillStyle::CrossHatch,
-        "solid" => FillStyle::Solid,
-        _ => FillStyle::Hachure,
+    let fill_style = match element.fill_style {
+        ExcalidrawFillStyle::Hachure => FillStyle::Hachure,
+        ExcalidrawFillStyle::CrossHatch => FillStyle::CrossHatch,
+        ExcalidrawFillStyle::Solid => FillStyle::Solid,
+        ExcalidrawFillStyle::Zigzag | ExcalidrawFillStyle::Unknown => FillStyle::Hachure,
     };
 
     // Create rough options
@@ -479,7 +707,7 @@ fn render_element<'a, 'b: 'a>(
     options_builder.seed(element.seed as u64);
     // Stroke dash pattern per strokeStyle (use scaled stroke width)
     let scaled_stroke_width = (element.stroke_width * scale as f64) as f32;
-    if let Some(dash) = get_stroke_dash_array(&element.stroke_style, scaled_stroke_width as f64) {
+    if let Some(dash) = get_stroke_dash_array(element.stroke_style, scaled_stroke_width as f64) {
         // Prefer backend dash support if exposed by roughr
         #[allow(unused_must_use)]
         {
@@ -492,10 +720,9 @@ fn render_element<'a, 'b: 'a>(
     let dpi = BASE_DPI * scale;
     options_builder.fill_weight(dpi * 0.01);
 
-    let options = options_builder.build().unwrap();
-    let generator = SkiaGenerator::new(options);
+    let shape = options_builder.build().unwrap();
 
-    // Helper: stroke-only generator for linear/arrow paths to avoid fill paths
+    // Stroke-only options for linear/arrow paths to avoid fill paths
     let mut stroke_only_builder = OptionsBuilder::default();
     if should_stroke {
         stroke_only_builder.stroke(
@@ -507,16 +734,15 @@ fn render_element<'a, 'b: 'a>(
     stroke_only_builder.roughness(element.roughness as f32);
     stroke_only_builder.seed(element.seed as u64);
     stroke_only_builder.fill_weight(dpi * 0.01);
-    if let Some(dash) = get_stroke_dash_array(&element.stroke_style, scaled_stroke_width as f64) {
+    if let Some(dash) = get_stroke_dash_array(element.stroke_style, scaled_stroke_width as f64) {
         #[allow(unused_must_use)]
         {
             stroke_only_builder.stroke_line_dash(dash);
         }
     }
-    let stroke_only_options = stroke_only_builder.build().unwrap();
-    let stroke_gen = SkiaGenerator::new(stroke_only_options);
+    let stroke_only = stroke_only_builder.build().unwrap();
 
-    // Build a separate generator for arrowhead caps:
+    // Separate options for arrowhead caps:
     // - solid for solid/dashed
     // - dotted with reduced gap for dotted
     let mut cap_builder = OptionsBuilder::default();
@@ -530,18 +756,103 @@ fn render_element<'a, 'b: 'a>(
     cap_builder.roughness(element.roughness as f32);
     cap_builder.seed(element.seed as u64);
     cap_builder.fill_weight(dpi * 0.01);
-    if element.stroke_style == "dotted" {
+    if element.stroke_style == StrokeStyle::Dotted {
         #[allow(unused_must_use)]
         {
             let dash = get_dotted_cap_dash_array(scaled_stroke_width as f64);
             cap_builder.stroke_line_dash(dash);
         }
     }
-    let cap_gen = SkiaGenerator::new(cap_builder.build().unwrap());
+    let cap = cap_builder.build().unwrap();
+
+    // Closed pen loops get a rough background fill without an outline
+    let mut fill_builder = OptionsBuilder::default();
+    fill_builder.stroke(Srgba::from_components((0u8, 0u8, 0u8, 0u8)).into_format());
+    fill_builder.fill(
+        Srgba::from_components((fill_rgba.0, fill_rgba.1, fill_rgba.2, fill_rgba.3))
+            .into_format(),
+    );
+    fill_builder.fill_style(fill_style);
+    fill_builder.roughness(element.roughness as f32);
+    fill_builder.seed(element.seed as u64);
+    fill_builder.fill_weight(dpi * 0.01);
+    let loop_fill = fill_builder.build().unwrap();
+
+    RoughOptions {
+        shape,
+        stroke_only,
+        cap,
+        loop_fill,
+    }
+}
+
+/// Rough path data for a line or arrow body in pixel space: an elbow path, a Catmull-Rom
+/// curve when rounded, or a straight polyline. `points` are already scaled.
+pub(crate) fn build_linear_path_d(element: &Element, points: &[(f64, f64)], x: f32, y: f32, scale: f32) -> Option<String> {
+    if points.len() < 2 {
+        return None;
+    }
+    if element.elbowed.unwrap_or(false) && points.len() >= 3 {
+        // Convert relative points to absolute
+        let abs_points: Vec<(f64, f64)> = points.iter()
+            .map(|p| (x as f64 + p.0, y as f64 + p.1))
+            .collect();
+        build_elbow_arrow_path(&abs_points, 16.0 * scale as f64)
+    } else if element.roundness.is_some() {
+        build_catmull_rom_cubic_path(points, x, y)
+    } else {
+        // straight polyline via rough path M/L
+        let mut d = String::new();
+        d.push_str(&format!("M {} {}", x + points[0].0 as f32, y + points[0].1 as f32));
+        for p in points.iter().skip(1) {
+            d.push_str(&format!(" L {} {}", x + p.0 as f32, y + p.1 as f32));
+        }
+        Some(d)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_element(
+    pixmap: &mut PixmapMut,
+    element: &Element,
+    offset: (f32, f32),
+    text_ctx: &mut TextRenderContext,
+    files: &HashMap<String, FileData>,
+    transform: Transform,
+    theme: Theme,
+    canvas_rgba: (u8, u8, u8, u8),
+) {
+    if element.is_deleted {
+        return;
+    }
+    
+    // Extract scale factor from transform (sx for uniform scaling)
+    let scale = transform.sx;
+    
+    // Transform coordinates relative to viewbox and apply scale
+    let x = ((element.x - offset.0 as f64) * scale as f64) as f32;
+    let y = ((element.y - offset.1 as f64) * scale as f64) as f32;
+    let width = (element.width * scale as f64) as f32;
+    let height = (element.height * scale as f64) as f32;
+    // Rotation around the element center, applied to every path drawn
+    let rotation = get_element_rotation(element, offset, scale).unwrap_or_default();
+
+    // Element colors as drawn in the export theme
+    let stroke_rgba = get_themed_rgba(parse_color(&element.stroke_color), theme);
+    let fill_rgba = get_themed_rgba(parse_color(&element.background_color), theme);
+
+    let should_stroke = has_stroke(element);
+    let should_fill = has_fill(element);
+
+    let scaled_stroke_width = (element.stroke_width * scale as f64) as f32;
+    let rough = build_rough_options(element, stroke_rgba, fill_rgba, scale);
+    let generator = SkiaGenerator::new(rough.shape.clone());
+    let stroke_gen = SkiaGenerator::new(rough.stroke_only.clone());
+    let cap_gen = SkiaGenerator::new(rough.cap.clone());
 
     // Render based on element type
-    match element.element_type.as_str() {
-        "rectangle" => {
+    match element.kind() {
+        ElementKind::Rectangle => {
             // Check if rectangle has roundness
             if element.roundness.is_some() {
                 // Convert f32 to f64 for corner radius calculation, then use shared path generation
@@ -550,19 +861,19 @@ fn render_element<'a, 'b: 'a>(
                 let path_d = generate_rounded_rect_path(x as f64, y as f64, width as f64, height as f64, r as f64);
 
                 let rounded_rect = generator.path::<f32>(path_d);
-                rounded_rect.draw(pixmap);
+                draw_rough(pixmap, rounded_rect, rotation);
             } else {
                 let rect = generator.rectangle::<f32>(x, y, width, height);
-                rect.draw(pixmap);
+                draw_rough(pixmap, rect, rotation);
             }
         }
-        "ellipse" => {
+        ElementKind::Ellipse => {
             // rough.js ellipse expects center coordinates (cx, cy) and diameters (width, height)
             let (cx, cy) = calculate_center(x, y, width, height);
             let ellipse = generator.ellipse::<f32>(cx, cy, width, height);
-            ellipse.draw(pixmap);
+            draw_rough(pixmap, ellipse, rotation);
         }
-        "diamond" => {
+        ElementKind::Diamond => {
             // Create diamond path using polygon
             let (cx, cy) = calculate_center(x, y, width, height);
             let points = vec![
@@ -572,138 +883,171 @@ fn render_element<'a, 'b: 'a>(
                 Point2D::new(x, cy),           // left
             ];
             let polygon = generator.polygon(&points);
-            polygon.draw(pixmap);
+            draw_rough(pixmap, polygon, rotation);
         }
-        "line" => {
-            if let Some(ref points) = element.points {
-                if points.len() >= 2 {
-                    // Scale points coordinates
-                    let scaled_points: Vec<(f64, f64)> = points.iter()
-                        .map(|p| (p.0 * scale as f64, p.1 * scale as f64))
-                        .collect();
-                    if element.roundness.is_some() {
-                        if let Some(path_d) = build_catmull_rom_cubic_path(&scaled_points, x, y) {
-                            let path = stroke_gen.path::<f32>(path_d);
-                            path.draw(pixmap);
-                        }
-                    } else {
-                        // straight polyline via rough path M/L
-                        let mut d = String::new();
-                        d.push_str(&format!("M {} {}", x + scaled_points[0].0 as f32, y + scaled_points[0].1 as f32));
-                        for p in scaled_points.iter().skip(1) {
-                            d.push_str(&format!(" L {} {}", x + p.0 as f32, y + p.1 as f32));
-                        }
-                        let path = stroke_gen.path::<f32>(d);
-                        path.draw(pixmap);
-                    }
+        ElementKind::Line { points } => {
+            if !points.is_empty() {
+                // Scale points coordinates
+                let scaled_points: Vec<(f64, f64)> = points.iter()
+                    .map(|p| (p.0 * scale as f64, p.1 * scale as f64))
+                    .collect();
+                if let Some(path_d) = build_linear_path_d(element, &scaled_points, x, y, scale) {
+                    let path = stroke_gen.path::<f32>(path_d);
+                    draw_rough(pixmap, path, rotation);
                 }
             }
         }
-        "arrow" => {
-            if let Some(ref points) = element.points {
-                if points.len() >= 2 {
-                    // Scale points coordinates
-                    let scaled_points: Vec<(f64, f64)> = points.iter()
-                        .map(|p| (p.0 * scale as f64, p.1 * scale as f64))
-                        .collect();
-                    
-                    // Draw curve or polyline depending on style
-                    if element.elbowed.unwrap_or(false) && scaled_points.len() >= 3 {
-                        // Convert relative points to absolute
-                        let abs_points: Vec<(f64, f64)> = scaled_points.iter()
-                            .map(|p| (x as f64 + p.0, y as f64 + p.1))
-                            .collect();
-                        
-                        if let Some(path_d) = build_elbow_arrow_path(&abs_points, 16.0 * scale as f64) {
-                            let path = stroke_gen.path::<f32>(path_d);
-                            path.draw(pixmap);
-                        }
-                    } else if element.roundness.is_some() && scaled_points.len() >= 2 {
-                        if let Some(path_d) = build_catmull_rom_cubic_path(&scaled_points, x, y) {
-                            let path = stroke_gen.path::<f32>(path_d);
-                            path.draw(pixmap);
-                        }
-                    } else {
-                        // straight polyline via rough path M/L
-                        let mut d = String::new();
-                        d.push_str(&format!("M {} {}", x + scaled_points[0].0 as f32, y + scaled_points[0].1 as f32));
-                        for p in scaled_points.iter().skip(1) {
-                            d.push_str(&format!(" L {} {}", x + p.0 as f32, y + p.1 as f32));
-                        }
-                        let path = stroke_gen.path::<f32>(d);
-                        path.draw(pixmap);
-                    }
+        ElementKind::Arrow { points, start_arrowhead, end_arrowhead, .. } => {
+            if points.len() >= 2 {
+                // Scale points coordinates
+                let scaled_points: Vec<(f64, f64)> = points.iter()
+                    .map(|p| (p.0 * scale as f64, p.1 * scale as f64))
+                    .collect();
+                
+                // Draw curve or polyline depending on style
+                if let Some(path_d) = build_linear_path_d(element, &scaled_points, x, y, scale) {
+                    let path = stroke_gen.path::<f32>(path_d);
+                    draw_rough(pixmap, path, rotation);
+                }
 
-                    // Draw start arrowhead if specified
-                    if let Some(ref start_arrowhead) = element.start_arrowhead {
-                        draw_arrowhead_ex(
-                            pixmap,
-                            &scaled_points,
-                            x,
-                            y,
-                            stroke_rgba,
-                            scaled_stroke_width,
-                            start_arrowhead,
-                            "start",
-                            &cap_gen,
-                        );
-                    }
-                    
-                    // Draw end arrowhead if specified
-                    if let Some(ref end_arrowhead) = element.end_arrowhead {
-                        draw_arrowhead_ex(
-                            pixmap,
-                            &scaled_points,
-                            x,
-                            y,
-                            stroke_rgba,
-                            scaled_stroke_width,
-                            end_arrowhead,
-                            "end",
-                            &cap_gen,
-                        );
-                    }
+                // Draw start arrowhead if specified
+                if let Some(start_arrowhead) = start_arrowhead {
+                    draw_arrowhead_ex(
+                        pixmap,
+                        &scaled_points,
+                        x,
+                        y,
+                        stroke_rgba,
+                        canvas_rgba,
+                        scaled_stroke_width,
+                        start_arrowhead,
+                        "start",
+                        &cap_gen,
+                        rotation,
+                    );
+                }
+                
+                // Draw end arrowhead if specified
+                if let Some(end_arrowhead) = end_arrowhead {
+                    draw_arrowhead_ex(
+                        pixmap,
+                        &scaled_points,
+                        x,
+                        y,
+                        stroke_rgba,
+                        canvas_rgba,
+                        scaled_stroke_width,
+                        end_arrowhead,
+                        "end",
+                        &cap_gen,
+                        rotation,
+                    );
                 }
             }
         }
-        "text" => {
+        ElementKind::Text { text, font_size, font_family, text_align, .. } => {
             // Render text element
-            if let Some(ref text) = element.text {
-                let font_size = (element.font_size.unwrap_or(20.0) * scale as f64) as f32;
-                let font_family = get_font_family(element.font_family);
-                // Create TextProperties with lifetimes tied to element
+            if !text.is_empty() {
+                let font_size = (font_size * scale as f64) as f32;
+                let font_family = text_ctx.fonts.family_for_id(font_family);
                 let text_props = TextProperties {
-                    text: text.as_str(),
+                    text,
                     x,
                     y: y + font_size,
                     font_size,
                     color: stroke_rgba,
-                    font_family,
-                    text_align: element.text_align.as_deref(),
+                    font_family: &font_family,
+                    text_align: Some(text_align),
                     container_width: width,
                 };
-                // Render text - the lifetime is satisfied because text_props only lives for this scope
-                render_text(pixmap, &text_props, text_ctx);
+                render_text(pixmap, &text_props, text_ctx, rotation);
             }
         }
-        _ => {
-            // Unsupported element type
-            eprintln!("Unsupported element type: {}", element.element_type);
+        ElementKind::Freedraw { points, .. } => {
+            // Closed pen loops get a rough background fill, like Excalidraw
+            if should_fill && is_path_a_loop(points) {
+                let fill_gen = SkiaGenerator::new(rough.loop_fill.clone());
+
+                let polygon_points: Vec<Point2D<f32>> = points.iter()
+                    .map(|p| Point2D::new(x + (p.0 * scale as f64) as f32, y + (p.1 * scale as f64) as f32))
+                    .collect();
+                let polygon = fill_gen.polygon(&polygon_points);
+                draw_rough(pixmap, polygon, rotation);
+            }
+
+            // The stroke itself is a filled, pressure-variable outline
+            if should_stroke {
+                let outline = get_freedraw_outline(element);
+                if let Some(path) = build_freedraw_path(&outline, x, y, scale) {
+                    let mut paint = Paint::default();
+                    paint.set_color_rgba8(stroke_rgba.0, stroke_rgba.1, stroke_rgba.2, stroke_rgba.3);
+                    paint.anti_alias = true;
+                    pixmap.fill_path(&path, &paint, FillRule::Winding, rotation, None);
+                }
+            }
+        }
+        ElementKind::Image { .. } => {
+            draw_image(pixmap, element, files, x, y, width, height, scale);
         }
+        ElementKind::Frame { .. } | ElementKind::Magicframe { .. } => {
+            // Frames are never rough: a rounded outline with the title above the top-left corner
+            if let Some(path) = build_rounded_rect_path(x, y, width, height, FRAME_RADIUS as f32 * scale) {
+                let (r, g, b, a) = get_themed_rgba(parse_color(FRAME_STROKE_COLOR), theme);
+                let mut paint = Paint::default();
+                paint.set_color_rgba8(r, g, b, a);
+                paint.anti_alias = true;
+                let stroke = Stroke {
+                    width: FRAME_STROKE_WIDTH as f32 * scale,
+                    ..Stroke::default()
+                };
+                pixmap.stroke_path(&path, &paint, &stroke, rotation, None);
+            }
+
+            let name_props = TextProperties {
+                text: get_frame_name(element),
+                x,
+                y: ((get_frame_name_baseline(element) - offset.1 as f64) * scale as f64) as f32,
+                font_size: (FRAME_NAME_FONT_SIZE * scale as f64) as f32,
+                color: get_themed_rgba(parse_color(FRAME_NAME_COLOR), theme),
+                font_family: FRAME_NAME_FONT_FAMILY,
+                text_align: None,
+                container_width: width,
+            };
+            render_text(pixmap, &name_props, text_ctx, rotation);
+        }
+        // Reported as warnings before rendering
+        ElementKind::Embeddable | ElementKind::Iframe | ElementKind::Unknown => {}
     }
 }
 
-/// Load custom fonts from embedded bytes
-fn load_custom_fonts() -> std::collections::HashMap<String, Vec<u8>> {
-    let mut fonts = std::collections::HashMap::new();
-    
-    // Load fonts from embedded bytes
-    fonts.insert("Liberation Sans".to_string(), LIBERATION_SANS_REGULAR.to_vec());
-    fonts.insert("Cascadia Code".to_string(), CASCADIA_CODE.to_vec());
-    fonts.insert("Excalifont".to_string(), EXCALIFONT_REGULAR.to_vec());
-    
-    eprintln!("Loaded {} custom fonts from embedded bytes", fonts.len());
-    fonts
+/// Rotation of an element around its center, like Excalidraw's canvas rotate, or `None`
+/// when it is not rotated. Images apply their own rotation when drawn.
+pub(crate) fn get_element_rotation(element: &Element, offset: (f32, f32), scale: f32) -> Option<Transform> {
+    if element.angle == 0.0 || element.element_type == ElementType::Image {
+        return None;
+    }
+    let (cx, cy) = get_element_center(element);
+    let cx = ((cx - offset.0 as f64) * scale as f64) as f32;
+    let cy = ((cy - offset.1 as f64) * scale as f64) as f32;
+    Some(Transform::from_rotate_at(element.angle.to_degrees() as f32, cx, cy))
+}
+
+/// Opacity to composite an element's layer at, or `None` when it is fully opaque
+/// Compositing the whole element at once keeps overlapping strokes and fills inside
+/// one element from darkening each other. Images apply their own opacity when drawn.
+pub(crate) fn get_layer_opacity(element: &Element) -> Option<f32> {
+    if element.element_type == ElementType::Image {
+        return None;
+    }
+    let opacity = (element.opacity / 100.0).clamp(0.0, 1.0) as f32;
+    (opacity < 1.0).then_some(opacity)
+}
+
+/// Make the provider's fonts available to parley, next to the system fonts
+fn register_fonts(font_cx: &mut FontContext, fonts: &dyn FontProvider) {
+    for font_data in fonts.fonts() {
+        font_cx.collection.register_fonts(Blob::new(font_data), None);
+    }
 }
 
 
@@ -714,62 +1058,172 @@ pub fn render_to_png(
     quality: u8,
     dpi: Option<u32>,
 ) -> Result<()> {
-    let viewbox = calculate_viewbox(&data.elements);
-    
-    // Calculate scale factor from DPI (assume source is 96 DPI)
-    const SOURCE_DPI: f32 = 96.0;
-    let scale = dpi.map(|d| d as f32 / SOURCE_DPI).unwrap_or(1.0);
+    let options = RenderOptions {
+        background,
+        quality,
+        dpi,
+        ..RenderOptions::default()
+    };
+    render_to_png_with_options(data, output_path, &options)
+}
+
+/// Render a scene to a PNG file with the given options
+pub fn render_to_png_with_options(
+    data: &ExcalidrawData,
+    output_path: &std::path::Path,
+    options: &RenderOptions,
+) -> Result<()> {
+    render_to_raster(data, output_path, RasterFormat::Png, options)
+}
+
+/// Render a scene to a PNG, JPEG or WebP file with the given options
+/// JPEG output is flattened onto the background color, since it has no alpha.
+pub fn render_to_raster(
+    data: &ExcalidrawData,
+    output_path: &std::path::Path,
+    format: RasterFormat,
+    options: &RenderOptions,
+) -> Result<()> {
+    let pixmap = render_to_pixmap(data, options)?;
+
+    // Encode with the format's quality control
+    let embed_scene = options.resolve_embedded_scene(data)?;
+    save_pixmap(&pixmap, output_path, format, options.quality, options.resolve_matte(data), embed_scene.as_ref())?;
+
+    Ok(())
+}
+
+/// Render a scene to PNG bytes with the given options
+pub fn render_to_png_bytes(data: &ExcalidrawData, options: &RenderOptions) -> Result<Vec<u8>> {
+    render_to_raster_bytes(data, RasterFormat::Png, options)
+}
+
+/// Render a scene to PNG, JPEG or WebP bytes with the given options
+pub fn render_to_raster_bytes(data: &ExcalidrawData, format: RasterFormat, options: &RenderOptions) -> Result<Vec<u8>> {
+    let pixmap = render_to_pixmap(data, options)?;
+    let embed_scene = options.resolve_embedded_scene(data)?;
+    encode_pixmap(&pixmap, format, options.quality, options.resolve_matte(data), embed_scene.as_ref())
+}
+
+/// Render a scene as PNG, JPEG or WebP into any writer, such as a socket or `stdout`
+pub fn render_to_raster_writer<W: std::io::Write>(
+    data: &ExcalidrawData,
+    writer: &mut W,
+    format: RasterFormat,
+    options: &RenderOptions,
+) -> Result<()> {
+    let pixmap = render_to_pixmap(data, options)?;
+    let embed_scene = options.resolve_embedded_scene(data)?;
+    write_pixmap(&pixmap, writer, format, options.quality, options.resolve_matte(data), embed_scene.as_ref())
+}
+
+/// Render a scene to raw pixels: `(width, height, rgba)`
+/// The pixels are packed RGBA rows with straight alpha, as image libraries expect.
+pub fn render_to_rgba(data: &ExcalidrawData, options: &RenderOptions) -> Result<(u32, u32, Vec<u8>)> {
+    let pixmap = render_to_pixmap(data, options)?;
+    Ok((pixmap.width(), pixmap.height(), pixmap_to_rgba(&pixmap)))
+}
+
+/// Render a scene into a pixmap
+/// Fails when `options.frame` does not name a frame of the scene, or `options.region` is empty.
+pub fn render_to_pixmap(data: &ExcalidrawData, options: &RenderOptions) -> Result<Pixmap> {
+    let padding = options.resolve_padding(data);
+    let (viewbox, elements) = resolve_export_scope(data, options.frame.as_deref(), options.region, padding)?;
+    options.report_element_warnings(&elements, &data.files);
+    options.report_text_warnings(&elements);
+    let theme = options.resolve_theme(data);
+
+    // Scale factor from DPI (assuming a 96 DPI source) or the export scale
+    let scale = options.resolve_scale(data) as f32;
     
     let width = (viewbox.width * scale as f64).ceil() as u32;
     let height = (viewbox.height * scale as f64).ceil() as u32;
     
     let mut pixmap = Pixmap::new(width, height)
-        .ok_or_else(|| anyhow::anyhow!("Failed to create pixmap"))?;
-
-    // Fill background if provided (or default to white if None)
-    if let Some((r, g, b, a)) = background.or(Some((255, 255, 255, 255))) {
-        if a > 0 {
-            let mut background_paint = Paint::default();
-            background_paint.set_color_rgba8(r, g, b, a);
-            pixmap.fill_rect(
-                Rect::from_xywh(0.0, 0.0, width as f32, height as f32).unwrap(),
-                &background_paint,
-                Transform::identity(),
-                None,
-            );
-        }
+        .ok_or(ExcaliosaError::DimensionTooLarge { width, height })?;
+
+    // Fill background if provided (or default to white if None), as drawn in the theme
+    let background = options.resolve_background(data).unwrap_or((255, 255, 255, 255));
+    let (r, g, b, a) = get_themed_rgba(background, theme);
+    if a > 0 {
+        let mut background_paint = Paint::default();
+        background_paint.set_color_rgba8(r, g, b, a);
+        pixmap.fill_rect(
+            Rect::from_xywh(0.0, 0.0, width as f32, height as f32).unwrap(),
+            &background_paint,
+            Transform::identity(),
+            None,
+        );
     }
     
     // Create font and layout contexts for text rendering
     let mut font_cx = FontContext::default();
     let mut layout_cx = LayoutContext::new();
-    
-    // Load custom fonts from the fonts directory
-    let custom_fonts = load_custom_fonts();
+    register_fonts(&mut font_cx, options.fonts.as_ref());
     
     // Create transform matrix for scaling
     let transform = Transform::from_scale(scale, scale);
     
+    let offset = (viewbox.min_x as f32, viewbox.min_y as f32);
+    // Outline arrowheads are filled with the canvas color
+    let (r, g, b) = options.resolve_matte(data);
+    let canvas_rgba = (r, g, b, 255);
+    // Children of a frame are clipped to the frame's bounds through a mask
+    let frames = collect_frames(&data.elements);
+    let mut frame_masks: HashMap<&str, Option<Mask>> = HashMap::new();
+    // Text is wrapped to its available width, and bound text positioned inside its container
+    let elements_by_id = index_elements(&data.elements);
+
+    let mut text_ctx = TextRenderContext {
+        font_cx: &mut font_cx,
+        layout_cx: &mut layout_cx,
+        fonts: options.fonts.as_ref(),
+    };
+
+    let mut scratch_layer: Option<Pixmap> = None;
+
     // Render each element
-    for element in &data.elements {
-        // Create text rendering context for each element to avoid borrowing conflicts
-        let mut text_ctx = TextRenderContext {
-            font_cx: &mut font_cx,
-            layout_cx: &mut layout_cx,
-            custom_fonts: &custom_fonts,
+    for element in elements {
+        if element.is_deleted || element.opacity <= 0.0 {
+            continue;
+        }
+        let element = layout_text_element(element, &elements_by_id, options.fonts.as_ref());
+        let element = &*element;
+
+        let clip_mask = match get_containing_frame(element, &frames) {
+            Some(frame) => frame_masks
+                .entry(frame.id.as_str())
+                .or_insert_with(|| build_frame_mask(frame, offset, scale, width, height))
+                .as_ref(),
+            None => None,
         };
-        
-        render_element(
-            &mut pixmap.as_mut(),
-            element,
-            (viewbox.min_x as f32, viewbox.min_y as f32),
-            &mut text_ctx,
-            transform,
-        );
+        let label_mask = get_arrow_label_mask_box(element, &elements_by_id, options.fonts.as_ref())
+            .and_then(|label_box| build_arrow_label_mask(clip_mask, label_box, offset, scale, width, height));
+        let clip_mask = label_mask.as_ref().or(clip_mask);
+
+        let layer_opacity = get_layer_opacity(element);
+        if layer_opacity.is_some() || clip_mask.is_some() {
+            // Draw on a layer, then composite it pixel for pixel at its opacity, clipped to
+            // its frame. One scratch layer is cleared and reused for every such element.
+            let mut layer = match scratch_layer.take() {
+                Some(mut layer) => {
+                    layer.fill(Color::TRANSPARENT);
+                    layer
+                }
+                None => Pixmap::new(width, height)
+                    .ok_or(ExcaliosaError::DimensionTooLarge { width, height })?,
+            };
+            render_element(&mut layer.as_mut(), element, offset, &mut text_ctx, &data.files, transform, theme, canvas_rgba);
+            let layer_paint = PixmapPaint {
+                opacity: layer_opacity.unwrap_or(1.0),
+                ..PixmapPaint::default()
+            };
+            pixmap.draw_pixmap(0, 0, layer.as_ref(), &layer_paint, Transform::identity(), clip_mask);
+            scratch_layer = Some(layer);
+        } else {
+            render_element(&mut pixmap.as_mut(), element, offset, &mut text_ctx, &data.files, transform, theme, canvas_rgba);
+        }
     }
-    
-    // Save to PNG with quality control
-    save_png_with_quality(&pixmap, output_path, quality)?;
-    
-    Ok(())
+
+    Ok(pixmap)
 }
@@ -0,0 +1,239 @@
+//! Lenient scene loading, modeled on Excalidraw's `restoreElements`
+//! Hand-written and older scenes often omit fields the model requires. Missing fields get
+//! Excalidraw's defaults, legacy fields are migrated, and elements of unknown types are
+//! dropped with a warning instead of failing the whole scene.
+use crate::error::{ExcaliosaError, Result, Warning};
+use crate::font_utils::get_font_family_by_name;
+use crate::models::ExcalidrawData;
+use serde_json::{json, Map, Value};
+
+/// Element types the model knows about
+const KNOWN_ELEMENT_TYPES: &[&str] = &[
+    "rectangle", "diamond", "ellipse", "text", "freedraw", "image", "line", "arrow", "frame", "magicframe",
+    "embeddable", "iframe",
+];
+
+/// Excalidraw's default font family (Excalifont) and size
+const DEFAULT_FONT_FAMILY: i64 = 5;
+const DEFAULT_FONT_SIZE: f64 = 20.0;
+
+/// Parse scene JSON, restoring missing and legacy fields
+/// Elements that cannot be restored are dropped, with a warning pushed to `warnings`.
+pub fn parse_scene(json: &str, warnings: &mut Vec<Warning>) -> Result<ExcalidrawData> {
+    let value: Value = serde_json::from_str(json)?;
+    restore_scene(value, warnings)
+}
+
+/// Restore a scene value into the model
+pub fn restore_scene(mut scene: Value, warnings: &mut Vec<Warning>) -> Result<ExcalidrawData> {
+    let Some(object) = scene.as_object_mut() else {
+        return Err(ExcaliosaError::Parse("Excalidraw scene is not a JSON object".to_string()));
+    };
+    set_default(object, "type", json!("excalidraw"));
+
+    let elements = match object.remove("elements") {
+        Some(Value::Array(elements)) => elements,
+        _ => Vec::new(),
+    };
+    let (elements, skipped) = restore_elements(elements);
+    warnings.extend(skipped);
+    object.insert("elements".to_string(), Value::Array(elements));
+    if object.get("appState").is_some_and(Value::is_null) {
+        object.remove("appState");
+    }
+    if object.get("files").is_some_and(Value::is_null) {
+        object.remove("files");
+    }
+
+    Ok(serde_json::from_value(scene)?)
+}
+
+/// Restore each element, dropping the ones that cannot be restored
+/// Returns the restored elements and a warning for each dropped element.
+pub fn restore_elements(elements: Vec<Value>) -> (Vec<Value>, Vec<Warning>) {
+    let mut warnings = Vec::new();
+    let mut restored: Vec<Value> = elements
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, element)| match restore_element(element, i) {
+            Ok(element) => Some(Value::Object(element)),
+            Err(reason) => {
+                warnings.push(Warning::SkippedElement { index: i, reason });
+                None
+            }
+        })
+        .collect();
+
+    // Fractional indices must increase along the array; regenerate them all when they don't
+    let indices: Vec<Option<&str>> = restored.iter().map(|el| el["index"].as_str()).collect();
+    let valid = indices.iter().all(Option::is_some) && indices.windows(2).all(|pair| pair[0] < pair[1]);
+    if !valid {
+        for (i, element) in restored.iter_mut().enumerate() {
+            element["index"] = Value::String(index_key(i));
+        }
+    }
+    (restored, warnings)
+}
+
+/// Restore the element at `position`, or explain why it was dropped
+fn restore_element(element: Value, position: usize) -> Result<Map<String, Value>, String> {
+    let Value::Object(mut el) = element else {
+        return Err("not a JSON object".to_string());
+    };
+    let element_type = match el.get("type").and_then(Value::as_str) {
+        Some("draw") => "line".to_string(),
+        Some(element_type) if KNOWN_ELEMENT_TYPES.contains(&element_type) => element_type.to_string(),
+        Some(element_type) => return Err(format!("unsupported element type \"{element_type}\"")),
+        None => return Err("missing element type".to_string()),
+    };
+    el.insert("type".to_string(), json!(element_type));
+
+    restore_common(&mut el, &element_type, position);
+    match element_type.as_str() {
+        "text" => restore_text(&mut el),
+        "freedraw" => {
+            set_default(&mut el, "points", json!([]));
+            set_default(&mut el, "pressures", json!([]));
+            set_default(&mut el, "simulatePressure", json!(true));
+        }
+        "image" => {
+            set_if_falsy(&mut el, "status", json!("pending"));
+            set_default(&mut el, "scale", json!([1, 1]));
+        }
+        "line" | "arrow" => restore_linear(&mut el, &element_type),
+        _ => {}
+    }
+    Ok(el)
+}
+
+/// Properties shared by all elements (`restoreElementWithProperties`)
+fn restore_common(el: &mut Map<String, Value>, element_type: &str, position: usize) {
+    set_if_falsy(el, "id", json!(format!("element-{position}")));
+    set_if_falsy(el, "version", json!(1));
+    set_default(el, "versionNonce", json!(0));
+    set_default(el, "isDeleted", json!(false));
+    set_if_falsy(el, "fillStyle", json!("solid"));
+    set_if_falsy(el, "strokeWidth", json!(2));
+    set_default(el, "strokeStyle", json!("solid"));
+    set_default(el, "roughness", json!(1));
+    set_default(el, "opacity", json!(100));
+    set_if_falsy(el, "angle", json!(0));
+    set_default(el, "x", json!(0));
+    set_default(el, "y", json!(0));
+    set_if_falsy(el, "strokeColor", json!("#1e1e1e"));
+    set_if_falsy(el, "backgroundColor", json!("transparent"));
+    set_if_falsy(el, "width", json!(0));
+    set_if_falsy(el, "height", json!(0));
+    set_default(el, "seed", json!(1));
+    set_default(el, "groupIds", json!([]));
+    set_default(el, "updated", json!(1));
+    set_default(el, "locked", json!(false));
+    if is_falsy(el.get("link")) {
+        el.insert("link".to_string(), Value::Null);
+    }
+
+    // Legacy `strokeSharpness: "round"` becomes a roundness of the type's kind
+    let sharpness = el.remove("strokeSharpness");
+    if is_falsy(el.get("roundness")) {
+        let roundness = match sharpness.as_ref().and_then(Value::as_str) {
+            Some("round") if uses_adaptive_radius(element_type) => json!({ "type": 3 }),
+            Some("round") => json!({ "type": 2 }),
+            _ => Value::Null,
+        };
+        el.insert("roundness".to_string(), roundness);
+    }
+
+    // Legacy `boundElementIds` only ever listed arrows
+    if let Some(Value::Array(ids)) = el.remove("boundElementIds") {
+        let bound: Vec<Value> = ids.into_iter().map(|id| json!({ "type": "arrow", "id": id })).collect();
+        el.insert("boundElements".to_string(), Value::Array(bound));
+    }
+}
+
+fn restore_text(el: &mut Map<String, Value>) {
+    // Legacy `font: "20px Virgil"`
+    if let Some(font) = el.remove("font") {
+        let mut parts = font.as_str().unwrap_or("").split(' ');
+        if let Some(size) = parts.next().and_then(|px| px.trim_end_matches("px").parse::<f64>().ok()) {
+            el.insert("fontSize".to_string(), json!(size));
+        }
+        let family = parts
+            .next()
+            .and_then(|name| get_font_family_by_name(name.trim_end_matches(',')))
+            .map_or(DEFAULT_FONT_FAMILY, |family| family.id as i64);
+        el.insert("fontFamily".to_string(), json!(family));
+    }
+    set_if_falsy(el, "fontSize", json!(DEFAULT_FONT_SIZE));
+    set_if_falsy(el, "fontFamily", json!(DEFAULT_FONT_FAMILY));
+
+    let text = el.get("text").and_then(Value::as_str).unwrap_or("").replace("\r\n", "\n");
+    el.insert("text".to_string(), json!(text));
+    set_if_falsy(el, "originalText", json!(text));
+    set_if_falsy(el, "textAlign", json!("left"));
+    set_if_falsy(el, "verticalAlign", json!("top"));
+    set_default(el, "autoResize", json!(true));
+}
+
+fn restore_linear(el: &mut Map<String, Value>, element_type: &str) {
+    // Lines need at least two points: span the element's box
+    let has_points = el.get("points").and_then(Value::as_array).is_some_and(|points| points.len() >= 2);
+    if !has_points {
+        let width = el["width"].clone();
+        let height = el["height"].clone();
+        el.insert("points".to_string(), json!([[0, 0], [width, height]]));
+    }
+    if !el.contains_key("endArrowhead") && element_type == "arrow" {
+        el.insert("endArrowhead".to_string(), json!("arrow"));
+    }
+    if element_type == "arrow" {
+        set_default(el, "elbowed", json!(false));
+    }
+}
+
+/// Rectangular elements use adaptive corner radii
+fn uses_adaptive_radius(element_type: &str) -> bool {
+    matches!(element_type, "rectangle" | "embeddable" | "iframe" | "image")
+}
+
+/// JavaScript's `??`: replace a missing or null value
+fn set_default(el: &mut Map<String, Value>, key: &str, default: Value) {
+    if el.get(key).is_none_or(Value::is_null) {
+        el.insert(key.to_string(), default);
+    }
+}
+
+/// JavaScript's `||`: replace a missing, null, false, zero or empty value
+fn set_if_falsy(el: &mut Map<String, Value>, key: &str, default: Value) {
+    if is_falsy(el.get(key)) {
+        el.insert(key.to_string(), default);
+    }
+}
+
+fn is_falsy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::Bool(b)) => !b,
+        Some(Value::Number(n)) => n.as_f64() == Some(0.0),
+        Some(Value::String(s)) => s.is_empty(),
+        Some(_) => false,
+    }
+}
+
+/// Fractional index key for the element at `position`, in Excalidraw's base-62 format:
+/// "a0".."az", then "b00".."bzz", then "c000"..
+fn index_key(position: usize) -> String {
+    const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    let mut rest = position;
+    let mut head = b'a';
+    let mut width = 1;
+    while rest >= 62usize.pow(width) {
+        rest -= 62usize.pow(width);
+        head += 1;
+        width += 1;
+    }
+    let mut key = String::from(head as char);
+    for i in (0..width).rev() {
+        key.push(DIGITS[rest / 62usize.pow(i) % 62] as char);
+    }
+    key
+}
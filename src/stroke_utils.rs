@@ -1,16 +1,18 @@
+use crate::models::StrokeStyle;
+
 /// Compute stroke dash pattern based on Excalidraw's strokeStyle and strokeWidth
 /// Returns dash array as Vec<f64> or None for solid strokes
-pub fn get_stroke_dash_array(stroke_style: &str, stroke_width: f64) -> Option<Vec<f64>> {
+pub fn get_stroke_dash_array(stroke_style: StrokeStyle, stroke_width: f64) -> Option<Vec<f64>> {
     match stroke_style {
-        "dashed" => {
+        StrokeStyle::Dashed => {
             // [8, 8 + strokeWidth]
             Some(vec![8.0, 8.0 + stroke_width.max(0.0)])
         }
-        "dotted" => {
+        StrokeStyle::Dotted => {
             // [1.5, 6 + strokeWidth]
             Some(vec![1.5, 6.0 + stroke_width.max(0.0)])
         }
-        _ => None,
+        StrokeStyle::Solid | StrokeStyle::Unknown => None,
     }
 }
 
@@ -22,7 +24,7 @@ pub fn get_dotted_cap_dash_array(stroke_width: f64) -> Vec<f64> {
 
 /// Get stroke dash array as SVG attribute value (e.g., "8,12")
 /// Returns "none" for solid strokes (for use in stroke-dasharray attribute)
-pub fn get_stroke_dasharray_attr(stroke_style: &str, stroke_width: f64) -> String {
+pub fn get_stroke_dasharray_attr(stroke_style: StrokeStyle, stroke_width: f64) -> String {
     get_stroke_dash_array(stroke_style, stroke_width)
         .map(|dash| {
             dash.iter()
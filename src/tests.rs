@@ -1,13 +1,16 @@
 #[cfg(test)]
 mod renderer_tests {
-    use crate::models::{ExcalidrawData, ExcalidrawElement};
+    use crate::models::{
+        Arrowhead, ElementType, ExcalidrawData, ExcalidrawElement, FillStyle, StrokeStyle, TextAlign, VerticalAlign,
+    };
+    use crate::fonts::FontSet;
     use crate::renderer::generate_svg;
     use crate::utils::calculate_viewbox;
     use std::collections::HashMap;
 
     fn create_test_element(
         id: &str,
-        element_type: &str,
+        element_type: ElementType,
         x: f64,
         y: f64,
         width: f64,
@@ -15,7 +18,7 @@ mod renderer_tests {
     ) -> ExcalidrawElement {
         ExcalidrawElement {
             id: id.to_string(),
-            element_type: element_type.to_string(),
+            element_type,
             x,
             y,
             width,
@@ -23,9 +26,9 @@ mod renderer_tests {
             angle: 0.0,
             stroke_color: "#000000".to_string(),
             background_color: "none".to_string(),
-            fill_style: "solid".to_string(),
+            fill_style: FillStyle::Solid,
             stroke_width: 1.0,
-            stroke_style: "solid".to_string(),
+            stroke_style: StrokeStyle::Solid,
             roughness: 0.0,
             opacity: 100.0,
             group_ids: vec![],
@@ -47,6 +50,7 @@ mod renderer_tests {
             container_id: None,
             original_text: None,
             line_height: None,
+            auto_resize: None,
             baseline: None,
             start_binding: None,
             end_binding: None,
@@ -57,7 +61,15 @@ mod renderer_tests {
             points: None,
             last_committed_point: None,
             elbowed: None,
+            pressures: None,
+            simulate_pressure: None,
+            file_id: None,
+            status: None,
+            scale: None,
+            crop: None,
+            name: None,
             version: None,
+            extra: Default::default(),
         }
     }
 
@@ -74,7 +86,7 @@ mod renderer_tests {
 
     #[test]
     fn test_calculate_viewbox_single_element() {
-        let elements = vec![create_test_element("rect1", "rectangle", 100.0, 100.0, 200.0, 150.0)];
+        let elements = vec![create_test_element("rect1", ElementType::Rectangle, 100.0, 100.0, 200.0, 150.0)];
         let viewbox = calculate_viewbox(&elements);
 
         assert_eq!(viewbox.min_x, 60.0); // 100 - 40 padding
@@ -85,8 +97,8 @@ mod renderer_tests {
 
     #[test]
     fn test_calculate_viewbox_ignores_deleted() {
-        let element1 = create_test_element("rect1", "rectangle", 100.0, 100.0, 200.0, 150.0);
-        let mut element2 = create_test_element("rect2", "rectangle", 500.0, 500.0, 100.0, 100.0);
+        let element1 = create_test_element("rect1", ElementType::Rectangle, 100.0, 100.0, 200.0, 150.0);
+        let mut element2 = create_test_element("rect2", ElementType::Rectangle, 500.0, 500.0, 100.0, 100.0);
         element2.is_deleted = true;
 
         let elements = vec![element1, element2];
@@ -101,7 +113,7 @@ mod renderer_tests {
 
     #[test]
     fn test_generate_svg_basic() {
-        let element = create_test_element("rect1", "rectangle", 100.0, 100.0, 200.0, 150.0);
+        let element = create_test_element("rect1", ElementType::Rectangle, 100.0, 100.0, 200.0, 150.0);
         let data = ExcalidrawData {
             data_type: "excalidraw".to_string(),
             version: Some(2),
@@ -142,7 +154,7 @@ mod renderer_tests {
 
     #[test]
     fn test_rectangle_rendering() {
-        let mut element = create_test_element("rect1", "rectangle", 100.0, 100.0, 200.0, 150.0);
+        let mut element = create_test_element("rect1", ElementType::Rectangle, 100.0, 100.0, 200.0, 150.0);
         element.stroke_color = "#ff0000".to_string();
         element.background_color = "#00ff00".to_string();
 
@@ -164,7 +176,7 @@ mod renderer_tests {
 
     #[test]
     fn test_ellipse_rendering() {
-        let element = create_test_element("circle1", "ellipse", 200.0, 200.0, 100.0, 100.0);
+        let element = create_test_element("circle1", ElementType::Ellipse, 200.0, 200.0, 100.0, 100.0);
         let data = ExcalidrawData {
             data_type: "excalidraw".to_string(),
             version: Some(2),
@@ -183,7 +195,7 @@ mod renderer_tests {
 
     #[test]
     fn test_text_rendering() {
-        let mut element = create_test_element("text1", "text", 100.0, 100.0, 100.0, 40.0);
+        let mut element = create_test_element("text1", ElementType::Text, 100.0, 100.0, 100.0, 40.0);
         element.text = Some("Hello World".to_string());
         element.font_size = Some(16.0);
 
@@ -205,7 +217,7 @@ mod renderer_tests {
     #[test]
     fn test_transparent_background() {
         // Test rectangle with transparent background - should have no fill
-        let mut element = create_test_element("rect1", "rectangle", 100.0, 100.0, 200.0, 150.0);
+        let mut element = create_test_element("rect1", ElementType::Rectangle, 100.0, 100.0, 200.0, 150.0);
         element.stroke_color = "#000000".to_string();
         element.background_color = "transparent".to_string();
         element.stroke_width = 2.0;
@@ -228,7 +240,7 @@ mod renderer_tests {
     #[test]
     fn test_transparent_stroke() {
         // Test rectangle with transparent stroke - should have no stroke
-        let mut element = create_test_element("rect1", "rectangle", 100.0, 100.0, 200.0, 150.0);
+        let mut element = create_test_element("rect1", ElementType::Rectangle, 100.0, 100.0, 200.0, 150.0);
         element.stroke_color = "transparent".to_string();
         element.background_color = "#ff0000".to_string();
         element.stroke_width = 0.0;
@@ -251,7 +263,7 @@ mod renderer_tests {
     #[test]
     fn test_both_stroke_and_fill() {
         // Test rectangle with both stroke and fill
-        let mut element = create_test_element("rect1", "rectangle", 100.0, 100.0, 200.0, 150.0);
+        let mut element = create_test_element("rect1", ElementType::Rectangle, 100.0, 100.0, 200.0, 150.0);
         element.stroke_color = "#000000".to_string();
         element.background_color = "#dbeafe".to_string();
         element.stroke_width = 2.0;
@@ -274,8 +286,8 @@ mod renderer_tests {
     #[test]
     fn test_hachure_fill_style() {
         // Test rectangle with hachure fill style
-        let mut element = create_test_element("rect1", "rectangle", 0.0, 0.0, 100.0, 100.0);
-        element.fill_style = "hachure".to_string();
+        let mut element = create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 100.0);
+        element.fill_style = FillStyle::Hachure;
         element.background_color = "#868e96".to_string();
         element.stroke_color = "#1e1e1e".to_string();
         element.stroke_width = 2.0;
@@ -303,13 +315,13 @@ mod renderer_tests {
     #[test]
     fn test_arrowheads_are_solid_even_when_dotted() {
         // Create an arrow with dotted stroke and arrowheads
-        let mut element = create_test_element("arrow1", "arrow", 0.0, 0.0, 0.0, 0.0);
+        let mut element = create_test_element("arrow1", ElementType::Arrow, 0.0, 0.0, 0.0, 0.0);
         element.stroke_color = "#000000".to_string();
-        element.stroke_style = "dotted".to_string();
+        element.stroke_style = StrokeStyle::Dotted;
         element.stroke_width = 2.0;
         element.points = Some(vec![(0.0, 0.0), (100.0, 0.0)]);
-        element.start_arrowhead = Some("arrow".to_string());
-        element.end_arrowhead = Some("arrow".to_string());
+        element.start_arrowhead = Some(Arrowhead::Arrow);
+        element.end_arrowhead = Some(Arrowhead::Arrow);
 
         let data = ExcalidrawData {
             data_type: "excalidraw".to_string(),
@@ -328,5 +340,1661 @@ mod renderer_tests {
         let dasharray_count = svg.matches("stroke-dasharray=\"").count();
         assert_eq!(dasharray_count, 1, "Only the shaft should be dashed, arrowheads must be solid");
     }
+
+    #[test]
+    fn test_freedraw_rendering() {
+        let mut element = create_test_element("pen1", ElementType::Freedraw, 10.0, 10.0, 100.0, 50.0);
+        element.stroke_color = "#1e1e1e".to_string();
+        element.stroke_width = 2.0;
+        element.points = Some(vec![(0.0, 0.0), (20.0, 10.0), (50.0, 30.0), (100.0, 50.0)]);
+        element.pressures = Some(vec![0.2, 0.5, 0.8, 0.4]);
+        element.simulate_pressure = Some(false);
+
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+
+        let svg = generate_svg(&data, None);
+        // Freedraw strokes are filled outlines, not stroked paths
+        assert!(svg.contains("fill=\"#1e1e1e\" stroke=\"none\""), "Freedraw should be a filled outline");
+        assert!(svg.contains(" Q "), "Freedraw outline should use quadratic curves");
+    }
+
+    #[test]
+    fn test_freedraw_single_point_is_dot() {
+        let mut element = create_test_element("pen1", ElementType::Freedraw, 0.0, 0.0, 0.0, 0.0);
+        element.stroke_width = 2.0;
+        element.points = Some(vec![(0.0, 0.0)]);
+        element.last_committed_point = Some(vec![0.0, 0.0]);
+
+        let outline = crate::freedraw_utils::get_freedraw_outline(&element);
+        assert!(!outline.is_empty(), "A single point should still produce an outline");
+        // Every outline point stays within the stroke size around the origin
+        let size = element.stroke_width * 4.25;
+        assert!(outline.iter().all(|(x, y)| x.hypot(*y) <= size + 1.5));
+    }
+
+    // 2x2 PNG: red, blue / green, white
+    const TEST_PNG_DATA_URL: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAYAAABytg0kAAAAEklEQVR4nGP4z8AAQmDqPwgAAEnICfchVbMlAAAAAElFTkSuQmCC";
+
+    #[test]
+    fn test_decode_png_data_url() {
+        use crate::image_utils::{decode_image, parse_data_url};
+
+        let (mime_type, bytes) = parse_data_url(TEST_PNG_DATA_URL).expect("valid data URL");
+        assert_eq!(mime_type, "image/png");
+
+        let pixmap = decode_image(&mime_type, &bytes, (2, 2)).expect("decodable PNG");
+        assert_eq!((pixmap.width(), pixmap.height()), (2, 2));
+        let top_left = pixmap.pixel(0, 0).unwrap();
+        assert_eq!((top_left.red(), top_left.green(), top_left.blue()), (255, 0, 0));
+    }
+
+    #[test]
+    fn test_image_rendering_skia() {
+        use crate::models::FileData;
+        use crate::renderer_skia::render_to_png;
+
+        let mut element = create_test_element("img1", ElementType::Image, 0.0, 0.0, 40.0, 40.0);
+        element.file_id = Some("file1".to_string());
+        element.status = Some("saved".to_string());
+        element.scale = Some((-1.0, 1.0));
+
+        let mut files = HashMap::new();
+        files.insert(
+            "file1".to_string(),
+            FileData {
+                mime_type: "image/png".to_string(),
+                id: "file1".to_string(),
+                data_url: TEST_PNG_DATA_URL.to_string(),
+                created: None,
+                last_retrieved: None,
+            },
+        );
+
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files,
+        };
+
+        let path = std::env::temp_dir().join(format!("excaliosa_image_skia_{}.png", std::process::id()));
+        render_to_png(&data, &path, None, 0, None).expect("default renderer");
+        let pixmap = tiny_skia::Pixmap::load_png(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // The image spans (40, 40)..(80, 80) after padding; sample each quadrant's center
+        let rgb = |x: u32, y: u32| {
+            let c = pixmap.pixel(x, y).unwrap().demultiply();
+            (c.red(), c.green(), c.blue())
+        };
+        let is_close = |a: (u8, u8, u8), b: (u8, u8, u8)| {
+            a.0.abs_diff(b.0) < 48 && a.1.abs_diff(b.1) < 48 && a.2.abs_diff(b.2) < 48
+        };
+        // Flipped horizontally: blue, red / white, green
+        assert!(is_close(rgb(50, 50), (0, 0, 255)), "top-left was {:?}", rgb(50, 50));
+        assert!(is_close(rgb(70, 50), (255, 0, 0)), "top-right was {:?}", rgb(70, 50));
+        assert!(is_close(rgb(50, 70), (255, 255, 255)), "bottom-left was {:?}", rgb(50, 70));
+        assert!(is_close(rgb(70, 70), (0, 255, 0)), "bottom-right was {:?}", rgb(70, 70));
+    }
+
+    #[test]
+    fn test_image_pending_upload() {
+        use crate::models::FileData;
+        use crate::renderer_skia::render_to_png;
+
+        let mut element = create_test_element("img1", ElementType::Image, 0.0, 0.0, 40.0, 40.0);
+        element.file_id = Some("file1".to_string());
+        element.status = Some("pending".to_string());
+
+        // Files being uploaded have no dataURL yet
+        let file: FileData = serde_json::from_str(r#"{ "mimeType": "image/png", "id": "file1" }"#)
+            .expect("file without a dataURL should parse");
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files: HashMap::from([("file1".to_string(), file)]),
+        };
+        assert!(data.files["file1"].data_url.is_empty());
+
+        let svg = generate_svg(&data, None);
+        assert!(!svg.contains("<image"), "Images without data should be skipped in SVG");
+
+        let path = std::env::temp_dir().join(format!("excaliosa_image_pending_{}.png", std::process::id()));
+        render_to_png(&data, &path, None, 0, None).expect("default renderer");
+        let pixmap = tiny_skia::Pixmap::load_png(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let center = pixmap.pixel(pixmap.width() / 2, pixmap.height() / 2).unwrap().demultiply();
+        assert_eq!((center.red(), center.green(), center.blue()), (0xE7, 0xE7, 0xE7), "Placeholder should be drawn");
+    }
+
+    #[test]
+    fn test_image_rendering() {
+        use crate::models::FileData;
+
+        let mut element = create_test_element("img1", ElementType::Image, 10.0, 20.0, 40.0, 40.0);
+        element.file_id = Some("file1".to_string());
+        element.status = Some("saved".to_string());
+        element.scale = Some((-1.0, 1.0));
+
+        let mut files = HashMap::new();
+        files.insert(
+            "file1".to_string(),
+            FileData {
+                mime_type: "image/png".to_string(),
+                id: "file1".to_string(),
+                data_url: TEST_PNG_DATA_URL.to_string(),
+                created: None,
+                last_retrieved: None,
+            },
+        );
+
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files,
+        };
+
+        let svg = generate_svg(&data, None);
+        assert!(svg.contains("<image"), "Image element should be rendered");
+        assert!(svg.contains(TEST_PNG_DATA_URL), "Image should reference the file's data URL");
+        assert!(svg.contains("scale(-1 1)"), "Horizontal flip should be applied");
+    }
+
+    /// Pixels darker than mid-gray, as a mask
+    fn ink_mask(pixmap: &tiny_skia::Pixmap) -> Vec<bool> {
+        pixmap
+            .pixels()
+            .iter()
+            .map(|p| {
+                let c = p.demultiply();
+                (c.red() as u32 + c.green() as u32 + c.blue() as u32) < 3 * 128 && c.alpha() > 128
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rotated_shape_backends_agree() {
+        use crate::converter::convert_svg_to_png;
+        use crate::renderer_skia::render_to_png;
+
+        // A long, thin bar rotated by 30 degrees: a wrong direction or center is obvious
+        let mut element = create_test_element("bar", ElementType::Rectangle, 100.0, 100.0, 240.0, 40.0);
+        element.angle = std::f64::consts::PI / 6.0;
+        element.background_color = "#000000".to_string();
+        element.stroke_color = "#000000".to_string();
+        element.stroke_width = 2.0;
+
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+
+        let dir = std::env::temp_dir();
+        let skia_path = dir.join(format!("excaliosa_rotated_skia_{}.png", std::process::id()));
+        let legacy_path = dir.join(format!("excaliosa_rotated_legacy_{}.png", std::process::id()));
+
+        render_to_png(&data, &skia_path, None, 0, None).expect("default renderer");
+        let svg = generate_svg(&data, None);
+        convert_svg_to_png(&svg, &legacy_path, None, 0, None).expect("legacy renderer");
+
+        let skia = tiny_skia::Pixmap::load_png(&skia_path).unwrap();
+        let legacy = tiny_skia::Pixmap::load_png(&legacy_path).unwrap();
+        let _ = std::fs::remove_file(&skia_path);
+        let _ = std::fs::remove_file(&legacy_path);
+
+        assert_eq!((skia.width(), skia.height()), (legacy.width(), legacy.height()));
+
+        let a = ink_mask(&skia);
+        let b = ink_mask(&legacy);
+        let intersection = a.iter().zip(&b).filter(|(x, y)| **x && **y).count();
+        let union = a.iter().zip(&b).filter(|(x, y)| **x || **y).count();
+        assert!(union > 0, "Rotated shape should be drawn");
+        let iou = intersection as f64 / union as f64;
+        assert!(iou > 0.9, "Backends disagree on rotated shape (IoU {iou:.3})");
+    }
+
+    #[test]
+    fn test_opacity_composites_element_as_unit() {
+        use crate::renderer_skia::render_to_png;
+
+        // Stroke and fill share a color, so with per-element compositing their overlap
+        // must look the same as the fill alone
+        let mut element = create_test_element("overlay", ElementType::Rectangle, 100.0, 100.0, 200.0, 100.0);
+        element.background_color = "#000000".to_string();
+        element.stroke_width = 4.0;
+        element.opacity = 50.0;
+
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+
+        let path = std::env::temp_dir().join(format!("excaliosa_opacity_{}.png", std::process::id()));
+        render_to_png(&data, &path, None, 0, None).expect("render");
+        let pixmap = tiny_skia::Pixmap::load_png(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // Viewbox padding is 40px, so the left edge sits at x = 40
+        let gray = |x: u32, y: u32| pixmap.pixel(x, y).unwrap().demultiply().red() as i32;
+        let interior = gray(140, 90);
+        let edge = gray(41, 90);
+        assert!((interior - 128).abs() <= 8, "Fill should be half transparent, got {interior}");
+        assert!((edge - interior).abs() <= 8, "Stroke over fill should not double-darken ({edge} vs {interior})");
+    }
+
+    #[test]
+    fn test_translucent_elements_do_not_share_layer_contents() {
+        use crate::renderer_skia::render_to_pixmap;
+
+        // Both squares are drawn through the reused scratch layer; if it were not cleared,
+        // the first would be composited a second time with the other
+        let mut left = create_test_element("left", ElementType::Rectangle, 0.0, 0.0, 100.0, 100.0);
+        let mut right = create_test_element("right", ElementType::Rectangle, 200.0, 0.0, 100.0, 100.0);
+        for element in [&mut left, &mut right] {
+            element.background_color = "#000000".to_string();
+            element.opacity = 50.0;
+        }
+
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![left, right],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+
+        let pixmap = render_to_pixmap(&data, &crate::options::RenderOptions::default()).expect("render");
+        // Viewbox padding is 40px
+        let gray = |x: u32, y: u32| pixmap.pixel(x, y).unwrap().demultiply().red() as i32;
+        for (x, name) in [(90, "left"), (290, "right")] {
+            let value = gray(x, 90);
+            assert!((value - 128).abs() <= 8, "{name} square should be half transparent, got {value}");
+        }
+    }
+
+    fn create_frame_scene() -> ExcalidrawData {
+        let mut frame = create_test_element("frame1", ElementType::Frame, 100.0, 100.0, 200.0, 200.0);
+        frame.name = Some("Sketch <1>".to_string());
+
+        // Solid black square hanging off the frame's right edge
+        let mut child = create_test_element("child", ElementType::Rectangle, 200.0, 150.0, 200.0, 100.0);
+        child.background_color = "#000000".to_string();
+        child.frame_id = Some("frame1".to_string());
+
+        ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![child, frame],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_frame_svg_clips_children() {
+        let svg = generate_svg(&create_frame_scene(), None);
+
+        assert!(svg.contains(r#"<clipPath id="clip-frame-frame1">"#), "Frame clip path should be defined");
+        assert!(svg.contains(r#"<g clip-path="url(#clip-frame-frame1)">"#), "Child should be clipped");
+        assert!(svg.contains("Sketch &lt;1&gt;"), "Frame name should be rendered and escaped");
+        assert!(svg.contains(r##"stroke="#bbbbbb""##), "Frame outline should be drawn");
+    }
+
+    #[test]
+    fn test_frame_png_clips_children() {
+        use crate::renderer_skia::render_to_png;
+
+        let data = create_frame_scene();
+        let path = std::env::temp_dir().join(format!("excaliosa_frame_{}.png", std::process::id()));
+        render_to_png(&data, &path, None, 0, None).expect("render");
+        let pixmap = tiny_skia::Pixmap::load_png(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // Viewbox starts at (60, 60): the frame spans x 40..240, the child x 140..340
+        let red = |x: u32, y: u32| pixmap.pixel(x, y).unwrap().demultiply().red();
+        assert!(red(190, 140) < 64, "Child should be drawn inside the frame");
+        assert!(red(300, 140) > 192, "Child should be clipped outside the frame");
+    }
+
+    #[test]
+    fn test_single_frame_export() {
+        use crate::options::RenderOptions;
+        use crate::renderer::generate_svg_with_options;
+
+        let mut data = create_frame_scene();
+        let mut outside = create_test_element("outside", ElementType::Ellipse, 600.0, 600.0, 50.0, 50.0);
+        outside.stroke_color = "#123456".to_string();
+        data.elements.push(outside);
+
+        let options = RenderOptions {
+            frame: Some("Sketch <1>".to_string()),
+            ..RenderOptions::default()
+        };
+        let svg = generate_svg_with_options(&data, &options).expect("frame should be found by name");
+
+        assert!(svg.contains(r#"viewBox="100 100 200 200""#), "Viewbox should be the frame bounds");
+        assert!(svg.contains(r#"<g clip-path="url(#clip-frame-frame1)">"#), "Frame children should be rendered");
+        assert!(!svg.contains("#123456"), "Elements outside the frame should be skipped");
+        assert!(!svg.contains("Sketch &lt;1&gt;"), "Frame title should not be rendered");
+
+        let missing = RenderOptions {
+            frame: Some("nope".to_string()),
+            ..RenderOptions::default()
+        };
+        assert!(generate_svg_with_options(&data, &missing).is_err());
+    }
+
+    #[test]
+    fn test_region_export() {
+        use crate::error::ExcaliosaError;
+        use crate::models::ViewBox;
+        use crate::options::RenderOptions;
+        use crate::renderer::generate_svg_with_options;
+        use crate::renderer_pdf::render_to_pdf_bytes;
+        use crate::renderer_skia::render_to_pixmap;
+
+        let mut left = create_test_element("left", ElementType::Rectangle, 0.0, 0.0, 100.0, 100.0);
+        let mut right = create_test_element("right", ElementType::Rectangle, 200.0, 0.0, 100.0, 100.0);
+        left.background_color = "#ff0000".to_string();
+        right.background_color = "#000000".to_string();
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![left, right],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let options = RenderOptions {
+            region: Some(ViewBox { min_x: 150.0, min_y: -10.0, width: 200.0, height: 120.0 }),
+            ..RenderOptions::default()
+        };
+
+        let svg = generate_svg_with_options(&data, &options).unwrap();
+        assert!(svg.contains(r#"viewBox="150 -10 200 120""#), "Viewbox should be the region, without padding");
+
+        let pixmap = render_to_pixmap(&data, &options).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (200, 120));
+        let pixel = |x: u32, y: u32| pixmap.pixel(x, y).unwrap().demultiply();
+        // Scene (250, 50) is inside the right square, scene (160, 50) between the squares
+        assert_eq!(pixel(100, 60).red(), 0);
+        assert_eq!(pixel(10, 60).red(), 255);
+
+        // 200 x 120 scene pixels at 0.75pt each
+        let pdf = render_to_pdf_bytes(&data, &options).unwrap();
+        assert!(String::from_utf8_lossy(&pdf).contains("/MediaBox [0 0 150 90]"));
+
+        let empty = RenderOptions {
+            region: Some(ViewBox { min_x: 0.0, min_y: 0.0, width: 0.0, height: 10.0 }),
+            ..RenderOptions::default()
+        };
+        assert!(matches!(render_to_pixmap(&data, &empty), Err(ExcaliosaError::EmptyRegion { .. })));
+    }
+
+    #[test]
+    fn test_frame_file_stem() {
+        use crate::frame_utils::get_frame_file_stem;
+
+        let mut frame = create_test_element("f1", ElementType::Frame, 0.0, 0.0, 10.0, 10.0);
+        frame.name = Some("Login Flow #2".to_string());
+        assert_eq!(get_frame_file_stem(&frame), "login-flow-2");
+
+        frame.name = Some("***".to_string());
+        assert_eq!(get_frame_file_stem(&frame), "f1");
+
+        frame.name = None;
+        assert_eq!(get_frame_file_stem(&frame), "frame");
+    }
+
+    fn create_bound_text(container_id: &str, width: f64, height: f64) -> ExcalidrawElement {
+        let mut text = create_test_element("label", ElementType::Text, 0.0, 0.0, width, height);
+        text.text = Some("Hi".to_string());
+        text.font_size = Some(20.0);
+        text.text_align = Some(TextAlign::Center);
+        text.vertical_align = Some(VerticalAlign::Middle);
+        text.container_id = Some(container_id.to_string());
+        text
+    }
+
+    #[test]
+    fn test_bound_text_position() {
+        use crate::bound_text_utils::compute_bound_text_position;
+
+        let rect = create_test_element("box", ElementType::Rectangle, 100.0, 100.0, 200.0, 100.0);
+        let mut text = create_bound_text("box", 50.0, 25.0);
+        assert_eq!(compute_bound_text_position(&rect, &text, 50.0, 25.0), (175.0, 137.5));
+
+        text.vertical_align = Some(VerticalAlign::Top);
+        text.text_align = Some(TextAlign::Left);
+        assert_eq!(compute_bound_text_position(&rect, &text, 50.0, 25.0), (105.0, 105.0));
+
+        text.vertical_align = Some(VerticalAlign::Bottom);
+        text.text_align = Some(TextAlign::Right);
+        assert_eq!(compute_bound_text_position(&rect, &text, 50.0, 25.0), (245.0, 170.0));
+
+        // Diamonds inset the text box by a quarter of their size
+        let diamond = create_test_element("box", ElementType::Diamond, 0.0, 0.0, 200.0, 100.0);
+        text.vertical_align = Some(VerticalAlign::Top);
+        text.text_align = Some(TextAlign::Left);
+        assert_eq!(compute_bound_text_position(&diamond, &text, 50.0, 25.0), (55.0, 30.0));
+    }
+
+    #[test]
+    fn test_bound_text_centered_in_svg() {
+        let rect = create_test_element("box", ElementType::Rectangle, 100.0, 100.0, 200.0, 100.0);
+        let text = create_bound_text("box", 50.0, 25.0);
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![rect, text],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+
+        let svg = generate_svg(&data, None);
+        // Centered text is anchored at the middle of its box: 175 + 50 / 2
+        assert!(svg.contains(r#"<tspan x="200""#), "Bound text should be centered in its container");
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        use crate::text_layout::{wrap_text, TextMeasurer};
+
+        let measurer = TextMeasurer::for_font_family(Some(3), 20.0).expect("embedded font");
+        let char_width = measurer.line_width("a");
+        assert!(char_width > 0.0);
+
+        // Monospace: room for exactly ten characters per line
+        let max_width = char_width * 10.0 + 0.5;
+        assert_eq!(wrap_text("hello world again", &measurer, max_width), "hello\nworld\nagain");
+        assert_eq!(wrap_text("hi there friend", &measurer, max_width), "hi there\nfriend");
+        assert_eq!(wrap_text("abcdefghijklmnop", &measurer, max_width), "abcdefghij\nklmnop");
+        assert_eq!(wrap_text("short\nlines", &measurer, max_width), "short\nlines");
+    }
+
+    #[test]
+    fn test_bound_text_wraps_to_container() {
+        use crate::bound_text_utils::index_elements;
+        use crate::text_layout::layout_text_element;
+
+        let rect = create_test_element("box", ElementType::Rectangle, 0.0, 0.0, 120.0, 200.0);
+        let mut text = create_bound_text("box", 400.0, 25.0);
+        text.font_family = Some(2);
+        text.original_text = Some("the quick brown fox jumps over the lazy dog".to_string());
+        text.text = text.original_text.clone();
+
+        let elements = vec![rect, text];
+        let elements_by_id = index_elements(&elements);
+        let laid_out = layout_text_element(&elements[1], &elements_by_id, &FontSet::default());
+
+        let lines: Vec<&str> = laid_out.text.as_deref().unwrap().split('\n').collect();
+        assert!(lines.len() > 1, "Text should wrap inside the container");
+        assert!(laid_out.width <= 110.0, "Wrapped lines should fit the container's text box");
+        assert_eq!(laid_out.height, lines.len() as f64 * 25.0);
+    }
+
+    fn create_labeled_arrow_scene(points: Vec<(f64, f64)>) -> ExcalidrawData {
+        use crate::models::BoundElement;
+
+        let mut arrow = create_test_element("arrow1", ElementType::Arrow, 100.0, 100.0, 300.0, 0.0);
+        arrow.stroke_width = 2.0;
+        arrow.points = Some(points);
+        arrow.bound_elements = Some(vec![BoundElement {
+            id: "label".to_string(),
+            element_type: ElementType::Text,
+        }]);
+
+        // Stale position: the label must be placed from the arrow's path
+        let mut label = create_bound_text("arrow1", 30.0, 25.0);
+        label.x = 200.0;
+        label.y = 90.0;
+
+        ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![arrow, label],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_arrow_label_at_path_midpoint() {
+        use crate::bound_text_utils::{get_arrow_label_midpoint, index_elements};
+        use crate::text_layout::layout_text_element;
+
+        // Sharp elbow-shaped path: halfway along its 200px length is the corner
+        let data = create_labeled_arrow_scene(vec![(0.0, 0.0), (100.0, 0.0), (100.0, 100.0)]);
+        assert_eq!(get_arrow_label_midpoint(&data.elements[0]), Some((200.0, 100.0)));
+
+        let elements_by_id = index_elements(&data.elements);
+        let label = layout_text_element(&data.elements[1], &elements_by_id, &FontSet::default());
+        assert!((label.x + label.width / 2.0 - 200.0).abs() < 1e-9);
+        assert!((label.y + label.height / 2.0 - 100.0).abs() < 1e-9);
+
+        let svg = generate_svg(&data, None);
+        assert!(svg.contains(r#"<mask id="mask-arrow1""#), "Arrow should be masked behind its label");
+        assert!(svg.contains(r#"<g mask="url(#mask-arrow1)">"#));
+    }
+
+    #[test]
+    fn test_arrow_stroke_masked_behind_label_png() {
+        use crate::bound_text_utils::index_elements;
+        use crate::renderer_skia::render_to_png;
+        use crate::text_layout::get_arrow_label_mask_box;
+
+        let data = create_labeled_arrow_scene(vec![(0.0, 0.0), (300.0, 0.0)]);
+        let elements_by_id = index_elements(&data.elements);
+        let (mask_x, _, _, _) = get_arrow_label_mask_box(&data.elements[0], &elements_by_id, &FontSet::default()).unwrap();
+
+        let path = std::env::temp_dir().join(format!("excaliosa_arrow_label_{}.png", std::process::id()));
+        render_to_png(&data, &path, None, 0, None).expect("render");
+        let pixmap = tiny_skia::Pixmap::load_png(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // Viewbox starts at (60, 50); the arrow runs along scene y = 100
+        let red = |scene_x: f64| pixmap.pixel((scene_x - 60.0) as u32, 50).unwrap().demultiply().red();
+        assert!(red(150.0) < 128, "Arrow stroke should be drawn away from the label");
+        assert!(red(mask_x + 2.0) > 200, "Arrow stroke should be hidden behind the label");
+    }
+
+    #[test]
+    fn test_dark_mode_filter() {
+        use crate::color_utils::{apply_dark_mode_filter, get_themed_color};
+        use crate::options::Theme;
+
+        // Excalidraw's dark canvas: white becomes #121212, black a light gray
+        assert_eq!(apply_dark_mode_filter((255, 255, 255, 255)), (18, 18, 18, 255));
+        assert_eq!(apply_dark_mode_filter((0, 0, 0, 128)), (237, 237, 237, 128));
+        assert_eq!(get_themed_color("#ffffff", Theme::Dark), "#121212");
+        assert_eq!(get_themed_color("#ffffff", Theme::Light), "#ffffff");
+        assert_eq!(get_themed_color("transparent", Theme::Dark), "transparent");
+    }
+
+    #[test]
+    fn test_dark_theme_from_app_state() {
+        use crate::options::{RenderOptions, Theme};
+        use crate::renderer::generate_svg_with_options;
+
+        let mut element = create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 100.0);
+        element.stroke_color = "#1e1e1e".to_string();
+        let mut data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        data.app_state.insert("theme".to_string(), serde_json::json!("dark"));
+
+        assert_eq!(RenderOptions::default().resolve_theme(&data), Theme::Dark);
+        let dark_svg = generate_svg(&data, None);
+        assert!(!dark_svg.contains("#1e1e1e"), "Stroke color should be filtered in dark mode");
+
+        // An explicit option overrides appState
+        let light = RenderOptions {
+            theme: Some(Theme::Light),
+            ..RenderOptions::default()
+        };
+        let light_svg = generate_svg_with_options(&data, &light).unwrap();
+        assert!(light_svg.contains("#1e1e1e"));
+    }
+
+    #[test]
+    fn test_outline_arrowhead_filled_with_dark_canvas() {
+        use crate::options::RenderOptions;
+        use crate::renderer::generate_svg_with_options;
+        use crate::renderer_pdf::render_to_pdf_bytes;
+        use crate::renderer_skia::{exca_arrowhead_points, render_to_pixmap};
+
+        let mut arrow = create_test_element("arrow1", ElementType::Arrow, 0.0, 0.0, 200.0, 0.0);
+        arrow.stroke_width = 4.0;
+        arrow.points = Some(vec![(0.0, 0.0), (200.0, 0.0)]);
+        arrow.end_arrowhead = Some(Arrowhead::TriangleOutline);
+        let mut data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![arrow],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        data.app_state.insert("theme".to_string(), serde_json::json!("dark"));
+        let options = RenderOptions::default();
+
+        // White, the default canvas, is drawn as #121212 in the dark theme
+        let svg = generate_svg_with_options(&data, &options).unwrap();
+        assert!(svg.contains("fill=\"#121212\" stroke="), "SVG outline head should be filled with the canvas");
+
+        // Sample the middle of the head; the viewbox padding puts the arrow at (40, 40)
+        let pixmap = render_to_pixmap(&data, &options).unwrap();
+        let head = exca_arrowhead_points(&[(0.0, 0.0), (200.0, 0.0)], 40.0, 40.0, 4.0, Arrowhead::TriangleOutline, "end").unwrap();
+        let (cx, cy) = ((head[0] + head[2] + head[4]) / 3.0, (head[1] + head[3] + head[5]) / 3.0);
+        let pixel = pixmap.pixel(cx as u32, cy as u32).unwrap().demultiply();
+        assert_eq!((pixel.red(), pixel.green(), pixel.blue()), (0x12, 0x12, 0x12));
+
+        let pdf = render_to_pdf_bytes(&data, &options).unwrap();
+        assert!(!String::from_utf8_lossy(&pdf).contains("1 1 1 rg"), "PDF outline head should not be white");
+    }
+
+    #[test]
+    fn test_export_app_state_defaults() {
+        use crate::options::RenderOptions;
+        use crate::renderer_skia::render_to_pixmap;
+
+        let element = create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 50.0);
+        let mut data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        data.app_state.insert("viewBackgroundColor".to_string(), serde_json::json!("#ffc9c9"));
+        data.app_state.insert("exportScale".to_string(), serde_json::json!(2));
+        data.app_state.insert("exportPadding".to_string(), serde_json::json!(10));
+
+        let defaults = RenderOptions::default();
+        assert_eq!(defaults.resolve_background(&data), Some((255, 201, 201, 255)));
+        assert_eq!(defaults.resolve_scale(&data), 2.0);
+        assert_eq!(defaults.resolve_padding(&data), 10.0);
+
+        // (100 + 2 * 10) x (50 + 2 * 10) at 2x
+        let pixmap = render_to_pixmap(&data, &defaults).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (240, 140));
+        let corner = pixmap.pixel(0, 0).unwrap().demultiply();
+        assert_eq!((corner.red(), corner.green(), corner.blue()), (255, 201, 201));
+
+        data.app_state.insert("exportBackground".to_string(), serde_json::json!(false));
+        assert_eq!(defaults.resolve_background(&data), Some((0, 0, 0, 0)));
+        let svg = generate_svg(&data, None);
+        assert!(!svg.contains("#ffc9c9"), "exportBackground=false should drop the background");
+    }
+
+    #[test]
+    fn test_export_options_override_app_state() {
+        use crate::options::RenderOptions;
+        use crate::renderer_skia::render_to_pixmap;
+
+        let element = create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 50.0);
+        let mut data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        data.app_state.insert("exportBackground".to_string(), serde_json::json!(false));
+        data.app_state.insert("exportScale".to_string(), serde_json::json!(3));
+        data.app_state.insert("exportPadding".to_string(), serde_json::json!(10));
+
+        let options = RenderOptions {
+            background: Some((0, 0, 255, 255)),
+            scale: Some(1.0),
+            padding: Some(0.0),
+            ..RenderOptions::default()
+        };
+        assert_eq!(options.resolve_background(&data), Some((0, 0, 255, 255)));
+        let pixmap = render_to_pixmap(&data, &options).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (100, 50));
+
+        // DPI wins over both the option and appState scale
+        let dpi_options = RenderOptions {
+            dpi: Some(48),
+            ..options
+        };
+        assert_eq!(dpi_options.resolve_scale(&data), 0.5);
+    }
+
+    #[test]
+    fn test_legacy_fractional_export_scale() {
+        use crate::converter::convert_scene_to_pixmap;
+        use crate::options::RenderOptions;
+        use crate::renderer_skia::render_to_pixmap;
+
+        let element = create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 50.0);
+        let mut data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        data.app_state.insert("exportScale".to_string(), serde_json::json!(1.55));
+        data.app_state.insert("exportPadding".to_string(), serde_json::json!(10));
+
+        // A fractional scale must not be rounded to a whole DPI on the resvg path
+        let options = RenderOptions::default();
+        let legacy = convert_scene_to_pixmap(&data, &options).unwrap();
+
+        let pixmap = render_to_pixmap(&data, &options).unwrap();
+        assert_eq!((legacy.width(), legacy.height()), (pixmap.width(), pixmap.height()));
+    }
+
+    #[test]
+    fn test_pdf_export_with_link() {
+        use crate::options::RenderOptions;
+        use crate::renderer_pdf::render_to_pdf_bytes;
+
+        let mut rect = create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 50.0);
+        rect.link = Some("https://excalidraw.com".to_string());
+        let mut text = create_test_element("text1", ElementType::Text, 10.0, 80.0, 60.0, 25.0);
+        text.text = Some("Hello".to_string());
+        text.font_size = Some(20.0);
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![rect, text],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+
+        let bytes = render_to_pdf_bytes(&data, &RenderOptions::default()).unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+        assert!(pdf.starts_with("%PDF-"));
+        assert!(pdf.contains("/Subtype /Link"));
+        assert!(pdf.contains("(https://excalidraw.com)"));
+        // Text is outlined, so no fonts are needed
+        assert!(!pdf.contains("/Font"));
+        // 180 x 185 scene pixels at 0.75pt each
+        assert!(pdf.contains("/MediaBox [0 0 135 138.75]"));
+    }
+
+    #[test]
+    fn test_pdf_one_page_per_frame() {
+        use crate::options::{FrameOrder, RenderOptions};
+        use crate::renderer_pdf::render_to_pdf_bytes;
+
+        let mut wide = create_test_element("wide", ElementType::Frame, 0.0, 0.0, 400.0, 200.0);
+        wide.name = Some("B slide".to_string());
+        wide.index = "a0".to_string();
+        let mut tall = create_test_element("tall", ElementType::Frame, 500.0, 0.0, 200.0, 400.0);
+        tall.name = Some("A slide".to_string());
+        tall.index = "a1".to_string();
+        let mut data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            // Scene order differs from index order
+            elements: vec![tall, wide],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+
+        let media_boxes = |bytes: &[u8]| -> Vec<String> {
+            String::from_utf8_lossy(bytes)
+                .split("/MediaBox [")
+                .skip(1)
+                .map(|rest| rest[..rest.find(']').unwrap()].to_string())
+                .collect()
+        };
+
+        // Pages are sized to their frames: 400x200px -> 300x150pt, 200x400px -> 150x300pt
+        let by_index = RenderOptions {
+            frame_pages: Some(FrameOrder::Index),
+            ..RenderOptions::default()
+        };
+        let bytes = render_to_pdf_bytes(&data, &by_index).unwrap();
+        assert_eq!(media_boxes(&bytes), vec!["0 0 300 150", "0 0 150 300"]);
+
+        let by_name = RenderOptions {
+            frame_pages: Some(FrameOrder::Name),
+            ..RenderOptions::default()
+        };
+        let bytes = render_to_pdf_bytes(&data, &by_name).unwrap();
+        assert_eq!(media_boxes(&bytes), vec!["0 0 150 300", "0 0 300 150"]);
+
+        // Without frames the whole scene is a single page
+        data.elements = vec![create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 100.0)];
+        let bytes = render_to_pdf_bytes(&data, &by_index).unwrap();
+        assert_eq!(media_boxes(&bytes).len(), 1);
+        assert!(String::from_utf8_lossy(&bytes).contains("/Count 1"));
+    }
+
+    #[test]
+    fn test_raster_encoders() {
+        use crate::encoder::{encode_pixmap, RasterFormat};
+        use crate::options::RenderOptions;
+        use tiny_skia::Pixmap;
+
+        assert_eq!(RasterFormat::from_extension("JPEG"), Some(RasterFormat::Jpeg));
+        // Without lossy WebP, .webp still resolves to an encodable format
+        let webp = if RasterFormat::LOSSY_WEBP { RasterFormat::WebP } else { RasterFormat::WebPLossless };
+        assert_eq!(RasterFormat::from_extension("webp"), Some(webp));
+        assert_eq!(RasterFormat::from_path(std::path::Path::new("out.jpg")), Some(RasterFormat::Jpeg));
+        assert_eq!(RasterFormat::from_extension("svg"), None);
+
+        // A fully transparent image flattens onto the matte color in JPEG
+        let pixmap = Pixmap::new(16, 16).unwrap();
+        let jpeg = encode_pixmap(&pixmap, RasterFormat::Jpeg, 90, (255, 0, 0), None).unwrap();
+        let decoded = image::load_from_memory(&jpeg).unwrap().to_rgb8();
+        let pixel = decoded.get_pixel(8, 8);
+        assert!(pixel[0] > 240 && pixel[1] < 16 && pixel[2] < 16, "unexpected JPEG pixel {pixel:?}");
+
+        let lossy = encode_pixmap(&pixmap, RasterFormat::WebP, 75, (255, 255, 255), None);
+        if RasterFormat::LOSSY_WEBP {
+            let lossy = lossy.unwrap();
+            assert_eq!(&lossy[0..4], b"RIFF");
+            assert_eq!(&lossy[8..12], b"WEBP");
+        } else {
+            assert!(lossy.is_err(), "lossy WebP needs the webp-lossy feature");
+        }
+        let lossless = encode_pixmap(&pixmap, RasterFormat::WebPLossless, 75, (255, 255, 255), None).unwrap();
+        assert_eq!(&lossless[0..4], b"RIFF");
+        assert!(lossless.windows(4).any(|chunk| chunk == b"VP8L"));
+        let decoded = image::load_from_memory(&lossless).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(8, 8)[3], 0, "lossless WebP should keep transparency");
+
+        let png = encode_pixmap(&pixmap, RasterFormat::Png, 75, (255, 255, 255), None).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+
+        // The matte is the background, or white when the background is transparent
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let options = RenderOptions {
+            background: Some((0, 0, 0, 0)),
+            ..RenderOptions::default()
+        };
+        assert_eq!(options.resolve_matte(&data), (255, 255, 255));
+        let options = RenderOptions {
+            background: Some((18, 52, 86, 255)),
+            ..RenderOptions::default()
+        };
+        assert_eq!(options.resolve_matte(&data), (18, 52, 86));
+    }
+
+    #[test]
+    fn test_embed_scene_in_png_and_svg() {
+        use crate::embed_utils::{EncodedData, EXCALIDRAW_MIME, SVG_EXPORT_TAG};
+        use crate::options::RenderOptions;
+        use crate::renderer::generate_svg_with_options;
+        use crate::utils::encode_png_with_quality;
+        use base64::Engine;
+
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 50.0)],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let decode_envelope = |envelope: &str| -> ExcalidrawData {
+            let encoded: EncodedData = serde_json::from_str(envelope).unwrap();
+            assert_eq!((encoded.version.as_str(), encoded.encoding.as_str()), ("1", "bstring"));
+            assert!(encoded.compressed);
+            let bytes: Vec<u8> = encoded.encoded.chars().map(|ch| ch as u8).collect();
+            let json = miniz_oxide::inflate::decompress_to_vec_zlib(&bytes).unwrap();
+            serde_json::from_slice(&json).unwrap()
+        };
+
+        // PNG: tEXt chunk keyed by Excalidraw's MIME type
+        let pixmap = tiny_skia::Pixmap::new(4, 4).unwrap();
+        let png_bytes = encode_png_with_quality(&pixmap, 75, Some(&data)).unwrap();
+        let reader = png::Decoder::new(std::io::Cursor::new(png_bytes)).read_info().unwrap();
+        let chunk = reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == EXCALIDRAW_MIME)
+            .expect("scene tEXt chunk");
+        let restored = decode_envelope(&chunk.text);
+        assert_eq!(restored.elements.len(), 1);
+        assert_eq!(restored.elements[0].id, "rect1");
+
+        // SVG: base64 payload inside <metadata>
+        let options = RenderOptions {
+            embed_scene: true,
+            ..RenderOptions::default()
+        };
+        let svg = generate_svg_with_options(&data, &options).unwrap();
+        assert!(svg.contains(SVG_EXPORT_TAG));
+        assert!(svg.contains("<!-- payload-type:application/vnd.excalidraw+json -->"));
+        let start = svg.find("<!-- payload-start -->").unwrap() + "<!-- payload-start -->".len();
+        let end = svg.find("<!-- payload-end -->").unwrap();
+        let latin1 = base64::engine::general_purpose::STANDARD.decode(&svg[start..end]).unwrap();
+        let envelope: String = latin1.iter().map(|&b| b as char).collect();
+        let restored = decode_envelope(&envelope);
+        assert_eq!(restored.elements[0].element_type, ElementType::Rectangle);
+
+        let plain = generate_svg_with_options(&data, &RenderOptions::default()).unwrap();
+        assert!(!plain.contains("<metadata>"));
+    }
+
+    #[test]
+    fn test_embedded_scene_is_lossless() {
+        use crate::embed_utils::{get_exported_scene, serialize_scene};
+        use crate::loader::load_scene;
+        use crate::options::RenderOptions;
+        use crate::renderer::generate_svg_with_options;
+
+        let json = r##"{
+            "type": "excalidraw",
+            "version": 2,
+            "elements": [
+                { "type": "frame", "id": "frame1", "x": 0, "y": 0, "width": 200, "height": 100, "name": "Flow" },
+                { "type": "rectangle", "id": "inside", "x": 10, "y": 10, "width": 50, "height": 50, "frameId": "frame1",
+                  "roundness": { "type": 3 }, "customData": { "owner": "ops" } },
+                { "type": "ellipse", "id": "outside", "x": 300, "y": 0, "width": 50, "height": 50 }
+            ]
+        }"##;
+        let mut data = load_scene(json.as_bytes()).unwrap();
+        data.elements.push(create_test_element("mystery", ElementType::Unknown, 0.0, 0.0, 10.0, 10.0));
+
+        // Absent fields stay absent, unknown keys survive, unknown types are left out
+        let scene = serialize_scene(&get_exported_scene(&data, None).unwrap()).unwrap();
+        assert!(!scene.contains("null"), "absent fields should not be written: {scene}");
+        assert!(scene.contains(r#""customData":{"owner":"ops"}"#));
+        assert!(scene.contains(r#""roundness":{"type":3}"#));
+        assert!(!scene.contains("unknown"));
+        let reloaded = load_scene(scene.as_bytes()).unwrap();
+        assert_eq!(reloaded.elements.len(), 3);
+        assert_eq!(reloaded.elements[1].extra["customData"]["owner"], "ops");
+
+        // A frame export embeds the frame and its children only
+        let options = RenderOptions {
+            embed_scene: true,
+            frame: Some("Flow".to_string()),
+            ..RenderOptions::default()
+        };
+        let svg = generate_svg_with_options(&data, &options).unwrap();
+        let embedded = load_scene(svg.as_bytes()).unwrap();
+        let ids: Vec<&str> = embedded.elements.iter().map(|el| el.id.as_str()).collect();
+        assert_eq!(ids, ["frame1", "inside"]);
+    }
+
+    #[test]
+    fn test_load_scene_from_embedded_exports() {
+        use crate::loader::load_scene;
+        use crate::options::RenderOptions;
+        use crate::renderer::generate_svg_with_options;
+        use crate::utils::encode_png_with_quality;
+        use base64::Engine;
+
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![create_test_element("rect1", ElementType::Rectangle, 10.0, 20.0, 100.0, 50.0)],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+
+        let pixmap = tiny_skia::Pixmap::new(4, 4).unwrap();
+        let png_bytes = encode_png_with_quality(&pixmap, 75, Some(&data)).unwrap();
+        let from_png = load_scene(&png_bytes).unwrap();
+        assert_eq!(from_png.elements[0].id, "rect1");
+        assert_eq!(from_png.elements[0].x, 10.0);
+
+        let options = RenderOptions {
+            embed_scene: true,
+            ..RenderOptions::default()
+        };
+        let svg = generate_svg_with_options(&data, &options).unwrap();
+        let from_svg = load_scene(svg.as_bytes()).unwrap();
+        assert_eq!(from_svg.elements[0].id, "rect1");
+
+        // Payload version 1: base64'd UTF-8 scene JSON without the envelope
+        let json = serde_json::to_string(&data).unwrap();
+        let legacy = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\"><!-- payload-type:application/vnd.excalidraw+json --><!-- payload-version:1 --><!-- payload-start -->{}<!-- payload-end --></svg>",
+            base64::engine::general_purpose::STANDARD.encode(json.as_bytes())
+        );
+        assert_eq!(load_scene(legacy.as_bytes()).unwrap().elements.len(), 1);
+
+        // Plain JSON still loads, and images without a scene are rejected
+        assert_eq!(load_scene(json.as_bytes()).unwrap().elements.len(), 1);
+        let bare_png = encode_png_with_quality(&pixmap, 75, None).unwrap();
+        assert!(load_scene(&bare_png).is_err());
+        let bare_svg = generate_svg_with_options(&data, &RenderOptions::default()).unwrap();
+        assert!(load_scene(bare_svg.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_load_obsidian_markdown() {
+        use crate::loader::load_scene;
+        use crate::lz_string::decompress_from_base64;
+
+        assert_eq!(decompress_from_base64("BIUwNmD2A0AEDukBOYAmQ===").as_deref(), Some("Hello, world"));
+        assert_eq!(decompress_from_base64("IY18ZQ==").as_deref(), Some("aaaaaaaaaaaaaaaaaaaaaaaaaaaa"));
+
+        // The plugin wraps the compressed drawing over several lines
+        let compressed = "N4IgLgngDgpiBcIYA8DGBDANgSwCYCd0B3EAGhADcZ8BnbAewDsEAmcm+gV31TkQAswYKDXgB6MQHNsYfpwBGAOlT0AtmIBeNCtlQbs6RmPry6uA4wC0KDDgLFLUTJ2nNyMTDFUxGYGggBtUDwEcGQ\n\nwAEYycGg+cBQwaOQEAAZyCFTyIjxZBAiUtJB+GGxJQVYAVnJDSU9MkBowfHoAaxgAYXpMenxQgGIImEHB6Pl0VBbJZs5GXE7u3sQmwxoodHwfRPIAM2xMTABlSDrEDjtoxua2gHUc/lZ2JtaYI4gThq6Q8mmyxhgafzwCLkehrVAyDJAgrkKZcKAASVwgICAF0doRvIiEIxOPtyNhZihQugUtFprM/gDsbjMOwYDBcHlyFRaAxGAA5Ji8JkgbA0AAiHhgYAZCG2WBoMHI8i4swAop5vL5ATi8SBOFBcOgRYygeQcIwWtS1d0JqL4OLMJLyCLwqFGlg4DsmGADtgNHwWIVti6AGLoVR7SHA+LhACCOEkzEQnm2W0o1DAuiwEdK0fAoOiKl86AJ1Cx8FVtJAPVKBKwABUEvawI7ogaYAAJEplRJAxQsCoAXzRIHQUCgR21fFAOhgRAAQuNJuS5l0ev1tkvlyAuzs9v8EMAu12gA=";
+        let document = format!(
+            "---\n\nexcalidraw-plugin: parsed\ntags: [excalidraw]\n\n---\n==⚠  Switch to EXCALIDRAW VIEW in the MORE OPTIONS menu of this document. ⚠==\n\n\n# Excalidraw Data\n## Text Elements\nHello\nworld ^txt1\n\n%%\n## Drawing\n```compressed-json\n{compressed}\n```\n%%"
+        );
+        let data = load_scene(document.as_bytes()).unwrap();
+        assert_eq!(data.elements.len(), 1);
+        let text = &data.elements[0];
+        assert_eq!(text.text.as_deref(), Some("Hello\nworld"));
+        assert_eq!(text.original_text.as_deref(), Some("Hello\nworld"));
+
+        // Tags and markdown headings inside a text element do not end the section
+        let document = format!(
+            "# Excalidraw Data\n## Text Elements\n#todo ^other\n\n# Title\nHello ^txt1\n\n## Element Links\ntxt1: [[Note]]\n\n%%\n## Drawing\n```compressed-json\n{compressed}\n```\n%%"
+        );
+        let data = load_scene(document.as_bytes()).unwrap();
+        assert_eq!(data.elements[0].text.as_deref(), Some("# Title\nHello"));
+
+        // Plain json drawing blocks, without a Text Elements entry for the element
+        let json = decompress_from_base64(compressed).unwrap();
+        let document = format!("# Drawing\n```json\n{json}\n```\n");
+        let data = load_scene(document.as_bytes()).unwrap();
+        assert_eq!(data.elements[0].text.as_deref(), Some("stale"));
+    }
+
+    #[test]
+    fn test_restore_lenient_scene() {
+        use crate::loader::load_scene;
+
+        // Hand-written scene: most fields missing, legacy fields, and an unknown element type
+        let json = r##"{
+            "elements": [
+                { "type": "rectangle", "id": "r1", "x": 10, "y": 20, "width": 100, "height": 50, "strokeSharpness": "round" },
+                { "type": "sticker", "id": "s1" },
+                { "type": "text", "id": "t1", "font": "36px Virgil", "text": "Hi\r\nthere", "boundElementIds": ["a1"] },
+                { "type": "arrow", "id": "a1", "width": 80, "height": 0 },
+                { "type": "draw", "id": "d1", "points": [[0, 0], [5, 5]], "strokeSharpness": "round" }
+            ]
+        }"##;
+        let data = load_scene(json.as_bytes()).unwrap();
+        assert_eq!(data.data_type, "excalidraw");
+        let ids: Vec<&str> = data.elements.iter().map(|el| el.id.as_str()).collect();
+        assert_eq!(ids, ["r1", "t1", "a1", "d1"], "unknown types are dropped");
+
+        let rect = &data.elements[0];
+        assert_eq!(rect.roundness.as_ref().map(|r| r.roundness_type), Some(3));
+        assert_eq!((rect.stroke_style, rect.roughness, rect.opacity), (StrokeStyle::Solid, 1.0, 100.0));
+        assert_eq!(rect.stroke_color, "#1e1e1e");
+
+        let text = &data.elements[1];
+        assert_eq!((text.font_size, text.font_family), (Some(36.0), Some(1)));
+        assert_eq!(text.text.as_deref(), Some("Hi\nthere"));
+        assert_eq!(text.original_text.as_deref(), Some("Hi\nthere"));
+        let bound = text.bound_elements.as_ref().unwrap();
+        assert_eq!((bound[0].id.as_str(), bound[0].element_type), ("a1", ElementType::Arrow));
+
+        let arrow = &data.elements[2];
+        assert_eq!(arrow.points.as_deref(), Some(&[(0.0, 0.0), (80.0, 0.0)][..]));
+        assert_eq!(arrow.end_arrowhead, Some(Arrowhead::Arrow));
+
+        let line = &data.elements[3];
+        assert_eq!(line.element_type, ElementType::Line);
+        assert_eq!(line.roundness.as_ref().map(|r| r.roundness_type), Some(2));
+
+        // Missing indices are regenerated in order
+        let indices: Vec<&str> = data.elements.iter().map(|el| el.index.as_str()).collect();
+        assert_eq!(indices, ["a0", "a1", "a2", "a3"]);
+    }
+
+    #[test]
+    fn test_typed_element_fields() {
+        use crate::models::ElementKind;
+
+        let parse = |json: &str| -> (ElementType, FillStyle, Arrowhead, TextAlign) {
+            let value: serde_json::Value = serde_json::from_str(json).unwrap();
+            (
+                serde_json::from_value(value["type"].clone()).unwrap(),
+                serde_json::from_value(value["fillStyle"].clone()).unwrap(),
+                serde_json::from_value(value["endArrowhead"].clone()).unwrap(),
+                serde_json::from_value(value["textAlign"].clone()).unwrap(),
+            )
+        };
+        let known = r#"{ "type": "magicframe", "fillStyle": "cross-hatch", "endArrowhead": "crowfoot_one_or_many", "textAlign": "center" }"#;
+        assert_eq!(
+            parse(known),
+            (ElementType::Magicframe, FillStyle::CrossHatch, Arrowhead::CrowfootOneOrMany, TextAlign::Center)
+        );
+        let unknown = r#"{ "type": "sticker", "fillStyle": "dots", "endArrowhead": "star", "textAlign": "justify" }"#;
+        assert_eq!(
+            parse(unknown),
+            (ElementType::Unknown, FillStyle::Unknown, Arrowhead::Unknown, TextAlign::Unknown)
+        );
+        assert_eq!(serde_json::to_value(FillStyle::CrossHatch).unwrap(), "cross-hatch");
+        assert_eq!(ElementType::Magicframe.to_string(), "magicframe");
+
+        // The per-type view carries the type's own data, with the legacy arrowhead field as fallback
+        let mut arrow = create_test_element("a1", ElementType::Arrow, 0.0, 0.0, 50.0, 0.0);
+        arrow.points = Some(vec![(0.0, 0.0), (50.0, 0.0)]);
+        arrow.end_arrow_type = Some(Arrowhead::Triangle);
+        match arrow.kind() {
+            ElementKind::Arrow { points, start_arrowhead, end_arrowhead, elbowed } => {
+                assert_eq!(points.len(), 2);
+                assert_eq!((start_arrowhead, end_arrowhead, elbowed), (None, Some(Arrowhead::Triangle), false));
+            }
+            kind => panic!("unexpected kind {kind:?}"),
+        }
+
+        // The renderers draw from the view: the legacy field's arrowhead is drawn, and lines
+        // never get arrowheads
+        let scene = |element: ExcalidrawElement| ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![element],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let arrowheads = |element: ExcalidrawElement| generate_svg(&scene(element), None).matches("<polygon").count();
+        let mut line = arrow.clone();
+        line.element_type = ElementType::Line;
+        assert!(arrowheads(arrow.clone()) > arrowheads(line));
+
+        let mut text = create_test_element("t1", ElementType::Text, 0.0, 0.0, 50.0, 20.0);
+        text.text = Some("Hello".to_string());
+        match text.kind() {
+            ElementKind::Text { text, font_size, text_align, vertical_align, .. } => {
+                assert_eq!((text, font_size), ("Hello", 20.0));
+                assert_eq!((text_align, vertical_align), (TextAlign::Left, VerticalAlign::Top));
+            }
+            kind => panic!("unexpected kind {kind:?}"),
+        }
+
+        let mut image = create_test_element("i1", ElementType::Image, 0.0, 0.0, 50.0, 50.0);
+        image.file_id = Some("file1".to_string());
+        assert!(matches!(image.kind(), ElementKind::Image { file_id: Some("file1"), crop: None }));
+    }
+
+    #[test]
+    fn test_render_to_memory() {
+        use crate::converter::convert_svg_to_png_bytes;
+        use crate::encoder::RasterFormat;
+        use crate::options::RenderOptions;
+        use crate::renderer_pdf::render_to_pdf_writer;
+        use crate::renderer_skia::{render_to_pixmap, render_to_png_bytes, render_to_raster_writer, render_to_rgba};
+
+        let mut rect = create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 50.0);
+        rect.background_color = "#ff0000".to_string();
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![rect],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let options = RenderOptions::default();
+
+        let pixmap = render_to_pixmap(&data, &options).unwrap();
+        let (width, height, rgba) = render_to_rgba(&data, &options).unwrap();
+        assert_eq!((width, height), (pixmap.width(), pixmap.height()));
+        assert_eq!(rgba.len(), (width * height * 4) as usize);
+        // The padding is the opaque white background
+        assert_eq!(&rgba[0..4], &[255, 255, 255, 255]);
+
+        let png = render_to_png_bytes(&data, &options).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (width, height));
+
+        let mut written = Vec::new();
+        render_to_raster_writer(&data, &mut written, RasterFormat::Png, &options).unwrap();
+        assert_eq!(written, png);
+
+        let mut pdf = Vec::new();
+        render_to_pdf_writer(&data, &mut pdf, &options).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+
+        let svg = generate_svg(&data, None);
+        let svg_png = convert_svg_to_png_bytes(&svg, None, 75, None).unwrap();
+        assert!(svg_png.starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn test_renderer_backends_share_options() {
+        use crate::backend::{PdfRenderer, Renderer, ResvgRenderer, SkiaRenderer, SvgRenderer};
+        use crate::encoder::RasterFormat;
+        use crate::options::{RenderOptions, Theme};
+        use crate::renderer::generate_svg_with_options;
+
+        fn export<R: Renderer>(renderer: &R, data: &ExcalidrawData, options: &RenderOptions) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            renderer.render_to_writer(data, &mut bytes, options).unwrap();
+            bytes
+        }
+
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![create_test_element("rect1", ElementType::Rectangle, 0.0, 0.0, 100.0, 50.0)],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let options = RenderOptions {
+            theme: Some(Theme::Dark),
+            scale: Some(2.0),
+            padding: Some(10.0),
+            ..RenderOptions::default()
+        };
+
+        let svg = export(&SvgRenderer, &data, &options);
+        assert_eq!(svg, generate_svg_with_options(&data, &options).unwrap().into_bytes());
+
+        // Both raster backends apply the same padding and scale
+        let pixmap = SkiaRenderer::default().render(&data, &options).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (240, 140));
+        let legacy = ResvgRenderer::default().render(&data, &options).unwrap();
+        assert_eq!((legacy.width(), legacy.height()), (240, 140));
+
+        let jpeg = export(&SkiaRenderer { format: RasterFormat::Jpeg }, &data, &options);
+        assert!(jpeg.starts_with(&[0xff, 0xd8]));
+        let png = export(&ResvgRenderer { format: RasterFormat::Png }, &data, &options);
+        assert!(png.starts_with(b"\x89PNG"));
+        let pdf = export(&PdfRenderer, &data, &options);
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn test_errors_and_warnings() {
+        use crate::error::{ExcaliosaError, Warning};
+        use crate::loader::{load_scene, load_scene_with_warnings};
+        use crate::options::RenderOptions;
+        use crate::renderer_skia::render_to_pixmap;
+        use std::sync::mpsc;
+
+        let json = r#"{
+            "type": "excalidraw",
+            "elements": [
+                { "type": "rectangle", "id": "r1", "width": 10, "height": 10 },
+                { "type": "sticker", "id": "s1" },
+                { "type": "embeddable", "id": "e1", "width": 10, "height": 10 },
+                { "type": "image", "id": "i1", "width": 10, "height": 10, "fileId": "nope" }
+            ]
+        }"#;
+        let (data, warnings) = load_scene_with_warnings(json.as_bytes()).unwrap();
+        assert_eq!(
+            warnings,
+            [Warning::SkippedElement { index: 1, reason: "unsupported element type \"sticker\"".to_string() }]
+        );
+
+        // Render warnings go to the channel instead of stderr
+        let (sender, receiver) = mpsc::channel();
+        let options = RenderOptions {
+            warnings: Some(sender),
+            ..RenderOptions::default()
+        };
+        render_to_pixmap(&data, &options).unwrap();
+        let render_warnings: Vec<Warning> = receiver.try_iter().collect();
+        assert_eq!(
+            render_warnings,
+            [
+                Warning::UnsupportedElement { id: "e1".to_string(), element_type: ElementType::Embeddable },
+                Warning::MissingFile { element_id: "i1".to_string(), file_id: "nope".to_string() },
+            ]
+        );
+        let strict: ExcaliosaError = render_warnings[1].clone().into();
+        assert!(matches!(strict, ExcaliosaError::MissingFile { .. }));
+
+        let missing_frame = RenderOptions {
+            frame: Some("nope".to_string()),
+            ..RenderOptions::default()
+        };
+        assert!(matches!(
+            render_to_pixmap(&data, &missing_frame),
+            Err(ExcaliosaError::FrameNotFound(ref name)) if name == "nope"
+        ));
+        assert!(matches!(load_scene(b"{ not json"), Err(ExcaliosaError::Json(_))));
+        assert!(matches!(load_scene(b"[]"), Err(ExcaliosaError::Parse(_))));
+    }
+
+    #[test]
+    fn test_font_set_maps_families_to_files() {
+        use crate::error::ExcaliosaError;
+        use crate::fonts::FontProvider;
+        use crate::options::RenderOptions;
+        use crate::renderer::generate_svg_with_options;
+        use crate::renderer_skia::render_to_pixmap;
+        use std::path::Path;
+        use std::sync::Arc;
+
+        let fonts = FontSet::default();
+        assert_eq!(fonts.family_for_id(Some(4)), "Excalifont");
+        assert!(fonts.font("liberation sans").is_some(), "Families should match case-insensitively");
+        assert!(fonts.font("Corporate Sans").is_none());
+
+        // "Local font" (id 4) and Excalifont drawn with a font file, under its own family name
+        let font_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fonts/CascadiaCode.ttf");
+        let mut fonts = FontSet::new();
+        fonts.map_font_file("4", &font_path).unwrap();
+        fonts.map_font_file("Excalifont", &font_path).unwrap();
+        assert_eq!(fonts.family_for_id(Some(4)), "Cascadia Code");
+        assert_eq!(fonts.family_for_id(None), "Cascadia Code");
+        assert!(fonts.font("Excalifont").is_some());
+
+        let mut text = create_test_element("t1", ElementType::Text, 0.0, 0.0, 100.0, 25.0);
+        text.text = Some("Local".to_string());
+        text.font_family = Some(4);
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![text],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let options = RenderOptions {
+            fonts: Arc::new(fonts),
+            ..RenderOptions::default()
+        };
+        let svg = generate_svg_with_options(&data, &options).unwrap();
+        assert!(svg.contains(r#"font-family="Local Font, Cascadia Code""#));
+        assert!(render_to_pixmap(&data, &options).is_ok());
+
+        let mut fonts = FontSet::new();
+        let not_a_font = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        assert!(matches!(fonts.add_font_file(&not_a_font), Err(ExcaliosaError::Font(_))));
+    }
+
+    #[test]
+    fn test_font_collection_registers_every_font() {
+        use crate::converter::{CASCADIA_CODE, EXCALIFONT_REGULAR};
+        use crate::fonts::{FontData, FontProvider};
+        use std::sync::Arc;
+
+        // A collection (.ttc) of two fonts: the header lists each font's table directory, whose
+        // table offsets are relative to the start of the file
+        let fonts: [&[u8]; 2] = [EXCALIFONT_REGULAR, CASCADIA_CODE];
+        let header_len = 12 + 4 * fonts.len();
+        let mut ttc = b"ttcf".to_vec();
+        ttc.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        ttc.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+        let mut body = Vec::new();
+        for font in fonts {
+            let base = (header_len + body.len()) as u32;
+            ttc.extend_from_slice(&base.to_be_bytes());
+            let mut font = font.to_vec();
+            let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+            for table in 0..num_tables {
+                let at = 12 + 16 * table + 8;
+                let offset = u32::from_be_bytes(font[at..at + 4].try_into().unwrap());
+                font[at..at + 4].copy_from_slice(&(offset + base).to_be_bytes());
+            }
+            body.extend_from_slice(&font);
+            body.resize(body.len().next_multiple_of(4), 0);
+        }
+        ttc.extend_from_slice(&body);
+
+        let mut set = FontSet::new();
+        let embedded_files = set.fonts().len();
+        let families = set.add_font(Arc::new(ttc) as FontData).unwrap();
+        assert_eq!(families, ["Excalifont", "Cascadia Code"]);
+
+        // The second font is reachable, with its index in the collection
+        let cascadia = set.font("Cascadia Code").unwrap();
+        assert_eq!(cascadia.index, 1);
+        assert!(cascadia.font_ref().is_some());
+        assert!(cascadia.has_glyph('a'));
+        assert_eq!(set.font("Excalifont").unwrap().index, 0);
+        // Font databases get the collection file once
+        assert_eq!(set.fonts().len(), embedded_files + 1);
+    }
+
+    #[test]
+    fn test_font_family_registry() {
+        use crate::font_utils::{get_font_family, get_font_family_by_name};
+        use crate::fonts::FontProvider;
+
+        assert_eq!(get_font_family(Some(1)), "Virgil");
+        assert_eq!(get_font_family(Some(2)), "Helvetica");
+        assert_eq!(get_font_family(Some(6)), "Nunito");
+        assert_eq!(get_font_family(Some(9)), "Liberation Sans");
+        assert_eq!(get_font_family(None), "Excalifont");
+        assert_eq!(get_font_family(Some(42)), "Excalifont");
+        assert_eq!(get_font_family_by_name("comic shanns").map(|family| family.id), Some(8));
+
+        // Families that are not bundled are drawn with a bundled fallback
+        let fonts = FontSet::default();
+        assert_eq!(fonts.family_for_id(Some(1)), "Excalifont");
+        assert_eq!(fonts.family_for_id(Some(2)), "Liberation Sans");
+        assert_eq!(fonts.family_for_id(Some(3)), "Cascadia Code");
+        assert_eq!(fonts.family_for_id(Some(7)), "Liberation Sans");
+        assert_eq!(fonts.family_for_id(Some(8)), "Cascadia Code");
+        assert_eq!(fonts.family_for_id(Some(9)), "Liberation Sans");
+
+        // A mapped family wins over the fallback
+        let mut fonts = FontSet::new();
+        fonts.map_family("Nunito", "Cascadia Code");
+        assert_eq!(fonts.family_for_id(Some(6)), "Cascadia Code");
+
+        // SVG names the Excalidraw family first, so viewers with the real font use it
+        let text = |id: &str, font_family: i32| {
+            let mut text = create_test_element(id, ElementType::Text, 0.0, 0.0, 100.0, 25.0);
+            text.text = Some("Hi".to_string());
+            text.font_family = Some(font_family);
+            text
+        };
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![text("t1", 6), text("t2", 1), text("t3", 5)],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let svg = generate_svg(&data, None);
+        assert!(svg.contains(r#"font-family="Nunito, Liberation Sans""#));
+        assert!(svg.contains(r#"font-family="Virgil, Excalifont""#));
+        assert!(svg.contains(r#"font-family="Excalifont""#));
+    }
+
+    #[test]
+    fn test_font_fallback_for_missing_glyphs() {
+        use crate::error::Warning;
+        use crate::fonts::{resolve_text_fonts, FontProvider};
+        use crate::options::RenderOptions;
+        use crate::renderer_skia::render_to_pixmap;
+        use std::sync::{mpsc, Arc};
+
+        // Excalifont has no arrows; the embedded Liberation Sans does
+        let fonts = FontSet::new();
+        let excalifont = fonts.font_for_id(Some(5));
+        let (faces, missing) = resolve_text_fonts(&fonts, excalifont.clone(), "a → b");
+        assert_eq!(faces.len(), 2);
+        assert!(faces[1].has_glyph('→'));
+        assert!(missing.is_empty());
+
+        let (faces, missing) = resolve_text_fonts(&fonts, excalifont, "漢字 😀\n");
+        assert_eq!(faces.len(), 1);
+        assert_eq!(missing, ['漢', '字', '😀']);
+
+        // Preferred fallback families are tried first
+        let mut fonts = FontSet::new();
+        fonts.add_fallback_family("Cascadia Code");
+        let fallback = fonts.fallback_font('→').unwrap();
+        assert!(Arc::ptr_eq(&fallback.data, &fonts.font("Cascadia Code").unwrap().data));
+
+        // Characters no font has are reported
+        let mut text = create_test_element("t1", ElementType::Text, 0.0, 0.0, 100.0, 25.0);
+        text.text = Some("→ 漢".to_string());
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![text],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let (sender, receiver) = mpsc::channel();
+        let options = RenderOptions {
+            warnings: Some(sender),
+            ..RenderOptions::default()
+        };
+        render_to_pixmap(&data, &options).unwrap();
+        let warnings: Vec<Warning> = receiver.try_iter().collect();
+        assert_eq!(warnings, [Warning::MissingGlyphs { element_id: "t1".to_string(), characters: vec!['漢'] }]);
+        assert_eq!(warnings[0].to_string(), "Element t1 has characters no font can draw: U+6F22");
+    }
+
+    #[test]
+    fn test_bitmap_only_glyphs_are_missing() {
+        use crate::converter::LIBERATION_SANS_REGULAR;
+        use crate::error::Warning;
+        use crate::fonts::{FontData, FontFace, FontProvider};
+        use crate::options::RenderOptions;
+        use crate::renderer_skia::render_to_pixmap;
+        use skrifa::MetadataProvider;
+        use std::sync::{mpsc, Arc};
+
+        // Like a color emoji font: the charmap still maps every character, but the glyf and
+        // loca tables are renamed away, so no glyph has an outline. The new tags keep the
+        // table directory sorted.
+        let mut font = LIBERATION_SANS_REGULAR.to_vec();
+        let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+        for table in 0..num_tables {
+            let at = 12 + 16 * table;
+            match &font[at..at + 4] {
+                b"glyf" => font[at..at + 4].copy_from_slice(b"glyg"),
+                b"loca" => font[at..at + 4].copy_from_slice(b"locb"),
+                _ => {}
+            }
+        }
+        let font = Arc::new(font);
+        let face = FontFace::new(font.clone());
+        assert!(face.font_ref().unwrap().charmap().map('A').is_some());
+        assert!(!face.has_glyph('A'));
+
+        #[derive(Debug)]
+        struct BitmapFonts(Arc<Vec<u8>>);
+
+        impl FontProvider for BitmapFonts {
+            fn family_for_id(&self, _font_id: Option<i32>) -> String {
+                "Liberation Sans".to_string()
+            }
+            fn font(&self, _family: &str) -> Option<FontFace> {
+                Some(FontFace::new(self.0.clone()))
+            }
+            fn fonts(&self) -> Vec<FontData> {
+                vec![self.0.clone()]
+            }
+        }
+
+        // Text drawn with nothing but bitmap glyphs is reported instead of vanishing
+        let mut text = create_test_element("t1", ElementType::Text, 0.0, 0.0, 100.0, 25.0);
+        text.text = Some("Hi".to_string());
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![text],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let (sender, receiver) = mpsc::channel();
+        let options = RenderOptions {
+            warnings: Some(sender),
+            fonts: Arc::new(BitmapFonts(font)),
+            ..RenderOptions::default()
+        };
+        render_to_pixmap(&data, &options).unwrap();
+        let warnings: Vec<Warning> = receiver.try_iter().collect();
+        assert_eq!(warnings, [Warning::MissingGlyphs { element_id: "t1".to_string(), characters: vec!['H', 'i'] }]);
+    }
+
+    #[test]
+    fn test_text_warnings_match_drawn_fonts() {
+        use crate::error::Warning;
+        use crate::fonts::{resolve_family_fonts, FontProvider};
+        use crate::options::RenderOptions;
+        use crate::renderer_skia::render_to_pixmap;
+        use std::sync::{mpsc, Arc};
+
+        // A family the provider has no font for is drawn, and checked, with Excalifont
+        let mut fonts = FontSet::new();
+        fonts.map_family("5", "No Such Family");
+        assert_eq!(fonts.family_for_id(Some(5)), "No Such Family");
+        let (faces, missing) = resolve_family_fonts(&fonts, "No Such Family", "a → 漢");
+        assert!(faces[0].has_glyph('a') && !faces[0].has_glyph('→'));
+        assert_eq!(missing, ['漢']);
+
+        let mut text = create_test_element("t1", ElementType::Text, 0.0, 0.0, 100.0, 25.0);
+        text.text = Some("a → 漢".to_string());
+        text.font_family = Some(5);
+        let data = ExcalidrawData {
+            data_type: "excalidraw".to_string(),
+            version: Some(2),
+            version_nonce: None,
+            source: Some("test".to_string()),
+            elements: vec![text],
+            app_state: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let (sender, receiver) = mpsc::channel();
+        let unmapped = render_to_pixmap(&data, &RenderOptions::default()).unwrap();
+        let options = RenderOptions {
+            warnings: Some(sender),
+            fonts: Arc::new(fonts),
+            ..RenderOptions::default()
+        };
+        let mapped = render_to_pixmap(&data, &options).unwrap();
+        assert!(mapped.data() == unmapped.data(), "Text should be drawn with the fonts the warnings checked");
+        let warnings: Vec<Warning> = receiver.try_iter().collect();
+        assert_eq!(warnings, [Warning::MissingGlyphs { element_id: "t1".to_string(), characters: vec!['漢'] }]);
+    }
 }
 
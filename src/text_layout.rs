@@ -0,0 +1,197 @@
+//! Text layout shared by the SVG and tiny-skia renderers
+//! Wraps text to its available width with the fonts' advance widths, following
+//! Excalidraw's `wrapText`, and positions bound text inside its container.
+use crate::bound_text_utils::{
+    compute_bound_text_position, get_bound_text, get_bound_text_max_width, get_container, get_text_height,
+    BOUND_TEXT_PADDING,
+};
+use crate::font_utils::{get_font_data, get_line_height};
+use crate::fonts::{resolve_family_fonts, FontFace, FontProvider};
+use crate::models::{ElementType, ExcalidrawElement};
+use skrifa::instance::{LocationRef, Size};
+use skrifa::metrics::GlyphMetrics;
+use skrifa::raw::FontRef;
+use skrifa::{charmap::Charmap, MetadataProvider};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Measures text with a font, and optionally fallback fonts, at a given size
+pub struct TextMeasurer<'a> {
+    fonts: Vec<(Charmap<'a>, GlyphMetrics<'a>)>,
+}
+
+impl<'a> TextMeasurer<'a> {
+    /// Measurer for raw font bytes, or `None` if the font cannot be parsed
+    pub fn new(font_data: &'a [u8], font_size: f64) -> Option<Self> {
+        let font = FontRef::new(font_data).ok()?;
+        Self::with_fallbacks(&[font], font_size)
+    }
+
+    /// Measurer for a font followed by fallbacks for the characters it lacks, or `None`
+    /// without fonts
+    pub fn with_fallbacks(fonts: &[FontRef<'a>], font_size: f64) -> Option<Self> {
+        if fonts.is_empty() {
+            return None;
+        }
+        let fonts = fonts
+            .iter()
+            .map(|font| {
+                let glyph_metrics = font.glyph_metrics(Size::new(font_size as f32), LocationRef::default());
+                (font.charmap(), glyph_metrics)
+            })
+            .collect();
+        Some(Self { fonts })
+    }
+
+    /// Measurer for an Excalidraw font family id, with the embedded fonts
+    pub fn for_font_family(font_family: Option<i32>, font_size: f64) -> Option<TextMeasurer<'static>> {
+        TextMeasurer::new(get_font_data(font_family), font_size)
+    }
+
+    /// Advance width of a single line; each character is measured with the first font that
+    /// has it, and characters missing from all fonts take no space
+    pub fn line_width(&self, line: &str) -> f64 {
+        line.chars()
+            .filter_map(|ch| {
+                self.fonts.iter().find_map(|(charmap, glyph_metrics)| {
+                    charmap.map(ch).map(|glyph_id| glyph_metrics.advance_width(glyph_id).unwrap_or(0.0))
+                })
+            })
+            .map(|advance| advance as f64)
+            .sum()
+    }
+
+    /// Width of the widest line
+    pub fn text_width(&self, text: &str) -> f64 {
+        text.split('\n').map(|line| self.line_width(line)).fold(0.0, f64::max)
+    }
+}
+
+/// Wrap text so that no line exceeds `max_width`
+/// Lines break between words; words wider than `max_width` break between characters.
+/// Explicit newlines are kept, and trailing spaces are trimmed from wrapped lines.
+pub fn wrap_text(text: &str, measurer: &TextMeasurer, max_width: f64) -> String {
+    if !max_width.is_finite() || max_width < 0.0 {
+        return text.to_string();
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    for original_line in text.split('\n') {
+        if measurer.line_width(original_line) <= max_width {
+            lines.push(original_line.to_string());
+            continue;
+        }
+
+        let mut current = String::new();
+        for word in original_line.split(' ') {
+            if measurer.line_width(word) > max_width {
+                // The word alone is too wide: flush the current line and break it by characters
+                if !current.trim_end().is_empty() {
+                    lines.push(current.trim_end().to_string());
+                }
+                current.clear();
+                for ch in word.chars() {
+                    let mut candidate = current.clone();
+                    candidate.push(ch);
+                    if !current.is_empty() && measurer.line_width(&candidate) > max_width {
+                        lines.push(std::mem::take(&mut current));
+                        current.push(ch);
+                    } else {
+                        current = candidate;
+                    }
+                }
+            } else {
+                let candidate = format!("{current}{word}");
+                if !current.trim_end().is_empty() && measurer.line_width(&candidate) > max_width {
+                    lines.push(current.trim_end().to_string());
+                    current = word.to_string();
+                } else {
+                    current = candidate;
+                }
+            }
+            current.push(' ');
+        }
+
+        let rest = current.trim_end();
+        if !rest.is_empty() {
+            lines.push(rest.to_string());
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Width a text element wraps to: its container's text box when bound, or its own
+/// width when `autoResize` is off. `None` means the text is not wrapped.
+pub fn get_wrap_width(element: &ExcalidrawElement, container: Option<&ExcalidrawElement>) -> Option<f64> {
+    let font_size = element.font_size.unwrap_or(20.0);
+    match container {
+        Some(container) => Some(get_bound_text_max_width(container, font_size)),
+        None if element.auto_resize == Some(false) => Some(element.width),
+        None => None,
+    }
+}
+
+/// Text element as it should be drawn: wrapped to its available width and, when bound,
+/// positioned inside its container. Other elements are returned unchanged.
+pub fn layout_text_element<'a>(
+    element: &'a ExcalidrawElement,
+    elements_by_id: &HashMap<&str, &ExcalidrawElement>,
+    fonts: &dyn FontProvider,
+) -> Cow<'a, ExcalidrawElement> {
+    if element.element_type != ElementType::Text {
+        return Cow::Borrowed(element);
+    }
+    let container = get_container(element, elements_by_id);
+    let wrap_width = get_wrap_width(element, container);
+    if container.is_none() && wrap_width.is_none() {
+        return Cow::Borrowed(element);
+    }
+
+    let mut laid_out = element.clone();
+    let font_size = element.font_size.unwrap_or(20.0);
+
+    if let Some(max_width) = wrap_width {
+        let source = element.original_text.as_deref().or(element.text.as_deref()).unwrap_or("");
+        let (text_fonts, _) = resolve_family_fonts(fonts, &fonts.family_for_id(element.font_family), source);
+        let font_refs: Vec<FontRef> = text_fonts.iter().filter_map(FontFace::font_ref).collect();
+        if let Some(measurer) = TextMeasurer::with_fallbacks(&font_refs, font_size) {
+            let wrapped = wrap_text(source, &measurer, max_width);
+            let line_count = wrapped.split('\n').count();
+            laid_out.width = measurer.text_width(&wrapped);
+            laid_out.height = line_count as f64 * get_line_height(font_size, element.line_height);
+            laid_out.text = Some(wrapped);
+        }
+    }
+
+    if let Some(container) = container {
+        let text_height = get_text_height(&laid_out);
+        let (x, y) = compute_bound_text_position(container, &laid_out, laid_out.width, text_height);
+        laid_out.x = x;
+        laid_out.y = y;
+        laid_out.height = text_height;
+    }
+
+    Cow::Owned(laid_out)
+}
+
+/// Area masked out of an arrow's stroke behind its label, as (x, y, width, height):
+/// the laid-out label grown by the bound text padding, like Excalidraw
+pub fn get_arrow_label_mask_box(
+    arrow: &ExcalidrawElement,
+    elements_by_id: &HashMap<&str, &ExcalidrawElement>,
+    fonts: &dyn FontProvider,
+) -> Option<(f64, f64, f64, f64)> {
+    if arrow.element_type != ElementType::Arrow {
+        return None;
+    }
+    let label = get_bound_text(arrow, elements_by_id)?;
+    let label = layout_text_element(label, elements_by_id, fonts);
+    Some((
+        label.x - BOUND_TEXT_PADDING,
+        label.y - BOUND_TEXT_PADDING,
+        label.width + BOUND_TEXT_PADDING * 2.0,
+        label.height + BOUND_TEXT_PADDING * 2.0,
+    ))
+}
+
@@ -1,26 +1,37 @@
-use anyhow::Result;
 use std::path::Path;
 use tiny_skia::Pixmap;
-use crate::models::{ExcalidrawElement, ViewBox};
+use crate::arrow_utils::rotate_point;
+use crate::frame_utils::{find_frame, get_frame_children, get_frame_viewbox};
+use crate::embed_utils::{png_scene_text, EXCALIDRAW_MIME};
+use crate::error::{ExcaliosaError, Result};
+use crate::models::{ElementType, ExcalidrawData, ExcalidrawElement, ViewBox};
 
 /// Save a pixmap to PNG with compression quality control (0-100).
-/// Maps 0-100 to PNG compression types:
-/// - 0-25: Fast (fastest encoding, larger files)
-/// - 26-75: Default (balanced)
-/// - 76-100: Best (slowest encoding, smallest files)
+/// See [`encode_png_with_quality`] for how quality maps to compression and scenes are embedded.
 pub fn save_png_with_quality(
     pixmap: &Pixmap,
     output_path: &Path,
     quality: u8,
+    embed_scene: Option<&ExcalidrawData>,
 ) -> Result<()> {
-    use std::io::BufWriter;
-    use std::fs::File;
+    let bytes = encode_png_with_quality(pixmap, quality, embed_scene)?;
+    std::fs::write(output_path, bytes)
+        .map_err(|source| ExcaliosaError::Write { path: output_path.to_path_buf(), source })?;
     
-    let file = File::create(output_path)
-        .map_err(|e| anyhow::anyhow!("Failed to create PNG file: {e}"))?;
-    let writer = BufWriter::new(file);
+    Ok(())
+}
+
+/// Encode a pixmap to PNG with compression quality control (0-100).
+/// Maps 0-100 to PNG compression types:
+/// - 0-25: Fast (fastest encoding, larger files)
+/// - 26-75: Default (balanced)
+/// - 76-100: Best (slowest encoding, smallest files)
+///
+/// With `embed_scene`, the scene is stored in a `tEXt` chunk the way Excalidraw does.
+pub fn encode_png_with_quality(pixmap: &Pixmap, quality: u8, embed_scene: Option<&ExcalidrawData>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
     
-    let mut encoder = png::Encoder::new(writer, pixmap.width(), pixmap.height());
+    let mut encoder = png::Encoder::new(&mut bytes, pixmap.width(), pixmap.height());
     encoder.set_color(png::ColorType::Rgba);
     encoder.set_depth(png::BitDepth::Eight);
     encoder.set_filter(png::FilterType::Paeth);
@@ -35,21 +46,38 @@ pub fn save_png_with_quality(
     };
     encoder.set_compression(compression_type);
     
+    if let Some(data) = embed_scene {
+        encoder.add_text_chunk(EXCALIDRAW_MIME.to_string(), png_scene_text(data)?)
+            .map_err(png_error)?;
+    }
+    
     let mut writer = encoder.write_header()
-        .map_err(|e| anyhow::anyhow!("Failed to write PNG header: {e}"))?;
+        .map_err(png_error)?;
     
     // Write RGBA data
     let data = pixmap.data();
     writer.write_image_data(data)
-        .map_err(|e| anyhow::anyhow!("Failed to write PNG data: {e}"))?;
+        .map_err(png_error)?;
+    writer.finish()
+        .map_err(png_error)?;
     
-    Ok(())
+    Ok(bytes)
 }
 
+fn png_error(e: png::EncodingError) -> ExcaliosaError {
+    ExcaliosaError::Encode { format: "PNG", message: e.to_string() }
+}
+
+/// Padding around the scene when neither the options nor `appState.exportPadding` set one
+pub const DEFAULT_EXPORT_PADDING: f64 = 40.0;
+
 /// Calculate the viewbox that encompasses all non-deleted elements
 pub fn calculate_viewbox(elements: &[ExcalidrawElement]) -> ViewBox {
-    const PADDING: f64 = 40.0;
+    calculate_viewbox_with_padding(elements, DEFAULT_EXPORT_PADDING)
+}
 
+/// Calculate the viewbox that encompasses all non-deleted elements, plus `padding` on each side
+pub fn calculate_viewbox_with_padding(elements: &[ExcalidrawElement], padding: f64) -> ViewBox {
     if elements.is_empty() {
         return ViewBox {
             min_x: 0.0,
@@ -66,18 +94,99 @@ pub fn calculate_viewbox(elements: &[ExcalidrawElement]) -> ViewBox {
 
     for el in elements {
         if !el.is_deleted {
-            min_x = min_x.min(el.x);
-            min_y = min_y.min(el.y);
-            max_x = max_x.max(el.x + el.width);
-            max_y = max_y.max(el.y + el.height);
+            let (x1, y1, x2, y2) = get_rotated_element_bounds(el);
+            min_x = min_x.min(x1);
+            min_y = min_y.min(y1);
+            max_x = max_x.max(x2);
+            max_y = max_y.max(y2);
         }
     }
 
     ViewBox {
-        min_x: min_x - PADDING,
-        min_y: min_y - PADDING,
-        width: max_x - min_x + PADDING * 2.0,
-        height: max_y - min_y + PADDING * 2.0,
+        min_x: min_x - padding,
+        min_y: min_y - padding,
+        width: max_x - min_x + padding * 2.0,
+        height: max_y - min_y + padding * 2.0,
+    }
+}
+
+
+/// Unrotated bounds of an element as (min_x, min_y, max_x, max_y)
+/// Linear and freedraw elements use their points, which may extend left of or above x/y
+pub fn get_element_bounds(el: &ExcalidrawElement) -> (f64, f64, f64, f64) {
+    let is_point_based = matches!(el.element_type, ElementType::Line | ElementType::Arrow | ElementType::Freedraw);
+    match el.points {
+        Some(ref points) if is_point_based && !points.is_empty() => {
+            let mut min_x = f64::INFINITY;
+            let mut min_y = f64::INFINITY;
+            let mut max_x = f64::NEG_INFINITY;
+            let mut max_y = f64::NEG_INFINITY;
+            for (px, py) in points {
+                min_x = min_x.min(*px);
+                min_y = min_y.min(*py);
+                max_x = max_x.max(*px);
+                max_y = max_y.max(*py);
+            }
+            (el.x + min_x, el.y + min_y, el.x + max_x, el.y + max_y)
+        }
+        _ => (el.x, el.y, el.x + el.width, el.y + el.height),
     }
 }
 
+/// Rotation center of an element: the center of its unrotated bounds, as in Excalidraw
+pub fn get_element_center(el: &ExcalidrawElement) -> (f64, f64) {
+    let (x1, y1, x2, y2) = get_element_bounds(el);
+    ((x1 + x2) / 2.0, (y1 + y2) / 2.0)
+}
+
+/// Axis-aligned bounds of an element after rotating it by its `angle` (radians)
+pub fn get_rotated_element_bounds(el: &ExcalidrawElement) -> (f64, f64, f64, f64) {
+    let (x1, y1, x2, y2) = get_element_bounds(el);
+    if el.angle == 0.0 {
+        return (x1, y1, x2, y2);
+    }
+
+    let (cx, cy) = get_element_center(el);
+    let corners = [(x1, y1), (x2, y1), (x2, y2), (x1, y2)];
+    let mut min_x = f64::INFINITY;
+    let mut min_y = f64::INFINITY;
+    let mut max_x = f64::NEG_INFINITY;
+    let mut max_y = f64::NEG_INFINITY;
+    for (px, py) in corners {
+        let (rx, ry) = rotate_point(px, py, cx, cy, el.angle);
+        min_x = min_x.min(rx);
+        min_y = min_y.min(ry);
+        max_x = max_x.max(rx);
+        max_y = max_y.max(ry);
+    }
+    (min_x, min_y, max_x, max_y)
+}
+
+/// Viewbox and elements to export: the whole scene with `padding` around it, or a single
+/// frame (exactly its bounds) when `frame` is set
+/// A `region` replaces the viewbox, without padding; the elements stay those of the scene
+/// or frame.
+pub fn resolve_export_scope<'a>(
+    data: &'a ExcalidrawData,
+    frame: Option<&str>,
+    region: Option<ViewBox>,
+    padding: f64,
+) -> Result<(ViewBox, Vec<&'a ExcalidrawElement>)> {
+    if let Some(region) = region
+        && !(region.width > 0.0 && region.height > 0.0)
+    {
+        return Err(ExcaliosaError::EmptyRegion { width: region.width, height: region.height });
+    }
+    let (viewbox, elements) = match frame {
+        Some(name_or_id) => {
+            let frame = find_frame(&data.elements, name_or_id)
+                .ok_or_else(|| ExcaliosaError::FrameNotFound(name_or_id.to_string()))?;
+            (get_frame_viewbox(frame), get_frame_children(&data.elements, frame))
+        }
+        None => (
+            calculate_viewbox_with_padding(&data.elements, padding),
+            data.elements.iter().filter(|el| !el.is_deleted).collect(),
+        ),
+    };
+    Ok((region.unwrap_or(viewbox), elements))
+}